@@ -23,6 +23,7 @@ use dragonfly_client_util::{
     http::basic_auth,
     tls::{generate_ca_cert_from_pem, generate_cert_from_pem},
 };
+use ipnetwork::IpNetwork;
 use local_ip_address::{local_ip, local_ipv6};
 use rcgen::Certificate;
 use regex::Regex;
@@ -30,7 +31,7 @@ use rustls_pki_types::CertificateDer;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 use std::time::Duration;
 use tokio::fs;
@@ -206,6 +207,281 @@ fn default_storage_server_quic_port() -> u16 {
     4006
 }
 
+/// default_storage_server_quic_shutdown_grace_period is the default grace period to wait for the
+/// in-flight streams of the storage quic server to finish when shutting down.
+#[inline]
+fn default_storage_server_quic_shutdown_grace_period() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// default_storage_server_quic_drain_timeout is the default timeout to wait for the in-flight
+/// streams of the storage quic server to finish when draining.
+#[inline]
+fn default_storage_server_quic_drain_timeout() -> Duration {
+    Duration::from_secs(300)
+}
+
+/// default_storage_server_quic_cert_reload_interval is the default interval to check whether
+/// the certificates of the storage quic server are rotated.
+#[inline]
+fn default_storage_server_quic_cert_reload_interval() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// default_storage_server_quic_max_concurrent_connections is the default maximum number of
+/// concurrent connections of the storage quic server.
+#[inline]
+fn default_storage_server_quic_max_concurrent_connections() -> usize {
+    1000
+}
+
+/// default_storage_server_quic_request_timeout is the default timeout of the storage quic server
+/// to read the request and write each chunk of the response.
+#[inline]
+fn default_storage_server_quic_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// default_storage_server_quic_max_request_size is the default maximum size of the request
+/// message accepted by the storage quic server.
+#[inline]
+fn default_storage_server_quic_max_request_size() -> ByteSize {
+    ByteSize::mib(4)
+}
+
+/// default_storage_server_quic_max_buffered_request_size is the default maximum size of the
+/// requests buffered by all streams of the storage quic server.
+#[inline]
+fn default_storage_server_quic_max_buffered_request_size() -> ByteSize {
+    ByteSize::mib(256)
+}
+
+/// default_storage_server_quic_write_buffer_size is the default size of the buffer to read the
+/// piece content from the storage for each quic stream.
+#[inline]
+fn default_storage_server_quic_write_buffer_size() -> ByteSize {
+    ByteSize::kib(64)
+}
+
+/// default_storage_server_quic_max_concurrent_streams_per_connection is the default maximum number
+/// of concurrent streams per connection of the storage quic server.
+#[inline]
+fn default_storage_server_quic_max_concurrent_streams_per_connection() -> u32 {
+    100
+}
+
+/// default_storage_server_quic_verify_digest is the default value of whether to verify the
+/// digest of the piece before serving it by the storage quic server.
+#[inline]
+fn default_storage_server_quic_verify_digest() -> bool {
+    true
+}
+
+/// default_storage_server_quic_peer_rate_limit_burst is the default burst of the requests per
+/// peer of the storage quic server.
+#[inline]
+fn default_storage_server_quic_peer_rate_limit_burst() -> u32 {
+    100
+}
+
+/// default_storage_server_quic_peer_ban_window is the default sliding window to count the
+/// protocol violations of each peer of the storage quic server.
+#[inline]
+fn default_storage_server_quic_peer_ban_window() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// default_storage_server_quic_peer_ban_duration is the default duration to refuse the
+/// connections of the banned peer of the storage quic server.
+#[inline]
+fn default_storage_server_quic_peer_ban_duration() -> Duration {
+    Duration::from_secs(600)
+}
+
+/// default_storage_server_quic_workers is the default number of the endpoints bound to each
+/// listen address of the storage quic server.
+#[inline]
+fn default_storage_server_quic_workers() -> usize {
+    1
+}
+
+/// default_storage_server_quic_max_concurrent_dispatches is the default maximum number of the
+/// streams dispatched to the handlers concurrently by the storage quic server.
+#[inline]
+fn default_storage_server_quic_max_concurrent_dispatches() -> usize {
+    512
+}
+
+/// default_storage_server_quic_dispatch_queue_depth is the default maximum number of the streams
+/// waiting to be dispatched by the storage quic server.
+#[inline]
+fn default_storage_server_quic_dispatch_queue_depth() -> usize {
+    1024
+}
+
+/// default_storage_server_quic_storage_worker_queue_depth is the default maximum number of the
+/// pieces waiting for the storage workers of the storage quic server.
+#[inline]
+fn default_storage_server_quic_storage_worker_queue_depth() -> usize {
+    1024
+}
+
+/// default_storage_server_quic_connection_idle_timeout is the default timeout to close the idle
+/// connections of the storage quic server.
+#[inline]
+fn default_storage_server_quic_connection_idle_timeout() -> Duration {
+    Duration::from_secs(600)
+}
+
+/// default_storage_server_quic_rebind_max_retries is the default maximum number of the retries
+/// to rebind the closed endpoint of the storage quic server.
+#[inline]
+fn default_storage_server_quic_rebind_max_retries() -> u32 {
+    10
+}
+
+/// default_download_quic_connect_timeout is the default timeout of the quic client to establish
+/// the connection to the parent.
+#[inline]
+fn default_download_quic_connect_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+/// default_download_quic_request_timeout is the default timeout of the quic client to wait for
+/// the progress of the response from the parent.
+#[inline]
+fn default_download_quic_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// default_download_quic_max_concurrent_streams is the default maximum number of the concurrent
+/// streams of the quic client to a parent, it matches the default limit of the quic server.
+#[inline]
+fn default_download_quic_max_concurrent_streams() -> u32 {
+    100
+}
+
+/// default_download_quic_retry_max_attempts is the default maximum number of the attempts of a
+/// request to the parent, including the first attempt.
+#[inline]
+fn default_download_quic_retry_max_attempts() -> u32 {
+    3
+}
+
+/// default_download_quic_retry_initial_backoff is the default backoff before the first retry.
+#[inline]
+fn default_download_quic_retry_initial_backoff() -> Duration {
+    Duration::from_millis(100)
+}
+
+/// default_download_quic_retry_max_backoff is the default maximum backoff between the retries.
+#[inline]
+fn default_download_quic_retry_max_backoff() -> Duration {
+    Duration::from_secs(2)
+}
+
+/// default_download_quic_circuit_breaker_failure_threshold is the default number of the
+/// consecutive failures to open the circuit to the parent.
+#[inline]
+fn default_download_quic_circuit_breaker_failure_threshold() -> u32 {
+    5
+}
+
+/// default_download_quic_circuit_breaker_cooldown is the default duration the circuit to the
+/// parent stays open before the probe request.
+#[inline]
+fn default_download_quic_circuit_breaker_cooldown() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// default_download_quic_fallback_connect_timeout is the default timeout to establish the
+/// connection to the parent before falling back to the gRPC protocol.
+#[inline]
+fn default_download_quic_fallback_connect_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+/// default_download_quic_fallback_ttl is the default duration to remember the parent which
+/// doesn't serve quic.
+#[inline]
+fn default_download_quic_fallback_ttl() -> Duration {
+    Duration::from_secs(600)
+}
+
+/// default_quic_max_piece_size is the default maximum size of the piece served and downloaded
+/// over quic.
+#[inline]
+fn default_quic_max_piece_size() -> ByteSize {
+    // The pieces of the fixed piece length may be larger than the maximum piece length
+    // calculated by the content length, so the default leaves the room for them.
+    ByteSize::gib(1)
+}
+
+/// default_quic_transport_window is the default size of the send and receive windows of the
+/// quic connection and stream.
+#[inline]
+fn default_quic_transport_window() -> ByteSize {
+    ByteSize::mib(16)
+}
+
+/// default_quic_transport_max_idle_timeout is the default maximum idle timeout of the quic
+/// connection. The live peers keep the connection active with the keep-alive packets, so the
+/// dead peers are detected within the idle timeout.
+#[inline]
+fn default_quic_transport_max_idle_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// default_quic_transport_keep_alive_interval is the default interval for sending the keep-alive
+/// packets of the quic connection.
+#[inline]
+fn default_quic_transport_keep_alive_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// default_quic_transport_max_concurrent_uni_streams is the default maximum number of the
+/// concurrent unidirectional streams opened by the remote peer.
+#[inline]
+fn default_quic_transport_max_concurrent_uni_streams() -> u32 {
+    100
+}
+
+/// default_quic_transport_initial_rtt is the default initial round trip time estimate of the
+/// quic connection before the first sample is taken.
+#[inline]
+fn default_quic_transport_initial_rtt() -> Duration {
+    Duration::from_millis(333)
+}
+
+/// default_quic_transport_datagram_receive_buffer_size is the default size of the buffer for
+/// the received datagrams of the quic connection.
+#[inline]
+fn default_quic_transport_datagram_receive_buffer_size() -> ByteSize {
+    ByteSize::kib(64)
+}
+
+/// default_quic_transport_mtu_discovery is the default of whether to discover the path mtu of
+/// the quic connection.
+#[inline]
+fn default_quic_transport_mtu_discovery() -> bool {
+    true
+}
+
+/// default_quic_transport_mtu_discovery_upper_bound is the default upper bound of the udp
+/// payload size searched by the path mtu discovery, it fits the paths with the ethernet mtu
+/// and the common tunnel overheads.
+#[inline]
+fn default_quic_transport_mtu_discovery_upper_bound() -> u16 {
+    1452
+}
+
+/// default_quic_transport_segmentation_offload is the default of whether to offload the udp
+/// segmentation to the kernel or the nic.
+#[inline]
+fn default_quic_transport_segmentation_offload() -> bool {
+    true
+}
+
 /// default_storage_keep is the default keep of the task's metadata and content when the dfdaemon restarts.
 #[inline]
 fn default_storage_keep() -> bool {
@@ -517,6 +793,9 @@ pub struct Download {
     #[serde(default = "default_download_concurrent_piece_count")]
     #[validate(range(min = 1))]
     pub concurrent_piece_count: u32,
+
+    /// quic is the quic client configuration for downloading pieces from the parents.
+    pub quic: DownloadQUIC,
 }
 
 /// Download implements Default.
@@ -530,6 +809,389 @@ impl Default for Download {
             piece_timeout: default_download_piece_timeout(),
             collected_piece_timeout: default_collected_download_piece_timeout(),
             concurrent_piece_count: default_download_concurrent_piece_count(),
+            quic: DownloadQUIC::default(),
+        }
+    }
+}
+
+/// CongestionControl is the congestion control algorithm of the quic connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum CongestionControl {
+    /// Cubic is the cubic congestion control algorithm.
+    #[serde(rename = "cubic")]
+    Cubic,
+
+    /// NewReno is the new reno congestion control algorithm.
+    #[serde(rename = "newreno")]
+    NewReno,
+
+    /// Bbr is the bbr congestion control algorithm, it performs better than the loss-based
+    /// algorithms on the lossy links between the data centers.
+    #[default]
+    #[serde(rename = "bbr")]
+    Bbr,
+}
+
+/// CongestionControl implements Display.
+impl fmt::Display for CongestionControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CongestionControl::Cubic => write!(f, "cubic"),
+            CongestionControl::NewReno => write!(f, "newreno"),
+            CongestionControl::Bbr => write!(f, "bbr"),
+        }
+    }
+}
+
+/// QUICTransport is the transport configuration of the quic connection, it is shared by the
+/// quic server of the storage and the quic client for downloading pieces.
+#[derive(Debug, Clone, Validate, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QUICTransport {
+    /// send_window is the maximum number of the bytes to transmit to the peer without
+    /// acknowledgment.
+    #[serde(with = "bytesize_serde", default = "default_quic_transport_window")]
+    pub send_window: ByteSize,
+
+    /// receive_window is the maximum number of the bytes the peer may transmit across all
+    /// streams of a connection before becoming blocked.
+    #[serde(with = "bytesize_serde", default = "default_quic_transport_window")]
+    pub receive_window: ByteSize,
+
+    /// stream_receive_window is the maximum number of the bytes the peer may transmit on a
+    /// single stream before becoming blocked.
+    #[serde(with = "bytesize_serde", default = "default_quic_transport_window")]
+    pub stream_receive_window: ByteSize,
+
+    /// max_idle_timeout is the maximum duration of inactivity to accept before timing out the
+    /// connection.
+    #[serde(
+        default = "default_quic_transport_max_idle_timeout",
+        with = "humantime_serde"
+    )]
+    pub max_idle_timeout: Duration,
+
+    /// keep_alive_interval is the interval for sending the keep-alive packets to prevent the
+    /// connection from timing out, it should be shorter than max_idle_timeout. The keep-alive
+    /// is disabled if it is 0.
+    #[serde(
+        default = "default_quic_transport_keep_alive_interval",
+        with = "humantime_serde"
+    )]
+    pub keep_alive_interval: Duration,
+
+    /// max_concurrent_uni_streams is the maximum number of the concurrent unidirectional streams
+    /// the peer may open.
+    #[serde(default = "default_quic_transport_max_concurrent_uni_streams")]
+    pub max_concurrent_uni_streams: u32,
+
+    /// initial_rtt is the initial round trip time estimate before the first sample is taken.
+    #[serde(
+        default = "default_quic_transport_initial_rtt",
+        with = "humantime_serde"
+    )]
+    pub initial_rtt: Duration,
+
+    /// congestion_control is the congestion control algorithm, the supported values are
+    /// cubic, newreno and bbr.
+    pub congestion_control: CongestionControl,
+
+    /// datagram_receive_buffer_size is the size of the buffer for the received datagrams, which
+    /// carry the pings to check the liveness and sample the round trip time of the connection.
+    /// The datagrams are disabled if it is zero.
+    #[serde(
+        with = "bytesize_serde",
+        default = "default_quic_transport_datagram_receive_buffer_size"
+    )]
+    pub datagram_receive_buffer_size: ByteSize,
+
+    /// mtu_discovery indicates whether to discover the path mtu of the connection, the
+    /// connection starts with the 1200 bytes datagrams and probes the larger ones, and falls
+    /// back when the probes are blackholed.
+    #[serde(default = "default_quic_transport_mtu_discovery")]
+    pub mtu_discovery: bool,
+
+    /// mtu_discovery_upper_bound is the upper bound of the udp payload size searched by the
+    /// path mtu discovery, it can be raised to use the jumbo frames, such as 8952 for the
+    /// fabric with the 9000 bytes mtu. The path mtu is also bounded by the peer's upper bound.
+    #[validate(range(min = 1200, max = 65527))]
+    #[serde(default = "default_quic_transport_mtu_discovery_upper_bound")]
+    pub mtu_discovery_upper_bound: u16,
+
+    /// segmentation_offload indicates whether to send the batches of the datagrams with the
+    /// udp generic segmentation offload if the platform supports it. The generic receive
+    /// offload is always used if the platform supports it.
+    #[serde(default = "default_quic_transport_segmentation_offload")]
+    pub segmentation_offload: bool,
+}
+
+/// QUICTransport implements Default.
+impl Default for QUICTransport {
+    fn default() -> Self {
+        QUICTransport {
+            send_window: default_quic_transport_window(),
+            receive_window: default_quic_transport_window(),
+            stream_receive_window: default_quic_transport_window(),
+            max_idle_timeout: default_quic_transport_max_idle_timeout(),
+            keep_alive_interval: default_quic_transport_keep_alive_interval(),
+            max_concurrent_uni_streams: default_quic_transport_max_concurrent_uni_streams(),
+            initial_rtt: default_quic_transport_initial_rtt(),
+            congestion_control: CongestionControl::default(),
+            datagram_receive_buffer_size: default_quic_transport_datagram_receive_buffer_size(),
+            mtu_discovery: default_quic_transport_mtu_discovery(),
+            mtu_discovery_upper_bound: default_quic_transport_mtu_discovery_upper_bound(),
+            segmentation_offload: default_quic_transport_segmentation_offload(),
+        }
+    }
+}
+
+/// QUICVerifyMode is the mode to verify the certificate of the parent's quic server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum QUICVerifyMode {
+    /// Full verifies the certificate chain by the trusted CA certificates and the server name.
+    #[default]
+    #[serde(rename = "full")]
+    Full,
+
+    /// Skip skips the verification of the certificate, it is insecure and only for the
+    /// testing.
+    #[serde(rename = "skip")]
+    Skip,
+}
+
+/// DownloadQUIC is the quic client configuration for downloading pieces from the parents.
+#[derive(Debug, Clone, Validate, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DownloadQUIC {
+    /// ca_cert is the CA bundle path with PEM format, and the certificates of the parents'
+    /// quic servers are verified by the CA certificates in the bundle. It is required by the
+    /// full verification unless the native roots are enabled, so the parents should serve the
+    /// certificates signed by the CA with the storage.server.quic.cert and key.
+    pub ca_cert: Option<PathBuf>,
+
+    /// verify_mode is the mode to verify the certificates of the parents' quic servers, the
+    /// supported values are full and skip, and the default is full. The quic client fails to
+    /// be created if the full verification has no trusted CA certificate.
+    pub verify_mode: QUICVerifyMode,
+
+    /// enable_native_roots indicates whether to trust the root certificates of the platform
+    /// in addition to the CA bundle.
+    pub enable_native_roots: bool,
+
+    /// server_name is the server name for verifying the certificates of the parents' quic
+    /// servers and SNI. The host of the parent's address is used if it is not set, so the
+    /// certificate of the parent addressed by the ip must contain the ip address.
+    pub server_name: Option<String>,
+
+    /// cert is the client cert path with PEM format for the quic client, and it is used for
+    /// mutual TLS when the parent's quic server requires the client authentication.
+    pub cert: Option<PathBuf>,
+
+    /// key is the client key path with PEM format for the quic client, and it is used for
+    /// mutual TLS.
+    pub key: Option<PathBuf>,
+
+    /// enable_zero_rtt indicates whether to send the requests as the 0-RTT early data when the
+    /// TLS session to the parent is resumed. The download requests are read-only, so they are
+    /// safe to be replayed.
+    pub enable_zero_rtt: bool,
+
+    /// max_piece_size is the maximum length of the piece downloaded from the parents, the
+    /// response of the larger piece is rejected before its content is read.
+    #[serde(with = "bytesize_serde", default = "default_quic_max_piece_size")]
+    pub max_piece_size: ByteSize,
+
+    /// connect_timeout is the timeout to establish the connection to the parent, including the
+    /// handshake.
+    #[serde(
+        default = "default_download_quic_connect_timeout",
+        with = "humantime_serde"
+    )]
+    pub connect_timeout: Duration,
+
+    /// request_timeout is the timeout to wait for the progress of the response after the request
+    /// is sent. It is reset whenever the bytes of the response are received, so the long piece
+    /// transfers which are still making progress are not interrupted.
+    #[serde(
+        default = "default_download_quic_request_timeout",
+        with = "humantime_serde"
+    )]
+    pub request_timeout: Duration,
+
+    /// max_concurrent_streams is the maximum number of the concurrent requests to a parent, each
+    /// request holds a stream until its response is fully read. The requests beyond the limit
+    /// wait for the in-flight requests unless fail_fast_when_busy is enabled, and it is unlimited
+    /// if it is 0.
+    #[serde(default = "default_download_quic_max_concurrent_streams")]
+    pub max_concurrent_streams: u32,
+
+    /// fail_fast_when_busy indicates whether to fail the requests beyond max_concurrent_streams
+    /// with the busy error instead of waiting, so the caller can choose another parent.
+    pub fail_fast_when_busy: bool,
+
+    /// verify_digest indicates whether to verify the digest of the downloaded pieces while they
+    /// are read, so the corrupt pieces from the buggy or malicious parents are not stored and
+    /// served to the others. It can be disabled for the trusted local setups.
+    pub verify_digest: bool,
+
+    /// enable_peer_metrics indicates whether to label the metrics of the quic client with the
+    /// address of the parent. The number of the series grows with the number of the parents, so
+    /// the metrics are aggregated over the parents by default.
+    pub enable_peer_metrics: bool,
+
+    /// cipher_suites is the list of the TLS 1.3 cipher suites offered by the quic client, such as
+    /// TLS13_AES_256_GCM_SHA384. QUIC requires TLS 1.3, and all TLS 1.3 cipher suites are
+    /// offered if it is empty.
+    pub cipher_suites: Vec<String>,
+
+    /// retry is the retry policy of the requests to the parent.
+    pub retry: QUICRetry,
+
+    /// circuit_breaker is the circuit breaker of the requests to the parents.
+    pub circuit_breaker: QUICCircuitBreaker,
+
+    /// fallback is the fallback to the gRPC protocol for the parents which don't serve quic.
+    pub fallback: QUICFallback,
+
+    /// transport is the transport configuration of the quic client.
+    pub transport: QUICTransport,
+}
+
+/// DownloadQUIC implements Default.
+impl Default for DownloadQUIC {
+    fn default() -> Self {
+        DownloadQUIC {
+            ca_cert: None,
+            verify_mode: QUICVerifyMode::default(),
+            enable_native_roots: false,
+            server_name: None,
+            cert: None,
+            key: None,
+            enable_zero_rtt: false,
+            max_piece_size: default_quic_max_piece_size(),
+            connect_timeout: default_download_quic_connect_timeout(),
+            request_timeout: default_download_quic_request_timeout(),
+            max_concurrent_streams: default_download_quic_max_concurrent_streams(),
+            fail_fast_when_busy: false,
+            verify_digest: true,
+            enable_peer_metrics: false,
+            cipher_suites: Vec::new(),
+            retry: QUICRetry::default(),
+            circuit_breaker: QUICCircuitBreaker::default(),
+            fallback: QUICFallback::default(),
+            transport: QUICTransport::default(),
+        }
+    }
+}
+
+/// QUICRetry is the retry policy of the quic client. Only the transient failures are retried,
+/// such as the lost connection, the timeout and the overloaded parent, and the missing piece and
+/// the other errors responded by the parent are returned immediately.
+#[derive(Debug, Clone, Validate, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QUICRetry {
+    /// max_attempts is the maximum number of the attempts of a request, including the first
+    /// attempt. The request is not retried if it is 1.
+    #[serde(default = "default_download_quic_retry_max_attempts")]
+    #[validate(range(min = 1))]
+    pub max_attempts: u32,
+
+    /// initial_backoff is the backoff before the first retry, and it is doubled for each of the
+    /// following retries.
+    #[serde(
+        default = "default_download_quic_retry_initial_backoff",
+        with = "humantime_serde"
+    )]
+    pub initial_backoff: Duration,
+
+    /// max_backoff is the maximum backoff between the retries.
+    #[serde(
+        default = "default_download_quic_retry_max_backoff",
+        with = "humantime_serde"
+    )]
+    pub max_backoff: Duration,
+
+    /// jitter indicates whether to randomize the backoff, so the clients failed by the same
+    /// parent don't retry in lockstep.
+    pub jitter: bool,
+}
+
+/// QUICRetry implements Default.
+impl Default for QUICRetry {
+    fn default() -> Self {
+        QUICRetry {
+            max_attempts: default_download_quic_retry_max_attempts(),
+            initial_backoff: default_download_quic_retry_initial_backoff(),
+            max_backoff: default_download_quic_retry_max_backoff(),
+            jitter: true,
+        }
+    }
+}
+
+/// QUICCircuitBreaker is the circuit breaker of the quic client for each parent. The circuit is
+/// opened after the consecutive transient failures of the requests to the parent, such as the
+/// timeout and the lost connection, and the requests to the parent fail immediately until the
+/// cooldown elapses. Then a probe request decides whether to close the circuit again.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QUICCircuitBreaker {
+    /// failure_threshold is the number of the consecutive failures to open the circuit to the
+    /// parent, and the circuit breaker is disabled if it is 0.
+    #[serde(default = "default_download_quic_circuit_breaker_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// cooldown is the duration the circuit to the parent stays open before the probe request.
+    #[serde(
+        default = "default_download_quic_circuit_breaker_cooldown",
+        with = "humantime_serde"
+    )]
+    pub cooldown: Duration,
+}
+
+/// QUICCircuitBreaker implements Default.
+impl Default for QUICCircuitBreaker {
+    fn default() -> Self {
+        QUICCircuitBreaker {
+            failure_threshold: default_download_quic_circuit_breaker_failure_threshold(),
+            cooldown: default_download_quic_circuit_breaker_cooldown(),
+        }
+    }
+}
+
+/// QUICFallback is the fallback of the quic client to the gRPC protocol. The pieces are
+/// downloaded by the gRPC protocol if the parent doesn't serve quic, such as the handshake fails
+/// or times out, so quic can be rolled out to the parents gradually.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QUICFallback {
+    /// enable indicates whether to fall back to the gRPC protocol.
+    pub enable: bool,
+
+    /// connect_timeout is the timeout to establish the connection to the parent if the fallback
+    /// is enabled, it is shorter than the connect timeout of the quic client, so the probe of
+    /// the parent without quic is cheap.
+    #[serde(
+        default = "default_download_quic_fallback_connect_timeout",
+        with = "humantime_serde"
+    )]
+    pub connect_timeout: Duration,
+
+    /// ttl is the duration to remember the parent which doesn't serve quic, the pieces of the
+    /// parent are downloaded by the gRPC protocol without probing quic within the ttl.
+    #[serde(
+        default = "default_download_quic_fallback_ttl",
+        with = "humantime_serde"
+    )]
+    pub ttl: Duration,
+}
+
+/// QUICFallback implements Default.
+impl Default for QUICFallback {
+    fn default() -> Self {
+        QUICFallback {
+            enable: true,
+            connect_timeout: default_download_quic_fallback_connect_timeout(),
+            ttl: default_download_quic_fallback_ttl(),
         }
     }
 }
@@ -973,6 +1635,9 @@ pub struct StorageServer {
     /// port is the port to the quic server.
     #[serde(default = "default_storage_server_quic_port")]
     pub quic_port: u16,
+
+    /// quic is the quic server configuration of the storage server.
+    pub quic: StorageServerQUIC,
 }
 
 /// Storage implements Default.
@@ -983,7 +1648,344 @@ impl Default for StorageServer {
             tcp_port: default_storage_server_tcp_port(),
             tcp_fastopen: false,
             quic_port: default_storage_server_quic_port(),
+            quic: StorageServerQUIC::default(),
+        }
+    }
+}
+
+/// StorageServerQUIC is the quic server configuration of the storage server.
+#[derive(Debug, Clone, Validate, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StorageServerQUIC {
+    /// shutdown_grace_period is the grace period to wait for the in-flight streams to finish
+    /// when the quic server is shutting down. The remaining connections will be closed after
+    /// the grace period.
+    #[serde(
+        default = "default_storage_server_quic_shutdown_grace_period",
+        with = "humantime_serde"
+    )]
+    pub shutdown_grace_period: Duration,
+
+    /// drain_timeout is the timeout to wait for the in-flight streams to finish when the quic
+    /// server is draining for the rolling restarts. The draining server refuses the new
+    /// connections and stops accepting the new streams, and it closes the endpoint after the
+    /// in-flight streams finish or the timeout is reached.
+    #[serde(
+        default = "default_storage_server_quic_drain_timeout",
+        with = "humantime_serde"
+    )]
+    pub drain_timeout: Duration,
+
+    /// ca_cert is the root CA cert path with PEM format for the quic server, and it is used
+    /// for mutual TLS. If it is set, the quic server requires the client certificates signed
+    /// by the CA, otherwise the client authentication is disabled.
+    pub ca_cert: Option<PathBuf>,
+
+    /// cert is the server cert path with PEM format for the quic server. If the cert and key
+    /// are not set, the quic server uses the self-signed certificate.
+    pub cert: Option<PathBuf>,
+
+    /// key is the server key path with PEM format for the quic server.
+    pub key: Option<PathBuf>,
+
+    /// cert_reload_interval is the interval to check whether the certificate files are rotated,
+    /// the quic server reloads the certificates for the new handshakes if the files are changed.
+    /// It only works when the cert and key are set.
+    #[serde(
+        default = "default_storage_server_quic_cert_reload_interval",
+        with = "humantime_serde"
+    )]
+    pub cert_reload_interval: Duration,
+
+    /// max_concurrent_connections is the maximum number of concurrent connections of the quic
+    /// server, the new connections will be refused when the limit is reached.
+    #[serde(default = "default_storage_server_quic_max_concurrent_connections")]
+    #[validate(range(min = 1))]
+    pub max_concurrent_connections: usize,
+
+    /// request_timeout is the timeout to read the request and write each chunk of the response.
+    /// The timer is reset when a chunk is written, so it does not interrupt the large pieces
+    /// which are still making progress. The stream is reset when the timeout is reached.
+    #[serde(
+        default = "default_storage_server_quic_request_timeout",
+        with = "humantime_serde"
+    )]
+    pub request_timeout: Duration,
+
+    /// max_request_size is the maximum size of the request message declared in the header.
+    /// The requests only carry the small metadata, so the larger requests are rejected with
+    /// the invalid argument error before the message is read.
+    #[serde(
+        with = "bytesize_serde",
+        default = "default_storage_server_quic_max_request_size"
+    )]
+    pub max_request_size: ByteSize,
+
+    /// max_buffered_request_size is the maximum total size of the requests buffered by all
+    /// in-flight streams. The size declared by a request is reserved until its stream
+    /// completes, and the new requests are rejected with the overloaded error when the
+    /// budget is exhausted, so the peers can't collectively exhaust the memory with the
+    /// requests just under max_request_size.
+    #[serde(
+        with = "bytesize_serde",
+        default = "default_storage_server_quic_max_buffered_request_size"
+    )]
+    pub max_buffered_request_size: ByteSize,
+
+    /// write_buffer_size is the size of the buffer to read the piece content from the storage
+    /// for each quic stream. The next chunk is not read until the previous one is accepted by
+    /// the stream, so the slow peers exert backpressure on the storage reads, and the memory of
+    /// a stream is bounded by the buffer size and the send window.
+    #[serde(
+        with = "bytesize_serde",
+        default = "default_storage_server_quic_write_buffer_size"
+    )]
+    pub write_buffer_size: ByteSize,
+
+    /// shared_read_buffer_size is the maximum bytes of the pieces read into the shared buffers.
+    /// The concurrent requests of the same piece share a single read of the storage, and the
+    /// pieces beyond the limit are streamed from the storage for each request. The shared
+    /// reads are disabled if it is zero.
+    #[serde(with = "bytesize_serde")]
+    pub shared_read_buffer_size: ByteSize,
+
+    /// max_concurrent_streams_per_connection is the maximum number of concurrent streams per
+    /// connection. The peer gets the backpressure at the protocol level when the limit is
+    /// reached, and the streams beyond the limit wait for the in-flight streams to finish.
+    #[serde(default = "default_storage_server_quic_max_concurrent_streams_per_connection")]
+    #[validate(range(min = 1))]
+    pub max_concurrent_streams_per_connection: u32,
+
+    /// max_concurrent_dispatches is the maximum number of the streams dispatched to the handlers
+    /// concurrently across all connections, the other streams wait in the dispatch queue.
+    #[serde(default = "default_storage_server_quic_max_concurrent_dispatches")]
+    #[validate(range(min = 1))]
+    pub max_concurrent_dispatches: usize,
+
+    /// dispatch_queue_depth is the maximum number of the streams waiting to be dispatched across
+    /// all connections. The streams beyond the depth are reset with the overloaded code
+    /// immediately, so the load is shed instead of queued.
+    #[serde(default = "default_storage_server_quic_dispatch_queue_depth")]
+    pub dispatch_queue_depth: usize,
+
+    /// storage_workers is the maximum number of the pieces read from the storage concurrently
+    /// across all connections, it bounds the disk queue separately from the network
+    /// concurrency, and the other pieces wait in the storage worker queue. The limit is
+    /// disabled if it is zero.
+    pub storage_workers: usize,
+
+    /// storage_worker_queue_depth is the maximum number of the pieces waiting for the storage
+    /// workers. The pieces beyond the depth are reset with the overloaded code immediately.
+    #[serde(default = "default_storage_server_quic_storage_worker_queue_depth")]
+    pub storage_worker_queue_depth: usize,
+
+    /// max_concurrent_streams_per_task is the maximum number of the in-flight streams of each
+    /// task across all connections, so a hot task can't starve the pieces of the other tasks.
+    /// The limit is disabled if it is zero.
+    pub max_concurrent_streams_per_task: u32,
+
+    /// queue_task_streams indicates whether the streams beyond max_concurrent_streams_per_task
+    /// wait for the in-flight streams of the task to finish, otherwise they are reset with the
+    /// overloaded code immediately.
+    pub queue_task_streams: bool,
+
+    /// verify_digest indicates whether to verify the digest of the piece before serving it,
+    /// so the corrupted piece is not propagated to the other peers. It costs an extra read
+    /// of the piece content, and the corrupted piece is removed to be downloaded again.
+    #[serde(default = "default_storage_server_quic_verify_digest")]
+    pub verify_digest: bool,
+
+    /// persistent_cache_fallback indicates whether to serve the piece from the persistent cache
+    /// if the content of the piece is missing, such as it has been evicted, and a persistent
+    /// cache piece with the identical digest and length is stored.
+    pub persistent_cache_fallback: bool,
+
+    /// disable_access_log indicates whether to disable the access log of the quic server. The
+    /// access log is emitted once per stream when the stream is finished, it can be disabled
+    /// for the high QPS deployments.
+    pub disable_access_log: bool,
+
+    /// enable_audit_log indicates whether to emit the audit log of the persistent cache pieces
+    /// served by the quic server, with the peer address, the identity of the client certificate,
+    /// the task id, the piece number, the sent bytes and the result. The audit log is emitted
+    /// with the dragonfly::audit target, so it can be routed to its own sink.
+    pub enable_audit_log: bool,
+
+    /// slow_request_threshold is the duration of the stream beyond which the warning of the
+    /// slow request is emitted, with the durations of receiving the request, reading the
+    /// storage and sending the response. The warning is disabled if it is zero.
+    #[serde(with = "humantime_serde")]
+    pub slow_request_threshold: Duration,
+
+    /// allowed_cidrs is the list of the CIDRs which are allowed to connect to the quic server,
+    /// both IPv4 and IPv6 CIDRs are supported. If it is empty, all peers are allowed.
+    pub allowed_cidrs: Vec<IpNetwork>,
+
+    /// denied_cidrs is the list of the CIDRs which are denied to connect to the quic server,
+    /// and it takes precedence over the allowed_cidrs.
+    pub denied_cidrs: Vec<IpNetwork>,
+
+    /// peer_rate_limit is the maximum number of the requests per second of each peer, the peer
+    /// is identified by the remote ip. The requests beyond the limit are reset with the
+    /// overloaded code, and the rate limit is disabled if it is zero.
+    pub peer_rate_limit: u32,
+
+    /// peer_rate_limit_burst is the maximum number of the requests of each peer in a burst.
+    #[serde(default = "default_storage_server_quic_peer_rate_limit_burst")]
+    #[validate(range(min = 1))]
+    pub peer_rate_limit_burst: u32,
+
+    /// peer_ban_threshold is the number of the protocol violations of a peer within the
+    /// peer_ban_window to ban the peer, such as the malformed requests and the failed
+    /// handshakes. The connections of the banned peer are closed, and its new connections are
+    /// refused for the peer_ban_duration. The errors like not found are not violations, and
+    /// the ban is disabled if it is zero.
+    pub peer_ban_threshold: u32,
+
+    /// peer_ban_window is the sliding window to count the protocol violations of each peer.
+    #[serde(
+        default = "default_storage_server_quic_peer_ban_window",
+        with = "humantime_serde"
+    )]
+    pub peer_ban_window: Duration,
+
+    /// peer_ban_duration is the duration to refuse the connections of the banned peer.
+    #[serde(
+        default = "default_storage_server_quic_peer_ban_duration",
+        with = "humantime_serde"
+    )]
+    pub peer_ban_duration: Duration,
+
+    /// connection_byte_quota is the maximum bytes of the piece content served on each
+    /// connection, so a tenant can't drain the bandwidth of the node on a single long-lived
+    /// connection. The in-flight streams are finished when the quota is exceeded, and the new
+    /// streams of the connection are reset with the quota exceeded code, the peer needs to
+    /// reconnect to continue. The quota is disabled if it is zero.
+    #[serde(with = "bytesize_serde")]
+    pub connection_byte_quota: ByteSize,
+
+    /// additional_listen_addrs is the list of the additional addresses for the quic server to
+    /// listen on besides the storage server ip and quic port, e.g. an IPv6 address for the
+    /// dual stack or a dedicated storage NIC.
+    pub additional_listen_addrs: Vec<SocketAddr>,
+
+    /// subject_alt_names is the list of the extra DNS names and IP addresses in the subject
+    /// alternative names of the self-signed certificate, besides the hostname and the
+    /// advertise ip of the host, and the listen ips of the quic server. It is ignored if the
+    /// cert and key are configured.
+    pub subject_alt_names: Vec<String>,
+
+    /// cipher_suites is the list of the TLS 1.3 cipher suites accepted by the quic server, such
+    /// as TLS13_AES_256_GCM_SHA384. QUIC requires TLS 1.3, and all TLS 1.3 cipher suites are
+    /// accepted if it is empty. The quic server fails to start if any of them is unsupported.
+    pub cipher_suites: Vec<String>,
+
+    /// allow_partial_listen indicates whether the quic server keeps running if it fails to listen
+    /// on some of the addresses, as long as it listens on at least one address.
+    pub allow_partial_listen: bool,
+
+    /// workers is the number of the endpoints bound to each listen address with SO_REUSEPORT,
+    /// each endpoint has its own UDP socket, so the receive work of the connections is spread
+    /// across the cores.
+    #[serde(default = "default_storage_server_quic_workers")]
+    #[validate(range(min = 1))]
+    pub workers: usize,
+
+    /// rebind_max_retries is the maximum number of the retries to rebind the endpoint which is
+    /// closed unexpectedly, such as its socket fails. The retries back off exponentially, and
+    /// the quic server exits with the error after the retries are exhausted.
+    #[serde(default = "default_storage_server_quic_rebind_max_retries")]
+    pub rebind_max_retries: u32,
+
+    /// connection_idle_timeout is the timeout to close the connection which serves no stream,
+    /// such as the peer finished its downloads but keeps the connection alive. It works at a
+    /// longer horizon than the max idle timeout of the transport, which is reset by the
+    /// keep-alive packets. The zero value disables closing the idle connections.
+    #[serde(
+        default = "default_storage_server_quic_connection_idle_timeout",
+        with = "humantime_serde"
+    )]
+    pub connection_idle_timeout: Duration,
+
+    /// enable_zero_rtt indicates whether to accept the 0-RTT early data of the resumed TLS
+    /// sessions. Only the replay-safe requests are served from the early data, the others are
+    /// rejected with the invalid argument error.
+    pub enable_zero_rtt: bool,
+
+    /// max_piece_size is the maximum length of the piece served by the quic server, the larger
+    /// piece is refused with the internal error before its content is read.
+    #[serde(with = "bytesize_serde", default = "default_quic_max_piece_size")]
+    pub max_piece_size: ByteSize,
+
+    /// transport is the transport configuration of the quic server, the maximum number of the
+    /// concurrent bidirectional streams is configured by max_concurrent_streams_per_connection.
+    pub transport: QUICTransport,
+}
+
+/// StorageServerQUIC implements Default.
+impl Default for StorageServerQUIC {
+    fn default() -> Self {
+        StorageServerQUIC {
+            shutdown_grace_period: default_storage_server_quic_shutdown_grace_period(),
+            drain_timeout: default_storage_server_quic_drain_timeout(),
+            ca_cert: None,
+            cert: None,
+            key: None,
+            cert_reload_interval: default_storage_server_quic_cert_reload_interval(),
+            max_concurrent_connections: default_storage_server_quic_max_concurrent_connections(),
+            request_timeout: default_storage_server_quic_request_timeout(),
+            max_request_size: default_storage_server_quic_max_request_size(),
+            max_buffered_request_size: default_storage_server_quic_max_buffered_request_size(),
+            write_buffer_size: default_storage_server_quic_write_buffer_size(),
+            shared_read_buffer_size: ByteSize::b(0),
+            max_concurrent_streams_per_connection:
+                default_storage_server_quic_max_concurrent_streams_per_connection(),
+            max_concurrent_dispatches: default_storage_server_quic_max_concurrent_dispatches(),
+            dispatch_queue_depth: default_storage_server_quic_dispatch_queue_depth(),
+            storage_workers: 0,
+            storage_worker_queue_depth: default_storage_server_quic_storage_worker_queue_depth(),
+            max_concurrent_streams_per_task: 0,
+            queue_task_streams: false,
+            verify_digest: default_storage_server_quic_verify_digest(),
+            persistent_cache_fallback: false,
+            disable_access_log: false,
+            enable_audit_log: false,
+            slow_request_threshold: Duration::ZERO,
+            allowed_cidrs: Vec::new(),
+            denied_cidrs: Vec::new(),
+            peer_rate_limit: 0,
+            peer_rate_limit_burst: default_storage_server_quic_peer_rate_limit_burst(),
+            peer_ban_threshold: 0,
+            peer_ban_window: default_storage_server_quic_peer_ban_window(),
+            peer_ban_duration: default_storage_server_quic_peer_ban_duration(),
+            connection_byte_quota: ByteSize::b(0),
+            additional_listen_addrs: Vec::new(),
+            subject_alt_names: Vec::new(),
+            cipher_suites: Vec::new(),
+            allow_partial_listen: false,
+            workers: default_storage_server_quic_workers(),
+            rebind_max_retries: default_storage_server_quic_rebind_max_retries(),
+            connection_idle_timeout: default_storage_server_quic_connection_idle_timeout(),
+            enable_zero_rtt: false,
+            max_piece_size: default_quic_max_piece_size(),
+            transport: QUICTransport::default(),
+        }
+    }
+}
+
+/// StorageServerQUIC is the implementation of StorageServerQUIC.
+impl StorageServerQUIC {
+    /// is_peer_allowed returns whether the peer is allowed to connect to the quic server. The
+    /// denied CIDRs take precedence over the allowed CIDRs, and the empty allowed CIDRs mean
+    /// all peers are allowed.
+    pub fn is_peer_allowed(&self, ip: IpAddr) -> bool {
+        // The IPv4-mapped IPv6 address is used by the dual stack socket.
+        let ip = ip.to_canonical();
+        if self.denied_cidrs.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
         }
+
+        self.allowed_cidrs.is_empty() || self.allowed_cidrs.iter().any(|cidr| cidr.contains(ip))
     }
 }
 
@@ -1731,7 +2733,35 @@ mod tests {
             "protocol": "quic",
             "rateLimit": "50GiB",
             "pieceTimeout": "30s",
-            "concurrentPieceCount": 10
+            "concurrentPieceCount": 10,
+            "quic": {
+                "caCert": "/etc/ssl/certs/ca.crt",
+                "verifyMode": "skip",
+                "serverName": "dfdaemon.example.com",
+                "enableZeroRtt": true,
+                "connectTimeout": "5s",
+                "maxConcurrentStreams": 16,
+                "failFastWhenBusy": true,
+                "verifyDigest": false,
+                "enablePeerMetrics": true,
+                "retry": {
+                    "maxAttempts": 5,
+                    "initialBackoff": "200ms"
+                },
+                "circuitBreaker": {
+                    "failureThreshold": 3
+                },
+                "fallback": {
+                    "enable": false,
+                    "ttl": "1m"
+                },
+                "cipherSuites": ["TLS13_AES_256_GCM_SHA384", "TLS13_CHACHA20_POLY1305_SHA256"],
+                "transport": {
+                    "streamReceiveWindow": "8MiB",
+                    "keepAliveInterval": "10s",
+                    "maxConcurrentUniStreams": 16
+                }
+            }
         }"#;
 
         let download: Download = serde_json::from_str(json_data).unwrap();
@@ -1745,6 +2775,92 @@ mod tests {
         assert_eq!(download.rate_limit, ByteSize::gib(50));
         assert_eq!(download.piece_timeout, Duration::from_secs(30));
         assert_eq!(download.concurrent_piece_count, 10);
+        assert_eq!(
+            download.quic.transport.stream_receive_window,
+            ByteSize::mib(8)
+        );
+        assert_eq!(
+            download.quic.transport.keep_alive_interval,
+            Duration::from_secs(10)
+        );
+        assert_eq!(download.quic.transport.max_concurrent_uni_streams, 16);
+        assert_eq!(
+            download.quic.ca_cert,
+            Some(PathBuf::from("/etc/ssl/certs/ca.crt"))
+        );
+        assert_eq!(download.quic.verify_mode, QUICVerifyMode::Skip);
+        assert!(!download.quic.enable_native_roots);
+        assert_eq!(
+            download.quic.server_name,
+            Some("dfdaemon.example.com".to_string())
+        );
+        assert!(download.quic.enable_zero_rtt);
+        assert_eq!(download.quic.max_piece_size, ByteSize::gib(1));
+        assert_eq!(download.quic.connect_timeout, Duration::from_secs(5));
+        assert_eq!(download.quic.request_timeout, Duration::from_secs(30));
+        assert_eq!(download.quic.max_concurrent_streams, 16);
+        assert!(download.quic.fail_fast_when_busy);
+        assert!(!download.quic.verify_digest);
+        assert!(download.quic.enable_peer_metrics);
+        assert_eq!(download.quic.retry.max_attempts, 5);
+        assert_eq!(
+            download.quic.retry.initial_backoff,
+            Duration::from_millis(200)
+        );
+        assert_eq!(download.quic.retry.max_backoff, Duration::from_secs(2));
+        assert!(download.quic.retry.jitter);
+        assert_eq!(download.quic.circuit_breaker.failure_threshold, 3);
+        assert_eq!(
+            download.quic.circuit_breaker.cooldown,
+            Duration::from_secs(30)
+        );
+        assert!(!download.quic.fallback.enable);
+        assert_eq!(
+            download.quic.fallback.connect_timeout,
+            Duration::from_secs(2)
+        );
+        assert_eq!(download.quic.fallback.ttl, Duration::from_secs(60));
+        assert_eq!(
+            download.quic.cipher_suites,
+            vec![
+                "TLS13_AES_256_GCM_SHA384".to_string(),
+                "TLS13_CHACHA20_POLY1305_SHA256".to_string()
+            ]
+        );
+        assert_eq!(
+            download.quic.transport.max_idle_timeout,
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            download.quic.transport.congestion_control,
+            CongestionControl::Bbr
+        );
+    }
+
+    #[test]
+    fn deserialize_quic_transport_congestion_control() {
+        let transport: QUICTransport =
+            serde_json::from_str(r#"{"congestionControl": "newreno"}"#).unwrap();
+        assert_eq!(transport.congestion_control, CongestionControl::NewReno);
+        assert_eq!(transport.datagram_receive_buffer_size, ByteSize::kib(64));
+
+        let transport: QUICTransport =
+            serde_json::from_str(r#"{"datagramReceiveBufferSize": "0B"}"#).unwrap();
+        assert_eq!(transport.datagram_receive_buffer_size, ByteSize::b(0));
+
+        let transport: QUICTransport = serde_json::from_str(
+            r#"{"mtuDiscovery": false, "mtuDiscoveryUpperBound": 8952, "segmentationOffload": false}"#,
+        )
+        .unwrap();
+        assert!(!transport.mtu_discovery);
+        assert_eq!(transport.mtu_discovery_upper_bound, 8952);
+        assert!(!transport.segmentation_offload);
+        assert!(QUICTransport::default().mtu_discovery);
+        assert_eq!(QUICTransport::default().mtu_discovery_upper_bound, 1452);
+
+        assert!(
+            serde_json::from_str::<QUICTransport>(r#"{"congestionControl": "vegas"}"#).is_err()
+        );
     }
 
     #[test]
@@ -2062,7 +3178,52 @@ key: /etc/ssl/private/client.pem
             "server": {
                 "ip": "128.0.0.1",
                 "tcpPort": 4005,
-                "quicPort": 4006
+                "quicPort": 4006,
+                "quic": {
+                    "shutdownGracePeriod": "10s",
+                    "drainTimeout": "1m",
+                    "certReloadInterval": "10m",
+                    "maxConcurrentConnections": 100,
+                    "requestTimeout": "5s",
+                    "maxConcurrentStreamsPerConnection": 10,
+                    "maxConcurrentDispatches": 64,
+                    "dispatchQueueDepth": 128,
+                    "storageWorkers": 32,
+                    "maxConcurrentStreamsPerTask": 4,
+                    "queueTaskStreams": true,
+                    "verifyDigest": false,
+                    "persistentCacheFallback": true,
+                    "disableAccessLog": true,
+                    "enableAuditLog": true,
+                    "slowRequestThreshold": "2s",
+                    "allowedCidrs": ["10.0.0.0/8", "fd00::/8"],
+                    "deniedCidrs": ["10.1.0.0/16"],
+                    "peerRateLimit": 50,
+                    "peerRateLimitBurst": 200,
+                    "peerBanThreshold": 5,
+                    "peerBanWindow": "30s",
+                    "peerBanDuration": "1h",
+                    "connectionByteQuota": "10GiB",
+                    "additionalListenAddrs": ["[::1]:4006"],
+                    "subjectAltNames": ["storage.example.com", "10.0.0.1"],
+                    "cipherSuites": ["TLS13_AES_256_GCM_SHA384"],
+                    "allowPartialListen": true,
+                    "workers": 4,
+                    "rebindMaxRetries": 3,
+                    "connectionIdleTimeout": "30m",
+                    "enableZeroRtt": true,
+                    "maxPieceSize": "64MiB",
+                    "maxRequestSize": "1MiB",
+                    "maxBufferedRequestSize": "64MiB",
+                    "writeBufferSize": "256KiB",
+                    "sharedReadBufferSize": "512MiB",
+                    "transport": {
+                        "receiveWindow": "32MiB",
+                        "maxIdleTimeout": "1m",
+                        "initialRtt": "100ms",
+                        "congestionControl": "cubic"
+                    }
+                }
             },
             "dir": "/tmp/storage",
             "keep": true,
@@ -2080,6 +3241,97 @@ key: /etc/ssl/private/client.pem
         );
         assert_eq!(storage.server.tcp_port, 4005);
         assert_eq!(storage.server.quic_port, 4006);
+        assert_eq!(
+            storage.server.quic.shutdown_grace_period,
+            Duration::from_secs(10)
+        );
+        assert_eq!(storage.server.quic.drain_timeout, Duration::from_secs(60));
+        assert_eq!(
+            storage.server.quic.cert_reload_interval,
+            Duration::from_secs(600)
+        );
+        assert_eq!(storage.server.quic.max_concurrent_connections, 100);
+        assert_eq!(storage.server.quic.request_timeout, Duration::from_secs(5));
+        assert_eq!(
+            storage.server.quic.max_concurrent_streams_per_connection,
+            10
+        );
+        assert_eq!(storage.server.quic.max_concurrent_dispatches, 64);
+        assert_eq!(storage.server.quic.dispatch_queue_depth, 128);
+        assert_eq!(storage.server.quic.storage_workers, 32);
+        assert_eq!(storage.server.quic.storage_worker_queue_depth, 1024);
+        assert_eq!(storage.server.quic.max_concurrent_streams_per_task, 4);
+        assert!(storage.server.quic.queue_task_streams);
+        assert!(!storage.server.quic.verify_digest);
+        assert!(storage.server.quic.persistent_cache_fallback);
+        assert!(storage.server.quic.disable_access_log);
+        assert!(storage.server.quic.enable_audit_log);
+        assert_eq!(
+            storage.server.quic.slow_request_threshold,
+            Duration::from_secs(2)
+        );
+        assert_eq!(storage.server.quic.allowed_cidrs.len(), 2);
+        assert_eq!(
+            storage.server.quic.denied_cidrs,
+            vec!["10.1.0.0/16".parse::<IpNetwork>().unwrap()]
+        );
+        assert_eq!(storage.server.quic.peer_rate_limit, 50);
+        assert_eq!(storage.server.quic.peer_rate_limit_burst, 200);
+        assert_eq!(storage.server.quic.peer_ban_threshold, 5);
+        assert_eq!(storage.server.quic.peer_ban_window, Duration::from_secs(30));
+        assert_eq!(
+            storage.server.quic.peer_ban_duration,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(storage.server.quic.connection_byte_quota, ByteSize::gib(10));
+        assert_eq!(
+            storage.server.quic.additional_listen_addrs,
+            vec!["[::1]:4006".parse::<SocketAddr>().unwrap()]
+        );
+        assert_eq!(
+            storage.server.quic.subject_alt_names,
+            vec!["storage.example.com".to_string(), "10.0.0.1".to_string()]
+        );
+        assert_eq!(
+            storage.server.quic.cipher_suites,
+            vec!["TLS13_AES_256_GCM_SHA384".to_string()]
+        );
+        assert!(storage.server.quic.allow_partial_listen);
+        assert_eq!(storage.server.quic.workers, 4);
+        assert_eq!(storage.server.quic.rebind_max_retries, 3);
+        assert_eq!(
+            storage.server.quic.connection_idle_timeout,
+            Duration::from_secs(1800)
+        );
+        assert!(storage.server.quic.enable_zero_rtt);
+        assert_eq!(storage.server.quic.max_piece_size, ByteSize::mib(64));
+        assert_eq!(storage.server.quic.max_request_size, ByteSize::mib(1));
+        assert_eq!(
+            storage.server.quic.max_buffered_request_size,
+            ByteSize::mib(64)
+        );
+        assert_eq!(storage.server.quic.write_buffer_size, ByteSize::kib(256));
+        assert_eq!(
+            storage.server.quic.shared_read_buffer_size,
+            ByteSize::mib(512)
+        );
+        assert_eq!(
+            storage.server.quic.transport.receive_window,
+            ByteSize::mib(32)
+        );
+        assert_eq!(storage.server.quic.transport.send_window, ByteSize::mib(16));
+        assert_eq!(
+            storage.server.quic.transport.max_idle_timeout,
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            storage.server.quic.transport.initial_rtt,
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            storage.server.quic.transport.congestion_control,
+            CongestionControl::Cubic
+        );
         assert_eq!(storage.dir, PathBuf::from("/tmp/storage"));
         assert!(storage.keep);
         assert_eq!(storage.write_piece_timeout, Duration::from_secs(20));
@@ -2088,6 +3340,44 @@ key: /etc/ssl/private/client.pem
         assert_eq!(storage.cache_capacity, ByteSize::mb(256));
     }
 
+    #[test]
+    fn storage_server_quic_is_peer_allowed() {
+        let config = StorageServerQUIC::default();
+        assert!(config.is_peer_allowed("10.0.0.1".parse().unwrap()));
+        assert!(config.is_peer_allowed("fd00::1".parse().unwrap()));
+
+        let config = StorageServerQUIC {
+            allowed_cidrs: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(config.is_peer_allowed("10.1.2.3".parse().unwrap()));
+        assert!(config.is_peer_allowed("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!config.is_peer_allowed("192.168.0.1".parse().unwrap()));
+
+        let config = StorageServerQUIC {
+            allowed_cidrs: vec!["fd00::/8".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(config.is_peer_allowed("fd00::1".parse().unwrap()));
+        assert!(!config.is_peer_allowed("fe80::1".parse().unwrap()));
+        assert!(!config.is_peer_allowed("10.0.0.1".parse().unwrap()));
+
+        let config = StorageServerQUIC {
+            allowed_cidrs: vec!["10.0.0.0/8".parse().unwrap()],
+            denied_cidrs: vec!["10.1.0.0/16".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(config.is_peer_allowed("10.2.0.1".parse().unwrap()));
+        assert!(!config.is_peer_allowed("10.1.0.1".parse().unwrap()));
+
+        let config = StorageServerQUIC {
+            denied_cidrs: vec!["10.1.0.0/16".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(config.is_peer_allowed("192.168.0.1".parse().unwrap()));
+        assert!(!config.is_peer_allowed("10.1.0.1".parse().unwrap()));
+    }
+
     #[test]
     fn validate_policy() {
         let valid_policy = Policy {
@@ -70,6 +70,58 @@ pub enum DFError {
     #[error{"wait for piece {0} finished timeout"}]
     WaitForPieceFinishedTimeout(String),
 
+    /// QUICTimeout is the error when the quic client times out to wait for the response of the
+    /// parent.
+    #[error{"quic {0} timeout"}]
+    QUICTimeout(String),
+
+    /// QUICConnectTimeout is the error when the quic client times out to connect to the parent.
+    #[error{"quic connect to {0} timeout"}]
+    QUICConnectTimeout(String),
+
+    /// CorruptPiece is the error when the content of the downloaded piece doesn't match the
+    /// expected digest.
+    #[error{"piece {0} is corrupt, expected digest {1}, actual digest {2}"}]
+    CorruptPiece(String, String, String),
+
+    /// QUICCancelled is the error when the quic request is cancelled by the caller.
+    #[error{"quic {0} cancelled"}]
+    QUICCancelled(String),
+
+    /// QUICHandshakeFailed is the error when the quic handshake with the peer fails, such as the
+    /// peer doesn't serve quic or negotiates the other protocols.
+    #[error{"quic handshake failed: {0}"}]
+    QUICHandshakeFailed(String),
+
+    /// PeerUnavailable is the error when the circuit to the peer is open after its consecutive
+    /// failures.
+    #[error{"peer {0} is unavailable"}]
+    PeerUnavailable(String),
+
+    /// QUICBusy is the error when the quic client has reached the maximum number of the
+    /// concurrent streams to the parent.
+    #[error{"quic {0} busy"}]
+    QUICBusy(String),
+
+    /// QUICOverloaded is the error when the quic peer sheds the load, such as the peer exceeds
+    /// the rate limit or the request queue of the server is full.
+    #[error{"quic overloaded: {0}"}]
+    QUICOverloaded(String),
+
+    /// QUICQuotaExceeded is the error when the quic connection exceeds the byte quota of the
+    /// peer, the requests are served again after reconnecting.
+    #[error{"quic quota exceeded: {0}"}]
+    QUICQuotaExceeded(String),
+
+    /// QUICConnectionLost is the error when the quic connection or the stream is lost, such as
+    /// the peer closes the connection or resets the stream.
+    #[error("{0}")]
+    QUICConnectionLost(String),
+
+    /// QUICRetryExhausted is the error when the quic request still fails after the attempts.
+    #[error("{1} after {0} attempts")]
+    QUICRetryExhausted(u32, Box<DFError>),
+
     /// AvailableManagerNotFound is the error when the available manager is not found.
     #[error{"available manager not found"}]
     AvailableManagerNotFound,
@@ -260,25 +312,31 @@ impl From<quinn::ConnectError> for DFError {
 
 impl From<quinn::ConnectionError> for DFError {
     fn from(err: quinn::ConnectionError) -> Self {
-        DFError::Unknown(format!("quinn connection error: {}", err))
+        DFError::QUICConnectionLost(format!("quinn connection error: {}", err))
     }
 }
 
 impl From<quinn::WriteError> for DFError {
     fn from(err: quinn::WriteError) -> Self {
-        DFError::Unknown(format!("quinn write error: {}", err))
+        DFError::QUICConnectionLost(format!("quinn write error: {}", err))
     }
 }
 
 impl From<quinn::ReadError> for DFError {
     fn from(err: quinn::ReadError) -> Self {
-        DFError::Unknown(format!("quinn read error: {}", err))
+        DFError::QUICConnectionLost(format!("quinn read error: {}", err))
     }
 }
 
 impl From<quinn::ReadExactError> for DFError {
     fn from(err: quinn::ReadExactError) -> Self {
-        DFError::Unknown(format!("quinn read exact error: {}", err))
+        DFError::QUICConnectionLost(format!("quinn read exact error: {}", err))
+    }
+}
+
+impl From<quinn::SendDatagramError> for DFError {
+    fn from(err: quinn::SendDatagramError) -> Self {
+        DFError::Unknown(format!("quinn send datagram error: {}", err))
     }
 }
 
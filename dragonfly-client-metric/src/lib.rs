@@ -267,6 +267,181 @@ lazy_static! {
             Opts::new("disk_usage_space_total", "Gauge of the disk usage space in bytes").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
             &[]
         ).expect("metric can be created");
+
+    /// QUIC_CONNECTION_COUNT is used to count the number of the storage quic connections.
+    pub static ref QUIC_CONNECTION_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_connection_total", "Counter of the number of the storage quic connection.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["result"]
+        ).expect("metric can be created");
+
+    /// CONCURRENT_QUIC_CONNECTION_GAUGE is used to gauge the number of concurrent storage quic connections.
+    pub static ref CONCURRENT_QUIC_CONNECTION_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("concurrent_quic_connection_total", "Gauge of the number of concurrent of the storage quic connection.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_CONNECTION_CLOSED_COUNT is used to count the number of the closed storage quic connections by reason.
+    pub static ref QUIC_CONNECTION_CLOSED_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_connection_closed_total", "Counter of the number of the closed storage quic connection.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["reason"]
+        ).expect("metric can be created");
+
+    /// QUIC_STREAM_COUNT is used to count the number of the storage quic streams.
+    pub static ref QUIC_STREAM_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_stream_total", "Counter of the number of the storage quic stream.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["type", "result"]
+        ).expect("metric can be created");
+
+    /// QUIC_STREAM_DURATION is used to record the storage quic stream duration.
+    pub static ref QUIC_STREAM_DURATION: HistogramVec =
+        HistogramVec::new(
+            HistogramOpts::new("quic_stream_duration_milliseconds", "Histogram of the storage quic stream duration.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME).buckets(exponential_buckets(1.0, 2.0, 24).unwrap()),
+            &["type"]
+        ).expect("metric can be created");
+
+    /// QUIC_STREAM_PHASE_DURATION is used to record the duration of each phase of the storage quic stream.
+    pub static ref QUIC_STREAM_PHASE_DURATION: HistogramVec =
+        HistogramVec::new(
+            HistogramOpts::new("quic_stream_phase_duration_milliseconds", "Histogram of the duration of each phase of the storage quic stream.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME).buckets(exponential_buckets(1.0, 2.0, 24).unwrap()),
+            &["type", "phase"]
+        ).expect("metric can be created");
+
+    /// QUIC_CONNECTION_RTT is used to record the round trip time of the storage quic connections when they are closed.
+    pub static ref QUIC_CONNECTION_RTT: HistogramVec =
+        HistogramVec::new(
+            HistogramOpts::new("quic_connection_rtt_milliseconds", "Histogram of the round trip time of the storage quic connections.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME).buckets(exponential_buckets(0.125, 2.0, 16).unwrap()),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_CONNECTION_LOSS_RATE is used to record the packet loss rate of the storage quic connections when they are closed.
+    pub static ref QUIC_CONNECTION_LOSS_RATE: HistogramVec =
+        HistogramVec::new(
+            HistogramOpts::new("quic_connection_loss_rate", "Histogram of the packet loss rate of the storage quic connections.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME).buckets(vec![0.0001, 0.001, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5]),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_UPLOAD_TRAFFIC is used to count the upload traffic of the storage quic server.
+    pub static ref QUIC_UPLOAD_TRAFFIC: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_upload_traffic", "Counter of the number of the upload traffic of the storage quic server.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["type"]
+        ).expect("metric can be created");
+
+    /// QUIC_CERT_RELOAD_COUNT is used to count the number of the certificate reloads of the storage quic server.
+    pub static ref QUIC_CERT_RELOAD_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_cert_reload_total", "Counter of the number of the certificate reload of the storage quic server.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["result"]
+        ).expect("metric can be created");
+
+    /// QUIC_REQUEST_BUFFERED_BYTES_GAUGE is used to gauge the request bytes reserved by the in-flight storage quic streams.
+    pub static ref QUIC_REQUEST_BUFFERED_BYTES_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_request_buffered_bytes", "Gauge of the request bytes buffered by the storage quic streams.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_BUFFERED_BYTES_GAUGE is used to gauge the bytes read from the storage but not yet handed over to the storage quic streams.
+    pub static ref QUIC_BUFFERED_BYTES_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_buffered_bytes", "Gauge of the bytes buffered by the storage quic streams.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_DISPATCH_QUEUE_DEPTH_GAUGE is used to gauge the number of the storage quic streams waiting to be dispatched.
+    pub static ref QUIC_DISPATCH_QUEUE_DEPTH_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_dispatch_queue_depth", "Gauge of the number of the storage quic streams waiting to be dispatched.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_DISPATCH_REJECTED_COUNT is used to count the number of the storage quic streams rejected by the full dispatch queue.
+    pub static ref QUIC_DISPATCH_REJECTED_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_dispatch_rejected_total", "Counter of the number of the storage quic stream rejected by the dispatch queue.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_STORAGE_WORKER_QUEUE_DEPTH_GAUGE is used to gauge the number of the storage quic streams waiting for the storage workers.
+    pub static ref QUIC_STORAGE_WORKER_QUEUE_DEPTH_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_storage_worker_queue_depth", "Gauge of the number of the storage quic streams waiting for the storage workers.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_STORAGE_WORKER_BUSY_GAUGE is used to gauge the number of the busy storage workers of the storage quic server.
+    pub static ref QUIC_STORAGE_WORKER_BUSY_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_storage_worker_busy", "Gauge of the number of the busy storage workers of the storage quic server.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_BANNED_PEER_GAUGE is used to gauge the number of the peers banned by the storage quic server.
+    pub static ref QUIC_BANNED_PEER_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_banned_peer_total", "Gauge of the number of the peers banned by the storage quic server.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &[]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_REQUEST_COUNT is used to count the number of the requests of the quic client by result.
+    pub static ref QUIC_CLIENT_REQUEST_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_client_request_total", "Counter of the number of the requests of the quic client.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["type", "peer", "result"]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_REQUEST_DURATION is used to record the duration of the requests of the quic client until the response is received.
+    pub static ref QUIC_CLIENT_REQUEST_DURATION: HistogramVec =
+        HistogramVec::new(
+            HistogramOpts::new("quic_client_request_duration_milliseconds", "Histogram of the duration of the requests of the quic client.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME).buckets(exponential_buckets(1.0, 2.0, 24).unwrap()),
+            &["type", "peer"]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_DOWNLOAD_TRAFFIC is used to count the bytes of the piece content received by the quic client.
+    pub static ref QUIC_CLIENT_DOWNLOAD_TRAFFIC: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_client_download_traffic", "Counter of the number of the download traffic of the quic client.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["type", "peer"]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_CONNECT_COUNT is used to count the number of the connection attempts of the quic client by result.
+    pub static ref QUIC_CLIENT_CONNECT_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_client_connect_total", "Counter of the number of the connection attempts of the quic client.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["peer", "result"]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_CONNECTION_GAUGE is used to gauge the number of the connections shared by the requests of the quic client.
+    pub static ref QUIC_CLIENT_CONNECTION_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_client_connection_total", "Gauge of the number of the connections of the quic client.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["peer"]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE is used to gauge the number of the in-flight streams of the quic client to each peer.
+    pub static ref QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE: IntGaugeVec =
+        IntGaugeVec::new(
+            Opts::new("quic_client_inflight_streams", "Gauge of the number of the in-flight streams of the quic client to each peer.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["peer"]
+        ).expect("metric can be created");
+
+    /// QUIC_CLIENT_RETRY_COUNT is used to count the number of the retried requests of the quic client.
+    pub static ref QUIC_CLIENT_RETRY_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("quic_client_retry_total", "Counter of the number of the retried requests of the quic client.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["type"]
+        ).expect("metric can be created");
+
+    /// DOWNLOAD_PIECE_PROTOCOL_COUNT is used to count the number of the pieces downloaded from the parents by each protocol.
+    pub static ref DOWNLOAD_PIECE_PROTOCOL_COUNT: IntCounterVec =
+        IntCounterVec::new(
+            Opts::new("download_piece_protocol_total", "Counter of the number of the pieces downloaded from the parents by each protocol.").namespace(dragonfly_client_config::SERVICE_NAME).subsystem(dragonfly_client_config::NAME),
+            &["protocol"]
+        ).expect("metric can be created");
 }
 
 /// register_custom_metrics registers all custom metrics.
@@ -382,6 +557,106 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(DISK_USAGE_SPACE.clone()))
         .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CONNECTION_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(CONCURRENT_QUIC_CONNECTION_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CONNECTION_CLOSED_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_STREAM_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_STREAM_DURATION.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_STREAM_PHASE_DURATION.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CONNECTION_RTT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CONNECTION_LOSS_RATE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_UPLOAD_TRAFFIC.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CERT_RELOAD_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_REQUEST_BUFFERED_BYTES_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_BUFFERED_BYTES_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_DISPATCH_QUEUE_DEPTH_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_DISPATCH_REJECTED_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_STORAGE_WORKER_QUEUE_DEPTH_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_STORAGE_WORKER_BUSY_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_BANNED_PEER_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_REQUEST_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_REQUEST_DURATION.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_DOWNLOAD_TRAFFIC.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_CONNECT_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_CONNECTION_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(QUIC_CLIENT_RETRY_COUNT.clone()))
+        .expect("metric can be registered");
+
+    REGISTRY
+        .register(Box::new(DOWNLOAD_PIECE_PROTOCOL_COUNT.clone()))
+        .expect("metric can be registered");
 }
 
 /// reset_custom_metrics resets all custom metrics.
@@ -414,6 +689,31 @@ fn reset_custom_metrics() {
     DELETE_HOST_FAILURE_COUNT.reset();
     DISK_SPACE.reset();
     DISK_USAGE_SPACE.reset();
+    QUIC_CONNECTION_COUNT.reset();
+    CONCURRENT_QUIC_CONNECTION_GAUGE.reset();
+    QUIC_CONNECTION_CLOSED_COUNT.reset();
+    QUIC_STREAM_COUNT.reset();
+    QUIC_STREAM_DURATION.reset();
+    QUIC_STREAM_PHASE_DURATION.reset();
+    QUIC_CONNECTION_RTT.reset();
+    QUIC_CONNECTION_LOSS_RATE.reset();
+    QUIC_UPLOAD_TRAFFIC.reset();
+    QUIC_CERT_RELOAD_COUNT.reset();
+    QUIC_REQUEST_BUFFERED_BYTES_GAUGE.reset();
+    QUIC_BUFFERED_BYTES_GAUGE.reset();
+    QUIC_DISPATCH_QUEUE_DEPTH_GAUGE.reset();
+    QUIC_DISPATCH_REJECTED_COUNT.reset();
+    QUIC_STORAGE_WORKER_QUEUE_DEPTH_GAUGE.reset();
+    QUIC_STORAGE_WORKER_BUSY_GAUGE.reset();
+    QUIC_BANNED_PEER_GAUGE.reset();
+    QUIC_CLIENT_REQUEST_COUNT.reset();
+    QUIC_CLIENT_REQUEST_DURATION.reset();
+    QUIC_CLIENT_DOWNLOAD_TRAFFIC.reset();
+    QUIC_CLIENT_CONNECT_COUNT.reset();
+    QUIC_CLIENT_CONNECTION_GAUGE.reset();
+    QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE.reset();
+    QUIC_CLIENT_RETRY_COUNT.reset();
+    DOWNLOAD_PIECE_PROTOCOL_COUNT.reset();
 }
 
 /// TaskSize represents the size of the task.
@@ -744,6 +1044,182 @@ pub fn collect_proxy_request_via_dfdaemon_metrics() {
         .inc();
 }
 
+/// collect_quic_connection_started_metrics collects the quic connection started metrics.
+pub fn collect_quic_connection_started_metrics() {
+    QUIC_CONNECTION_COUNT.with_label_values(&["accepted"]).inc();
+    CONCURRENT_QUIC_CONNECTION_GAUGE
+        .with_label_values(&[])
+        .inc();
+}
+
+/// collect_quic_connection_handshake_failure_metrics collects the quic connection handshake failure metrics.
+pub fn collect_quic_connection_handshake_failure_metrics() {
+    QUIC_CONNECTION_COUNT
+        .with_label_values(&["handshake_failed"])
+        .inc();
+}
+
+/// collect_quic_connection_finished_metrics collects the quic connection finished metrics.
+pub fn collect_quic_connection_finished_metrics() {
+    CONCURRENT_QUIC_CONNECTION_GAUGE
+        .with_label_values(&[])
+        .dec();
+}
+
+/// collect_quic_connection_refused_metrics collects the quic connection refused metrics.
+pub fn collect_quic_connection_refused_metrics(reason: &str) {
+    QUIC_CONNECTION_COUNT.with_label_values(&[reason]).inc();
+}
+
+/// collect_quic_connection_closed_metrics collects the quic connection closed metrics.
+pub fn collect_quic_connection_closed_metrics(reason: &str) {
+    QUIC_CONNECTION_CLOSED_COUNT
+        .with_label_values(&[reason])
+        .inc();
+}
+
+/// collect_quic_stream_finished_metrics collects the quic stream finished metrics.
+pub fn collect_quic_stream_finished_metrics(typ: &str, result: &str, sent: u64, cost: Duration) {
+    QUIC_STREAM_COUNT.with_label_values(&[typ, result]).inc();
+
+    QUIC_STREAM_DURATION
+        .with_label_values(&[typ])
+        .observe(cost.as_millis() as f64);
+
+    QUIC_UPLOAD_TRAFFIC.with_label_values(&[typ]).inc_by(sent);
+}
+
+/// collect_quic_stream_phase_metrics collects the duration metrics of a phase of the quic
+/// stream, such as receiving the request, reading the storage and sending the response.
+pub fn collect_quic_stream_phase_metrics(typ: &str, phase: &str, cost: Duration) {
+    QUIC_STREAM_PHASE_DURATION
+        .with_label_values(&[typ, phase])
+        .observe(cost.as_millis() as f64);
+}
+
+/// collect_quic_connection_path_metrics collects the round trip time and the packet loss rate of
+/// the closed quic connection.
+pub fn collect_quic_connection_path_metrics(rtt: Duration, loss_rate: f64) {
+    QUIC_CONNECTION_RTT
+        .with_label_values(&[])
+        .observe(rtt.as_secs_f64() * 1000.0);
+    QUIC_CONNECTION_LOSS_RATE
+        .with_label_values(&[])
+        .observe(loss_rate);
+}
+
+/// collect_quic_cert_reload_metrics collects the quic certificate reload metrics.
+pub fn collect_quic_cert_reload_metrics(result: &str) {
+    QUIC_CERT_RELOAD_COUNT.with_label_values(&[result]).inc();
+}
+
+/// collect_quic_request_buffered_bytes_metrics collects the buffered request bytes metrics of the
+/// quic streams, the delta is negative when the request bytes are released.
+pub fn collect_quic_request_buffered_bytes_metrics(delta: i64) {
+    QUIC_REQUEST_BUFFERED_BYTES_GAUGE
+        .with_label_values(&[])
+        .add(delta);
+}
+
+/// collect_quic_buffered_bytes_metrics collects the buffered bytes metrics of the quic streams,
+/// the delta is negative when the buffered bytes are released.
+pub fn collect_quic_buffered_bytes_metrics(delta: i64) {
+    QUIC_BUFFERED_BYTES_GAUGE.with_label_values(&[]).add(delta);
+}
+
+/// collect_quic_dispatch_queue_depth_metrics collects the dispatch queue depth metrics of the
+/// quic streams, the delta is negative when the streams leave the queue.
+pub fn collect_quic_dispatch_queue_depth_metrics(delta: i64) {
+    QUIC_DISPATCH_QUEUE_DEPTH_GAUGE
+        .with_label_values(&[])
+        .add(delta);
+}
+
+/// collect_quic_dispatch_rejected_metrics collects the metrics of the quic streams rejected by
+/// the full dispatch queue.
+pub fn collect_quic_dispatch_rejected_metrics() {
+    QUIC_DISPATCH_REJECTED_COUNT.with_label_values(&[]).inc();
+}
+
+/// collect_quic_storage_worker_queue_depth_metrics collects the queue depth metrics of the
+/// storage workers, the delta is negative when the streams leave the queue.
+pub fn collect_quic_storage_worker_queue_depth_metrics(delta: i64) {
+    QUIC_STORAGE_WORKER_QUEUE_DEPTH_GAUGE
+        .with_label_values(&[])
+        .add(delta);
+}
+
+/// collect_quic_storage_worker_busy_metrics collects the busy metrics of the storage workers,
+/// the delta is negative when the workers are released.
+pub fn collect_quic_storage_worker_busy_metrics(delta: i64) {
+    QUIC_STORAGE_WORKER_BUSY_GAUGE
+        .with_label_values(&[])
+        .add(delta);
+}
+
+/// collect_quic_banned_peer_metrics collects the number of the peers banned by the quic server.
+pub fn collect_quic_banned_peer_metrics(count: usize) {
+    QUIC_BANNED_PEER_GAUGE
+        .with_label_values(&[])
+        .set(count as i64);
+}
+
+/// collect_quic_client_request_metrics collects the request metrics of the quic client, the
+/// result is success or the kind of the error.
+pub fn collect_quic_client_request_metrics(typ: &str, peer: &str, result: &str, cost: Duration) {
+    QUIC_CLIENT_REQUEST_COUNT
+        .with_label_values(&[typ, peer, result])
+        .inc();
+    QUIC_CLIENT_REQUEST_DURATION
+        .with_label_values(&[typ, peer])
+        .observe(cost.as_millis() as f64);
+}
+
+/// collect_quic_client_download_traffic_metrics collects the download traffic metrics of the
+/// quic client.
+pub fn collect_quic_client_download_traffic_metrics(typ: &str, peer: &str, length: u64) {
+    QUIC_CLIENT_DOWNLOAD_TRAFFIC
+        .with_label_values(&[typ, peer])
+        .inc_by(length);
+}
+
+/// collect_quic_client_connect_metrics collects the connection attempt metrics of the quic
+/// client.
+pub fn collect_quic_client_connect_metrics(peer: &str, result: &str) {
+    QUIC_CLIENT_CONNECT_COUNT
+        .with_label_values(&[peer, result])
+        .inc();
+}
+
+/// collect_quic_client_connection_metrics collects the connection metrics of the quic client,
+/// the delta is negative when the connections are closed.
+pub fn collect_quic_client_connection_metrics(peer: &str, delta: i64) {
+    QUIC_CLIENT_CONNECTION_GAUGE
+        .with_label_values(&[peer])
+        .add(delta);
+}
+
+/// collect_quic_client_inflight_streams_metrics collects the in-flight streams metrics of the
+/// quic client to the peer, the delta is negative when the streams are finished.
+pub fn collect_quic_client_inflight_streams_metrics(peer: &str, delta: i64) {
+    QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE
+        .with_label_values(&[peer])
+        .add(delta);
+}
+
+/// collect_quic_client_retry_metrics collects the retry metrics of the quic client requests.
+pub fn collect_quic_client_retry_metrics(typ: &str) {
+    QUIC_CLIENT_RETRY_COUNT.with_label_values(&[typ]).inc();
+}
+
+/// collect_download_piece_protocol_metrics collects the metrics of the protocol serving the
+/// piece downloaded from the parent.
+pub fn collect_download_piece_protocol_metrics(protocol: &str) {
+    DOWNLOAD_PIECE_PROTOCOL_COUNT
+        .with_label_values(&[protocol])
+        .inc();
+}
+
 /// collect_update_task_started_metrics collects the update task started metrics.
 pub fn collect_update_task_started_metrics(typ: i32) {
     UPDATE_TASK_COUNT
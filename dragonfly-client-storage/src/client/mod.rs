@@ -27,6 +27,3 @@ const DEFAULT_RECV_BUFFER_SIZE: usize = 16 * 1024 * 1024;
 
 /// DEFAULT_KEEPALIVE_INTERVAL is the default interval for sending keepalive messages.
 const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
-
-/// DEFAULT_MAX_IDLE_TIMEOUT is the default maximum idle timeout for connections.
-const DEFAULT_MAX_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
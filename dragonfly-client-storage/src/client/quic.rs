@@ -14,65 +14,397 @@
  * limitations under the License.
  */
 
+use crate::ping::{BuildInfo, Datagram};
+use crate::server::quic::{
+    endpoint_config, overloaded_retry_after, QUIC_STREAM_CODE_CANCELLED,
+    QUIC_STREAM_CODE_OVERLOADED, QUIC_STREAM_CODE_QUOTA_EXCEEDED, VORTEX_ERROR_CODE_OVERLOADED,
+};
+use crate::tls::{crypto_provider, initial_suite, QUIC_ALPN};
 use bytes::{Bytes, BytesMut};
-use dragonfly_client_config::dfdaemon::Config;
+use dragonfly_client_config::dfdaemon::{Config, CongestionControl, QUICRetry, QUICVerifyMode};
 use dragonfly_client_core::{
     error::{ErrorType, OrErr},
     Error as ClientError, Result as ClientResult,
 };
+use dragonfly_client_metric::{
+    collect_quic_client_connect_metrics, collect_quic_client_connection_metrics,
+    collect_quic_client_download_traffic_metrics, collect_quic_client_inflight_streams_metrics,
+    collect_quic_client_request_metrics, collect_quic_client_retry_metrics,
+};
+use dragonfly_client_util::{
+    digest::{Algorithm, Hasher, SEPARATOR},
+    tls::{generate_cert_from_pem, generate_key_from_pem},
+};
+use futures::stream::{self, Stream, StreamExt};
 use quinn::crypto::rustls::QuicClientConfig;
-use quinn::{AckFrequencyConfig, ClientConfig, Endpoint, RecvStream, SendStream, TransportConfig};
-use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use quinn::rustls::client::{ClientSessionMemoryCache, Resumption, WebPkiServerVerifier};
+use quinn::rustls::{crypto::CryptoProvider, RootCertStore};
+use quinn::{
+    congestion::{BbrConfig, CubicConfig, NewRenoConfig},
+    AckFrequencyConfig, ClientConfig, Connection, Endpoint, MtuDiscoveryConfig, ReadError,
+    ReadExactError, RecvStream, SendStream, TransportConfig, VarInt, ZeroRttAccepted,
+};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use std::collections::HashMap;
+use std::future::Future;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::io::AsyncRead;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf, Take};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
 use tokio::time;
-use tracing::{error, instrument};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
+use tracing::{debug, error, instrument, warn};
 use vortex_protocol::{
     tlv::{
         download_persistent_cache_piece::DownloadPersistentCachePiece,
-        download_piece::DownloadPiece, error::Error as VortexError, persistent_cache_piece_content,
-        piece_content, Tag,
+        download_piece::DownloadPiece,
+        error::{Code, Error as VortexError},
+        persistent_cache_piece_content, piece_content, Tag,
     },
     Header, Vortex, HEADER_SIZE,
 };
 
-/// QUICClient is a QUIC-based client for quic storage service.
+/// SKIP_VERIFY_WARNING warns once that the verification of the parents' certificates is
+/// skipped.
+static SKIP_VERIFY_WARNING: Once = Once::new();
+
+/// DEFAULT_SESSION_CACHE_SIZE is the default number of the TLS sessions cached for resumption.
+const DEFAULT_SESSION_CACHE_SIZE: usize = 1024;
+
+/// DEFAULT_SERVER_NAME is the server name used if the server name can't be derived from the
+/// address of the parent.
+const DEFAULT_SERVER_NAME: &str = "localhost";
+
+/// DEFAULT_PING_TIMEOUT is the default timeout to wait for the pong of the ping, the ping is
+/// considered lost after the timeout.
+const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// DEFAULT_STREAM_BUFFER_SIZE is the default size of the buffer to stream the piece content into
+/// the writer.
+const DEFAULT_STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// RTT_ESTIMATE_WEIGHT is the weight of the new sample in the moving estimate of the round
+/// trip time and the loss rate.
+const RTT_ESTIMATE_WEIGHT: f64 = 0.125;
+
+/// SESSION_STORE is the TLS session store shared by all quic clients.
+static SESSION_STORE: OnceLock<Arc<ClientSessionMemoryCache>> = OnceLock::new();
+
+/// QUICEndpoint is the client endpoint shared by the quic clients of the parents, so the
+/// connections to the parents share the socket instead of binding one for each parent. The
+/// socket is bound by the first connection.
+#[derive(Clone)]
+pub struct QUICEndpoint {
+    /// config is the configuration of the dfdaemon.
+    config: Arc<Config>,
+
+    /// endpoint is the endpoint bound by the first connection.
+    endpoint: Arc<tokio::sync::OnceCell<Endpoint>>,
+
+    /// circuit_breakers are the circuit breakers of the parents which have failed recently, the
+    /// circuit breaker is removed once the circuit is closed.
+    circuit_breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+
+    /// client_crypto is the TLS config of the connections to the parents.
+    client_crypto: Arc<quinn::rustls::ClientConfig>,
+}
+
+/// QUICEndpoint implements the shared client endpoint.
+impl QUICEndpoint {
+    /// Creates a new QUICEndpoint instance. It fails if the TLS config is invalid, such as
+    /// the client certificate can't be loaded.
+    pub fn new(config: Arc<Config>) -> ClientResult<Self> {
+        let client_crypto = Arc::new(client_crypto(&config)?);
+        Ok(Self {
+            config,
+            endpoint: Arc::new(tokio::sync::OnceCell::new()),
+            circuit_breakers: Arc::new(Mutex::new(HashMap::new())),
+            client_crypto,
+        })
+    }
+
+    /// Returns the state of the circuit to the parent.
+    pub fn circuit_state(&self, addr: &str) -> CircuitState {
+        let cooldown = self.config.download.quic.circuit_breaker.cooldown;
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .get(addr)
+            .map_or(CircuitState::Closed, |circuit_breaker| {
+                circuit_breaker.state(cooldown)
+            })
+    }
+
+    /// Returns the parents whose circuits are not closed, so the scheduler can avoid them.
+    pub fn tripped_peers(&self) -> Vec<(String, CircuitState)> {
+        let cooldown = self.config.download.quic.circuit_breaker.cooldown;
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(addr, circuit_breaker)| (addr.clone(), circuit_breaker.state(cooldown)))
+            .filter(|(_, state)| *state != CircuitState::Closed)
+            .collect()
+    }
+
+    /// Acquires the circuit to the parent for a request. It returns None if the circuit is open,
+    /// or the probe of the half-open circuit is in flight.
+    fn acquire_circuit(&self, addr: &str) -> Option<CircuitGuard> {
+        let config = &self.config.download.quic.circuit_breaker;
+        if config.failure_threshold > 0 {
+            if let Some(circuit_breaker) = self.circuit_breakers.lock().unwrap().get_mut(addr) {
+                if !circuit_breaker.acquire(config.cooldown) {
+                    return None;
+                }
+            }
+        }
+
+        Some(CircuitGuard {
+            endpoint: self.clone(),
+            addr: addr.to_string(),
+            recorded: false,
+        })
+    }
+
+    /// Records the result of the request to the parent, the circuit is opened after the
+    /// consecutive failures, and closed after the success.
+    fn record_circuit(&self, addr: &str, failed: bool) {
+        let failure_threshold = self.config.download.quic.circuit_breaker.failure_threshold;
+        if failure_threshold == 0 {
+            return;
+        }
+
+        let mut circuit_breakers = self.circuit_breakers.lock().unwrap();
+        if !failed {
+            if circuit_breakers.remove(addr).is_some() {
+                debug!("circuit to {} is closed", addr);
+            }
+
+            return;
+        }
+
+        let circuit_breaker = circuit_breakers.entry(addr.to_string()).or_default();
+        if circuit_breaker.record_failure(failure_threshold) {
+            error!(
+                "circuit to {} is opened after {} consecutive failures",
+                addr, circuit_breaker.consecutive_failures
+            );
+        }
+    }
+
+    /// Releases the probe of the half-open circuit which finished without the result, such as
+    /// the request is cancelled, so the next request probes the parent.
+    fn release_circuit(&self, addr: &str) {
+        if let Some(circuit_breaker) = self.circuit_breakers.lock().unwrap().get_mut(addr) {
+            circuit_breaker.probing = false;
+        }
+    }
+
+    /// Returns the endpoint, and binds it if it is not bound yet.
+    async fn get(&self) -> ClientResult<Endpoint> {
+        self.endpoint
+            .get_or_try_init(|| async { self.bind() })
+            .await
+            .cloned()
+    }
+
+    /// Binds the endpoint on the ip of the storage server.
+    fn bind(&self) -> ClientResult<Endpoint> {
+        // Port is zero to let the OS assign an ephemeral port. The endpoint advertises the
+        // upper bound of the path mtu discovery, so the parent can send the larger datagrams.
+        let runtime = quinn::default_runtime()
+            .ok_or_else(|| ClientError::Unknown("no async runtime found".to_string()))?;
+        let socket =
+            std::net::UdpSocket::bind(SocketAddr::new(self.config.storage.server.ip.unwrap(), 0))?;
+        let endpoint = Endpoint::new(
+            endpoint_config(&self.config.download.quic.transport)?,
+            None,
+            socket,
+            runtime,
+        )?;
+
+        Ok(endpoint)
+    }
+}
+
+/// QUICClient is a QUIC-based client for quic storage service. The clones of the client share
+/// the connection, so the requests are sent concurrently from many tasks, each on its own
+/// bidirectional stream of the connection, and they only contend for the connection lookup.
 #[derive(Clone)]
 pub struct QUICClient {
     /// config is the configuration of the dfdaemon.
     config: Arc<Config>,
 
-    /// addr is the address of the QUIC server.
+    /// addr is the address of the QUIC server, the host is either the ip or the hostname.
     addr: String,
+
+    /// server_name overrides the server name of the parent, it takes precedence over the
+    /// configured server name.
+    server_name: Option<String>,
+
+    /// endpoint is the client endpoint, it may be shared with the clients of the other parents.
+    endpoint: QUICEndpoint,
+
+    /// connection is the connection to the server, it is established by the first request and
+    /// shared by the following requests, each request opens its own stream on it.
+    connection: Arc<tokio::sync::Mutex<Option<SharedConnection>>>,
+
+    /// stream_semaphore limits the concurrent streams to the server, it is None if the
+    /// concurrent streams are unlimited.
+    stream_semaphore: Option<Arc<Semaphore>>,
+
+    /// ping_sequence is the sequence number of the next ping.
+    ping_sequence: Arc<AtomicU64>,
+
+    /// rtt_estimate is the moving estimate of the round trip time and the loss rate of the
+    /// pings to the server.
+    rtt_estimate: Arc<Mutex<RttEstimate>>,
+
+    /// server_build_info is the build information of the server carried by the latest pong.
+    server_build_info: Arc<Mutex<Option<BuildInfo>>>,
 }
 
 /// QUICClient implements the QUIC-based client for quic storage service.
 impl QUICClient {
-    /// Creates a new QUICClient instance.
-    pub fn new(config: Arc<Config>, addr: String) -> Self {
-        Self { config, addr }
+    /// Creates a new QUICClient instance with its own endpoint. It fails if the TLS config is
+    /// invalid, such as the client certificate can't be loaded.
+    pub fn new(config: Arc<Config>, addr: String) -> ClientResult<Self> {
+        Ok(Self::with_endpoint(
+            config.clone(),
+            QUICEndpoint::new(config)?,
+            addr,
+        ))
+    }
+
+    /// Creates a new QUICClient instance on the shared endpoint.
+    pub fn with_endpoint(config: Arc<Config>, endpoint: QUICEndpoint, addr: String) -> Self {
+        let max_concurrent_streams = config.download.quic.max_concurrent_streams as usize;
+        Self {
+            config,
+            addr,
+            server_name: None,
+            endpoint,
+            connection: Arc::new(tokio::sync::Mutex::new(None)),
+            stream_semaphore: (max_concurrent_streams > 0)
+                .then(|| Arc::new(Semaphore::new(max_concurrent_streams))),
+            ping_sequence: Arc::new(AtomicU64::new(0)),
+            rtt_estimate: Arc::new(Mutex::new(RttEstimate::default())),
+            server_build_info: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Overrides the server name of the parent for verifying its certificate and SNI, so the
+    /// parents behind the different names are verified by their own names.
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
     }
 
     /// Downloads a piece from the server using the vortex protocol.
     ///
     /// This is the main entry point for downloading a piece. It applies
     /// a timeout based on the configuration and handles connection timeouts gracefully.
+    /// The piece found by the server is returned even if its content is empty, such as the
+    /// piece of the zero-byte file, and the missing piece is returned as the piece not found
+    /// error, so the caller never infers the missing piece from the content length. The
+    /// transient failures are retried with the retry policy.
     #[instrument(skip_all)]
     pub async fn download_piece(
         &self,
         number: u32,
         task_id: &str,
     ) -> ClientResult<(impl AsyncRead, u64, String)> {
-        time::timeout(
-            self.config.download.piece_timeout,
-            self.handle_download_piece(number, task_id),
+        self.download_piece_with_digest(number, task_id, None).await
+    }
+
+    /// Downloads a piece like `download_piece`, and the piece is also verified against the
+    /// expected digest supplied by the caller, such as the digest of the piece metadata from the
+    /// scheduler.
+    ///
+    /// The content is verified against the digest responded by the server while it is read,
+    /// and the read of the corrupt content fails with the invalid data error at the end of the
+    /// piece. The verification is skipped if verify_digest is disabled.
+    #[instrument(skip_all)]
+    pub async fn download_piece_with_digest(
+        &self,
+        number: u32,
+        task_id: &str,
+        expected_digest: Option<&str>,
+    ) -> ClientResult<(impl AsyncRead, u64, String)> {
+        self.download_piece_reader(
+            number,
+            task_id,
+            expected_digest,
+            self.config.download.quic.verify_digest,
+            None,
         )
         .await
-        .inspect_err(|err| {
-            error!("connect timeout to {}: {}", self.addr, err);
-        })?
     }
+
+    /// Downloads a piece like `download_piece`, and the download is aborted once the token is
+    /// cancelled, such as the piece is found from a faster parent.
+    ///
+    /// The cancelled request returns the cancelled error promptly, and the read of the piece
+    /// content fails with the connection aborted error once it is cancelled. The stream is
+    /// stopped and its permit is released, so the server stops sending the rest of the piece.
+    /// Dropping the reader before the content is fully read stops the stream as well.
+    #[instrument(skip_all)]
+    pub async fn download_piece_with_cancel(
+        &self,
+        number: u32,
+        task_id: &str,
+        cancel: CancellationToken,
+    ) -> ClientResult<(impl AsyncRead, u64, String)> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                debug!("download piece {}-{} from {} is cancelled", task_id, number, self.addr);
+                Err(ClientError::QUICCancelled(format!(
+                    "download piece {}-{} from {}",
+                    task_id, number, self.addr
+                )))
+            }
+            result = self.download_piece_reader(
+                number,
+                task_id,
+                None,
+                self.config.download.quic.verify_digest,
+                Some(&cancel),
+            ) => result,
+        }
+    }
+
+    /// Downloads a piece with the retry policy and the piece timeout, and returns the reader of
+    /// the piece content.
+    async fn download_piece_reader(
+        &self,
+        number: u32,
+        task_id: &str,
+        expected_digest: Option<&str>,
+        verify_digest: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> ClientResult<(impl AsyncRead, u64, String)> {
+        self.with_retry("download_piece", move || async move {
+            time::timeout(
+                self.config.download.piece_timeout,
+                self.handle_download_piece(number, task_id, expected_digest, verify_digest, cancel),
+            )
+            .await
+            .inspect_err(|err| {
+                error!(
+                    "download piece {}-{} from {} timeout after {:?}: {}",
+                    task_id, number, self.addr, self.config.download.piece_timeout, err
+                );
+            })?
+        })
+        .await
+    }
+
     /// Internal handler for downloading a piece.
     ///
     /// This method performs the actual protocol communication:
@@ -85,6 +417,9 @@ impl QUICClient {
         &self,
         number: u32,
         task_id: &str,
+        expected_digest: Option<&str>,
+        verify_digest: bool,
+        cancel: Option<&CancellationToken>,
     ) -> ClientResult<(impl AsyncRead, u64, String)> {
         let request: Bytes = Vortex::DownloadPiece(
             Header::new_download_piece(),
@@ -92,18 +427,54 @@ impl QUICClient {
         )
         .into();
 
+        let permit = self.acquire_stream_permit().await?;
         let (mut reader, _writer) = self.connect_and_write_request(request).await?;
-        let header = self.read_header(&mut reader).await?;
+        let header = self
+            .with_request_timeout(self.read_header(&mut reader))
+            .await?;
         match header.tag() {
             Tag::PieceContent => {
                 let piece_content: piece_content::PieceContent = self
-                    .read_piece_content(&mut reader, piece_content::METADATA_LENGTH_SIZE)
+                    .with_request_timeout(
+                        self.read_piece_content(&mut reader, piece_content::METADATA_LENGTH_SIZE),
+                    )
                     .await?;
 
+                // The piece content is streamed in chunks after the metadata, so limit
+                // the reader to the piece length instead of buffering the whole piece.
                 let metadata = piece_content.metadata();
-                Ok((reader, metadata.offset, metadata.digest))
+                self.check_piece_size(number, task_id, metadata.length)?;
+                let hasher = self.piece_hasher(
+                    number,
+                    task_id,
+                    &metadata.digest,
+                    expected_digest,
+                    verify_digest,
+                )?;
+                Ok((
+                    DigestReader::new(
+                        self.progress_timeout_reader(
+                            "download_piece",
+                            reader.take(metadata.length),
+                            permit,
+                            cancel.cloned(),
+                        ),
+                        hasher,
+                        &metadata.digest,
+                        format!("{}-{}", task_id, number),
+                    ),
+                    metadata.offset,
+                    metadata.digest,
+                ))
+            }
+            Tag::Error => {
+                self.with_request_timeout(async {
+                    Err(self
+                        .read_error(&mut reader, header.length() as usize, number, task_id)
+                        .await)
+                })
+                .await
             }
-            Tag::Error => Err(self.read_error(&mut reader, header.length() as usize).await),
             _ => Err(ClientError::Unknown(format!(
                 "unexpected tag: {:?}",
                 header.tag()
@@ -111,6 +482,91 @@ impl QUICClient {
         }
     }
 
+    /// Downloads a piece and streams its content into the writer as it arrives, so the piece
+    /// is not buffered in memory before it is written, such as to the disk.
+    ///
+    /// It returns the offset and the length of the piece, and the digest calculated over the
+    /// streamed bytes with the algorithm of the digest responded by the server. The streamed
+    /// digest is verified against the responded digest unless verify_digest is disabled, and
+    /// the corrupt piece is returned as the corrupt piece error after it is streamed, so the
+    /// caller should discard the written content.
+    #[instrument(skip_all)]
+    pub async fn download_piece_into<W: AsyncWrite + Unpin + ?Sized>(
+        &self,
+        number: u32,
+        task_id: &str,
+        writer: &mut W,
+    ) -> ClientResult<(u64, u64, String)> {
+        // The streamed bytes are verified here, so the corrupt piece is returned as the typed
+        // error instead of the invalid data error of the reader.
+        let (mut reader, offset, digest) = self
+            .download_piece_reader(number, task_id, None, false, None)
+            .await?;
+        let algorithm = digest
+            .split_once(SEPARATOR)
+            .and_then(|(algorithm, _)| algorithm.parse::<Algorithm>().ok())
+            .unwrap_or(Algorithm::Crc32);
+
+        let mut hasher = Hasher::new(algorithm);
+        let mut buffer = vec![0; DEFAULT_STREAM_BUFFER_SIZE];
+        let mut length = 0;
+        loop {
+            let n = reader.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+
+            hasher.update(&buffer[..n]);
+            writer.write_all(&buffer[..n]).await?;
+            length += n as u64;
+        }
+
+        writer.flush().await?;
+        let streamed_digest = hasher.finalize().to_string();
+        if self.config.download.quic.verify_digest && streamed_digest != digest {
+            error!(
+                "piece {}-{} from {} is corrupt, expected digest {}, actual digest {}",
+                task_id, number, self.addr, digest, streamed_digest
+            );
+            return Err(ClientError::CorruptPiece(
+                format!("{}-{}", task_id, number),
+                digest,
+                streamed_digest,
+            ));
+        }
+
+        Ok((offset, length, streamed_digest))
+    }
+
+    /// Downloads the pieces of the task concurrently, and returns the stream of the piece number
+    /// and the result of each piece as the pieces complete, so the order of the results is not
+    /// the order of the numbers.
+    ///
+    /// The pieces are downloaded on the streams of the shared connection, and the number of the
+    /// in-flight pieces is bounded by the concurrency and the concurrent streams to the server.
+    /// Each piece reports its own result, so the failed piece doesn't abort the others, and the
+    /// remaining pieces are cancelled if the returned stream is dropped.
+    pub fn download_pieces<'a>(
+        &'a self,
+        task_id: &'a str,
+        numbers: Vec<u32>,
+        concurrency: usize,
+    ) -> impl Stream<Item = (u32, ClientResult<(Bytes, u64, String)>)> + 'a {
+        stream::iter(numbers)
+            .map(move |number| async move {
+                let result: ClientResult<(Bytes, u64, String)> = async {
+                    let (mut reader, offset, digest) = self.download_piece(number, task_id).await?;
+                    let mut content = Vec::new();
+                    reader.read_to_end(&mut content).await?;
+                    Ok((content.into(), offset, digest))
+                }
+                .await;
+
+                (number, result)
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+
     /// Downloads a persistent cache piece from the server using the vortex protocol.
     ///
     /// Similar to `download_piece` but specifically for persistent cache piece.
@@ -120,14 +576,20 @@ impl QUICClient {
         number: u32,
         task_id: &str,
     ) -> ClientResult<(impl AsyncRead, u64, String)> {
-        time::timeout(
-            self.config.download.piece_timeout,
-            self.handle_download_persistent_cache_piece(number, task_id),
-        )
+        self.with_retry("download_persistent_cache_piece", move || async move {
+            time::timeout(
+                self.config.download.piece_timeout,
+                self.handle_download_persistent_cache_piece(number, task_id),
+            )
+            .await
+            .inspect_err(|err| {
+                error!(
+                    "download persistent cache piece {}-{} from {} timeout after {:?}: {}",
+                    task_id, number, self.addr, self.config.download.piece_timeout, err
+                );
+            })?
+        })
         .await
-        .inspect_err(|err| {
-            error!("connect timeout to {}: {}", self.addr, err);
-        })?
     }
 
     /// Internal handler for downloading a persistent cache piece.
@@ -146,18 +608,52 @@ impl QUICClient {
         )
         .into();
 
+        let permit = self.acquire_stream_permit().await?;
         let (mut reader, _writer) = self.connect_and_write_request(request).await?;
-        let header = self.read_header(&mut reader).await?;
+        let header = self
+            .with_request_timeout(self.read_header(&mut reader))
+            .await?;
         match header.tag() {
             Tag::PersistentCachePieceContent => {
                 let persistent_cache_piece_content: persistent_cache_piece_content::PersistentCachePieceContent =
-                self.read_piece_content(&mut reader, persistent_cache_piece_content::METADATA_LENGTH_SIZE)
+                self.with_request_timeout(self.read_piece_content(&mut reader, persistent_cache_piece_content::METADATA_LENGTH_SIZE))
                 .await?;
 
+                // The piece content is streamed in chunks after the metadata, so limit
+                // the reader to the piece length instead of buffering the whole piece.
                 let metadata = persistent_cache_piece_content.metadata();
-                Ok((reader, metadata.offset, metadata.digest))
+                self.check_piece_size(number, task_id, metadata.length)?;
+                let hasher = self.piece_hasher(
+                    number,
+                    task_id,
+                    &metadata.digest,
+                    None,
+                    self.config.download.quic.verify_digest,
+                )?;
+                Ok((
+                    DigestReader::new(
+                        self.progress_timeout_reader(
+                            "download_persistent_cache_piece",
+                            reader.take(metadata.length),
+                            permit,
+                            None,
+                        ),
+                        hasher,
+                        &metadata.digest,
+                        format!("{}-{}", task_id, number),
+                    ),
+                    metadata.offset,
+                    metadata.digest,
+                ))
+            }
+            Tag::Error => {
+                self.with_request_timeout(async {
+                    Err(self
+                        .read_error(&mut reader, header.length() as usize, number, task_id)
+                        .await)
+                })
+                .await
             }
-            Tag::Error => Err(self.read_error(&mut reader, header.length() as usize).await),
             _ => Err(ClientError::Unknown(format!(
                 "unexpected tag: {:?}",
                 header.tag()
@@ -165,145 +661,1308 @@ impl QUICClient {
         }
     }
 
+    /// Pings the server with a datagram and returns the measured round trip time.
+    ///
+    /// The ping doesn't open a stream, so it is cheap enough to check the liveness of the
+    /// parent and prefer the nearby parents. If the server doesn't support the datagrams, it
+    /// falls back to the round trip time estimated by the handshake. The result is also
+    /// folded into the moving estimate returned by `rtt` and `loss_rate`, and the lost pings
+    /// are retried with the retry policy.
+    #[instrument(skip_all)]
+    pub async fn ping(&self) -> ClientResult<Duration> {
+        self.with_retry("ping", move || self.ping_once()).await
+    }
+
+    /// Pings the server once, and folds the result into the moving estimate.
+    async fn ping_once(&self) -> ClientResult<Duration> {
+        let result = time::timeout(DEFAULT_PING_TIMEOUT, self.handle_ping()).await;
+        let mut rtt_estimate = self.rtt_estimate.lock().unwrap();
+        match result {
+            Ok(Ok(rtt)) => {
+                rtt_estimate.record(Some(rtt));
+                Ok(rtt)
+            }
+            Ok(Err(err)) => Err(err),
+            Err(err) => {
+                debug!("ping to {} is lost", self.addr);
+                rtt_estimate.record(None);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Pings the server periodically in the background, so the moving estimate of the round
+    /// trip time and the loss rate is kept fresh. The task runs until the returned handle is
+    /// aborted.
+    pub fn ping_periodically(&self, interval: Duration) -> JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(interval);
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(err) = client.ping().await {
+                    debug!("failed to ping {}: {}", client.addr, err);
+                }
+            }
+        })
+    }
+
+    /// Returns the state of the circuit to the server.
+    pub fn circuit_state(&self) -> CircuitState {
+        self.endpoint.circuit_state(&self.addr)
+    }
+
+    /// Returns the moving estimate of the round trip time to the server, it is None if no
+    /// ping has succeeded.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt_estimate.lock().unwrap().rtt
+    }
+
+    /// Returns the moving estimate of the loss rate of the pings to the server.
+    pub fn loss_rate(&self) -> f64 {
+        self.rtt_estimate.lock().unwrap().loss_rate
+    }
+
+    /// Returns the build information and the uptime of the server carried by the latest pong,
+    /// it is None if the server hasn't sent it, such as the older servers.
+    pub fn server_build_info(&self) -> Option<BuildInfo> {
+        self.server_build_info.lock().unwrap().clone()
+    }
+
+    /// Internal handler for pinging the server.
+    #[instrument(skip_all)]
+    async fn handle_ping(&self) -> ClientResult<Duration> {
+        let (connection, _) = self.connect().await?;
+        if connection.max_datagram_size().is_none() {
+            debug!(
+                "datagrams are not supported by {}, use the handshake rtt",
+                self.addr
+            );
+            let rtt = connection.rtt();
+            connection.close(VarInt::from_u32(0), b"");
+            return Ok(rtt);
+        }
+
+        let sequence = self.ping_sequence.fetch_add(1, Ordering::Relaxed);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let started_at = Instant::now();
+        connection
+            .send_datagram(
+                Datagram::Ping {
+                    sequence,
+                    timestamp,
+                    build_info: Some(BuildInfo::new(None)),
+                }
+                .into(),
+            )
+            .inspect_err(|err| error!("failed to send ping: {}", err))?;
+
+        // Skip the stale and invalid datagrams until the pong of the ping is received.
+        let result = loop {
+            let bytes = match connection.read_datagram().await {
+                Ok(bytes) => bytes,
+                Err(err) => break Err(err.into()),
+            };
+
+            if let Ok(Datagram::Pong {
+                sequence: pong_sequence,
+                build_info,
+                ..
+            }) = Datagram::try_from(bytes)
+            {
+                if pong_sequence == sequence {
+                    let rtt = started_at.elapsed();
+                    if let Some(build_info) = build_info {
+                        debug!(
+                            "server {} runs version {} of commit {}, uptime {:?}",
+                            self.addr, build_info.version, build_info.commit, build_info.uptime
+                        );
+                        *self.server_build_info.lock().unwrap() = Some(build_info);
+                    }
+
+                    break Ok(rtt);
+                }
+            }
+        };
+
+        connection.close(VarInt::from_u32(0), b"");
+        result
+    }
+
     /// Establishes QUIC connection and writes a vortex protocol request.
     ///
     /// This is a low-level utility function that handles the QUIC connection
     /// lifecycle and request transmission. It ensures proper error handling
-    /// and connection cleanup.
+    /// and connection cleanup. The shared connection may be lost without being
+    /// noticed, such as the server restarts, so the lost connection is dropped
+    /// and the request is sent once more on a new connection.
     #[instrument(skip_all)]
     async fn connect_and_write_request(
         &self,
         request: Bytes,
     ) -> ClientResult<(RecvStream, SendStream)> {
-        let mut client_config = ClientConfig::new(Arc::new(
-            QuicClientConfig::try_from(
-                quinn::rustls::ClientConfig::builder()
-                    .dangerous()
-                    .with_custom_certificate_verifier(NoVerifier::new())
-                    .with_no_client_auth(),
-            )
-            .map_err(|err| {
-                ClientError::Unknown(format!("failed to create quic client config: {}", err))
-            })?,
-        ));
-
-        let mut transport = TransportConfig::default();
-        transport.keep_alive_interval(Some(super::DEFAULT_KEEPALIVE_INTERVAL));
-        transport.max_idle_timeout(Some(super::DEFAULT_MAX_IDLE_TIMEOUT.try_into().unwrap()));
-        transport.ack_frequency_config(Some(AckFrequencyConfig::default()));
-        transport.send_window(super::DEFAULT_SEND_BUFFER_SIZE as u64);
-        transport.receive_window((super::DEFAULT_RECV_BUFFER_SIZE as u32).into());
-        transport.stream_receive_window((super::DEFAULT_RECV_BUFFER_SIZE as u32).into());
-        client_config.transport_config(Arc::new(transport));
-
-        // Port is zero to let the OS assign an ephemeral port.
-        let mut endpoint =
-            Endpoint::client(SocketAddr::new(self.config.storage.server.ip.unwrap(), 0))?;
-        endpoint.set_default_client_config(client_config);
-
-        // Connect's server name used for verifying the certificate. Since we used
-        // NoVerifier, it can be anything.
-        let connection = endpoint
-            .connect(self.addr.parse().or_err(ErrorType::ParseError)?, "d7y")?
+        let (connection, zero_rtt_accepted) = self.connection().await?;
+        match self
+            .write_request(&connection, zero_rtt_accepted, &request)
             .await
-            .inspect_err(|err| error!("failed to connect to {}: {}", self.addr, err))?;
+        {
+            Err(ClientError::QUICConnectionLost(err)) => {
+                debug!("connection to {} is lost: {}, reconnect", self.addr, err);
+                self.remove_connection(&connection).await;
+
+                let (connection, zero_rtt_accepted) = self.connection().await?;
+                self.write_request(&connection, zero_rtt_accepted, &request)
+                    .await
+            }
+            result => result,
+        }
+    }
 
+    /// Opens a stream on the connection and writes the request.
+    async fn write_request(
+        &self,
+        connection: &Connection,
+        zero_rtt_accepted: Option<ZeroRttAccepted>,
+        request: &Bytes,
+    ) -> ClientResult<(RecvStream, SendStream)> {
         let (mut writer, reader) = connection
             .open_bi()
             .await
             .inspect_err(|err| error!("failed to open bi stream: {}", err))?;
+        let written = writer.write_all(request).await;
 
-        writer
-            .write_all(&request)
-            .await
-            .inspect_err(|err| error!("failed to send request: {}", err))?;
+        // The streams of the early data are discarded if the server rejects the 0-RTT, so
+        // the request is sent again after the handshake completes.
+        if let Some(zero_rtt_accepted) = zero_rtt_accepted {
+            if !zero_rtt_accepted.await {
+                debug!("0-RTT is rejected by {}, resend the request", self.addr);
+                let (mut writer, reader) = connection
+                    .open_bi()
+                    .await
+                    .inspect_err(|err| error!("failed to open bi stream: {}", err))?;
+
+                writer
+                    .write_all(request)
+                    .await
+                    .inspect_err(|err| error!("failed to send request: {}", err))?;
+
+                return Ok((reader, writer));
+            }
+        }
 
+        written.inspect_err(|err| error!("failed to send request: {}", err))?;
         Ok((reader, writer))
     }
 
-    /// Reads and parses a vortex protocol header from the QUIC stream.
+    /// Removes the lost connection, so the next request establishes a new one. The connection
+    /// is kept if it has been replaced by the other requests.
+    async fn remove_connection(&self, connection: &Connection) {
+        let mut current = self.connection.lock().await;
+        if current
+            .as_ref()
+            .is_some_and(|current| current.connection.stable_id() == connection.stable_id())
+        {
+            *current = None;
+        }
+    }
+
+    /// Returns the connection to the server. The connection is established by the first request
+    /// and shared by the following requests, and the concurrent first requests wait for the same
+    /// connection instead of establishing the duplicate ones. The closed connection is replaced
+    /// by a new one.
     ///
-    /// The header contains metadata about the following message, including
-    /// the message type (tag) and payload length. This is critical for
-    /// proper protocol message framing.
-    #[instrument(skip_all)]
-    async fn read_header(&self, reader: &mut RecvStream) -> ClientResult<Header> {
-        let mut header_bytes = BytesMut::with_capacity(HEADER_SIZE);
-        header_bytes.resize(HEADER_SIZE, 0);
-        reader
-            .read_exact(&mut header_bytes)
-            .await
-            .inspect_err(|err| error!("failed to receive header: {}", err))?;
+    /// It returns the future of whether the 0-RTT is accepted if the connection is newly
+    /// established with the early data.
+    async fn connection(&self) -> ClientResult<(Connection, Option<ZeroRttAccepted>)> {
+        // The lock is only held to clone the live connection, and the concurrent requests wait
+        // for it only if the connection is being established.
+        let mut connection = self.connection.lock().await;
+        if let Some(SharedConnection { connection, .. }) = connection.as_ref() {
+            if connection.close_reason().is_none() {
+                return Ok((connection.clone(), None));
+            }
 
-        Header::try_from(header_bytes.freeze()).map_err(Into::into)
+            debug!("connection to {} is closed, reconnect", self.addr);
+        }
+
+        let (new_connection, zero_rtt_accepted) = self.connect().await?;
+        *connection = Some(SharedConnection::new(
+            new_connection.clone(),
+            self.metrics_peer(),
+        ));
+        Ok((new_connection, zero_rtt_accepted))
     }
 
-    /// Reads and parses piece content with variable-length metadata.
+    /// Establishes a new QUIC connection to the server.
     ///
-    /// This generic function handles the two-stage reading process for
-    /// piece content: first reading the metadata length, then reading
-    /// the actual metadata, and finally constructing the complete message.
+    /// It returns the future of whether the 0-RTT is accepted if the connection is
+    /// established with the early data.
     #[instrument(skip_all)]
-    async fn read_piece_content<T>(
-        &self,
-        reader: &mut RecvStream,
-        metadata_length_size: usize,
-    ) -> ClientResult<T>
-    where
-        T: TryFrom<Bytes, Error: Into<ClientError>>,
-    {
-        let mut metadata_length_bytes = BytesMut::with_capacity(metadata_length_size);
-        metadata_length_bytes.resize(metadata_length_size, 0);
-        reader
-            .read_exact(&mut metadata_length_bytes)
-            .await
-            .inspect_err(|err| error!("failed to receive metadata length: {}", err))?;
-        let metadata_length = u32::from_be_bytes(metadata_length_bytes[..].try_into()?) as usize;
+    pub(crate) async fn connect(&self) -> ClientResult<(Connection, Option<ZeroRttAccepted>)> {
+        let result = self.handle_connect().await;
+        collect_quic_client_connect_metrics(
+            self.metrics_peer(),
+            if result.is_ok() { "success" } else { "failure" },
+        );
 
-        let mut metadata_bytes = BytesMut::with_capacity(metadata_length);
-        metadata_bytes.resize(metadata_length, 0);
-        reader
-            .read_exact(&mut metadata_bytes)
-            .await
-            .inspect_err(|err| error!("failed to receive metadata: {}", err))?;
+        result
+    }
 
-        let mut content_bytes = BytesMut::with_capacity(metadata_length_size + metadata_length);
-        content_bytes.extend_from_slice(&metadata_length_bytes);
-        content_bytes.extend_from_slice(&metadata_bytes);
-        content_bytes.freeze().try_into().map_err(Into::into)
+    /// Internal handler for establishing a new QUIC connection to the server.
+    async fn handle_connect(&self) -> ClientResult<(Connection, Option<ZeroRttAccepted>)> {
+        // The initial packets are protected by the initial suite regardless of the configured
+        // cipher suites.
+        let mut client_config = ClientConfig::new(Arc::new(
+            QuicClientConfig::with_initial(self.endpoint.client_crypto.clone(), initial_suite())
+                .map_err(|err| {
+                    ClientError::Unknown(format!("failed to create quic client config: {}", err))
+                })?,
+        ));
+
+        let mut transport = TransportConfig::default();
+        let transport_config = &self.config.download.quic.transport;
+        // Keep the momentarily idle connection alive through the NATs, so the next request
+        // doesn't pay the reconnection. The keep-alive is disabled if the interval is zero.
+        let keep_alive_interval = (!transport_config.keep_alive_interval.is_zero())
+            .then_some(transport_config.keep_alive_interval);
+        match keep_alive_interval {
+            Some(interval) => debug!(
+                "keep-alive to {} is enabled with interval {:?}, max idle timeout {:?}",
+                self.addr, interval, transport_config.max_idle_timeout
+            ),
+            None => debug!("keep-alive to {} is disabled", self.addr),
+        }
+        transport.keep_alive_interval(keep_alive_interval);
+        transport.max_idle_timeout(transport_config.max_idle_timeout.try_into().ok());
+        transport.ack_frequency_config(Some(AckFrequencyConfig::default()));
+        transport.send_window(transport_config.send_window.as_u64());
+        transport.receive_window(
+            VarInt::from_u64(transport_config.receive_window.as_u64()).unwrap_or(VarInt::MAX),
+        );
+        transport.stream_receive_window(
+            VarInt::from_u64(transport_config.stream_receive_window.as_u64())
+                .unwrap_or(VarInt::MAX),
+        );
+        transport.max_concurrent_uni_streams(transport_config.max_concurrent_uni_streams.into());
+        transport.initial_rtt(transport_config.initial_rtt);
+        let datagram_receive_buffer_size =
+            transport_config.datagram_receive_buffer_size.as_u64() as usize;
+        transport.datagram_receive_buffer_size(
+            (datagram_receive_buffer_size > 0).then_some(datagram_receive_buffer_size),
+        );
+        match transport_config.congestion_control {
+            CongestionControl::Cubic => {
+                transport.congestion_controller_factory(Arc::new(CubicConfig::default()))
+            }
+            CongestionControl::NewReno => {
+                transport.congestion_controller_factory(Arc::new(NewRenoConfig::default()))
+            }
+            CongestionControl::Bbr => {
+                transport.congestion_controller_factory(Arc::new(BbrConfig::default()))
+            }
+        };
+        transport.mtu_discovery_config(transport_config.mtu_discovery.then(|| {
+            let mut mtu_discovery_config = MtuDiscoveryConfig::default();
+            mtu_discovery_config.upper_bound(transport_config.mtu_discovery_upper_bound);
+            mtu_discovery_config
+        }));
+        transport.enable_segmentation_offload(transport_config.segmentation_offload);
+        client_config.transport_config(Arc::new(transport));
+
+        // Connect's server name used for verifying the certificate and SNI, the resumed
+        // sessions are stored by the server name.
+        let addr = self.resolve_addr().await?;
+        let server_name = self.server_name();
+        let connecting = self
+            .endpoint
+            .get()
+            .await?
+            .connect_with(client_config, addr, &server_name)
+            .map_err(|err| match err {
+                quinn::ConnectError::InvalidServerName(server_name) => {
+                    ClientError::ValidationError(format!(
+                        "invalid server name {} of {}",
+                        server_name, self.addr
+                    ))
+                }
+                err => err.into(),
+            })?;
+        // The 0-RTT is only available if the early data is enabled and there is a session to
+        // resume, otherwise it falls back to the full handshake.
+        let (connection, zero_rtt_accepted) = match connecting.into_0rtt() {
+            Ok((connection, zero_rtt_accepted)) => (connection, Some(zero_rtt_accepted)),
+            Err(connecting) => (
+                time::timeout(self.config.download.quic.connect_timeout, connecting)
+                    .await
+                    .map_err(|_| {
+                        error!("connect timeout to {}", self.addr);
+                        ClientError::QUICConnectTimeout(self.addr.to_string())
+                    })?
+                    .map_err(|err| {
+                        error!("failed to connect to {}: {}", self.addr, err);
+                        ClientError::QUICHandshakeFailed(format!("{}: {}", self.addr, err))
+                    })?,
+                None,
+            ),
+        };
+
+        Ok((connection, zero_rtt_accepted))
     }
 
-    /// Reads and processes error responses from the server.
+    /// Sends the request with the retry policy. The request is retried after the backoff if it
+    /// fails with the transient error, and each attempt establishes a new connection, so the
+    /// attempt after the lost connection doesn't reuse it. The error is returned with the
+    /// number of the attempts if all attempts fail. The request metrics are collected once for
+    /// all the attempts.
     ///
-    /// When the server responds with an error tag, this function reads
-    /// the error payload and converts it into an appropriate client error.
-    /// This provides structured error handling for protocol-level failures.
-    #[instrument(skip_all)]
-    async fn read_error(&self, reader: &mut RecvStream, header_length: usize) -> ClientError {
-        let mut error_bytes = BytesMut::with_capacity(header_length);
-        error_bytes.resize(header_length, 0);
-        if let Err(err) = reader.read_exact(&mut error_bytes).await {
-            error!("failed to receive error: {}", err);
-            return ClientError::Unknown(err.to_string());
+    /// The request fails immediately with the peer unavailable error if the circuit to the
+    /// parent is open, and the result of the request is recorded by the circuit breaker.
+    async fn with_retry<T, F, Fut>(&self, typ: &str, request: F) -> ClientResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let started_at = Instant::now();
+        let result = match self.endpoint.acquire_circuit(&self.addr) {
+            Some(circuit) => {
+                let result = self.retry(typ, request).await;
+                circuit.record(&result);
+                result
+            }
+            None => {
+                debug!("circuit to {} is open, skip {}", self.addr, typ);
+                Err(ClientError::PeerUnavailable(self.addr.clone()))
+            }
         };
 
-        error_bytes
-            .freeze()
-            .try_into()
-            .map(|error: VortexError| {
-                ClientError::VortexProtocolStatus(error.code(), error.message().to_string())
-            })
-            .unwrap_or_else(|err| {
-                error!("failed to extract error: {}", err);
-                ClientError::Unknown(format!("failed to extract error: {}", err))
-            })
+        collect_quic_client_request_metrics(
+            typ,
+            self.metrics_peer(),
+            match &result {
+                Ok(_) => "success",
+                Err(err) => request_result(err),
+            },
+            started_at.elapsed(),
+        );
+
+        result
     }
-}
 
-/// NoVerifier is a verifier for QUIC Client that does not verify the server certificate.
-/// It is used for testing and should not be used in production.
-#[derive(Debug)]
-struct NoVerifier(Arc<quinn::rustls::crypto::CryptoProvider>);
+    /// Sends the request, and retries it after the backoff if it fails with the transient error.
+    async fn retry<T, F, Fut>(&self, typ: &str, mut request: F) -> ClientResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let retry = &self.config.download.quic.retry;
+        let mut attempts = 1;
+        loop {
+            let err = match request().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+
+            if !is_retryable(&err) {
+                return Err(err);
+            }
+
+            if attempts >= retry.max_attempts {
+                if attempts == 1 {
+                    return Err(err);
+                }
+
+                error!(
+                    "{} to {} failed after {} attempts",
+                    typ, self.addr, attempts
+                );
+                return Err(ClientError::QUICRetryExhausted(attempts, Box::new(err)));
+            }
+
+            // Back off for at least the retry-after hint of the overloaded parent.
+            let mut backoff = retry_backoff(retry, attempts);
+            if let ClientError::QUICOverloaded(message) = &err {
+                if let Some(retry_after) = overloaded_retry_after(message) {
+                    backoff = backoff.max(retry_after);
+                }
+            }
+
+            debug!(
+                "retry {} to {} in {:?} after attempt {}: {}",
+                typ, self.addr, backoff, attempts, err
+            );
+            collect_quic_client_retry_metrics(typ);
+            time::sleep(backoff).await;
+            attempts += 1;
+        }
+    }
+
+    /// Waits for a step of the response within the request timeout. Each step receives the
+    /// bytes of the response, so the deadline is reset on the progress of the response. The
+    /// expiry is returned as the quic timeout error, so the caller can tell the stalled parent
+    /// apart from the missing piece and the protocol errors.
+    async fn with_request_timeout<T>(
+        &self,
+        future: impl Future<Output = ClientResult<T>>,
+    ) -> ClientResult<T> {
+        time::timeout(self.config.download.quic.request_timeout, future)
+            .await
+            .map_err(|_| {
+                error!("request timeout to {}", self.addr);
+                ClientError::QUICTimeout(format!("request to {}", self.addr))
+            })?
+    }
+
+    /// Wraps the reader of the piece content, so the read fails if the content stops making
+    /// progress within the request timeout or the download is cancelled, and the stream permit
+    /// is released once the content is fully read.
+    fn progress_timeout_reader(
+        &self,
+        typ: &'static str,
+        reader: Take<RecvStream>,
+        permit: StreamPermit,
+        cancel: Option<CancellationToken>,
+    ) -> ProgressTimeoutReader {
+        ProgressTimeoutReader {
+            reader,
+            typ,
+            timeout: self.config.download.quic.request_timeout,
+            deadline: Box::pin(time::sleep(self.config.download.quic.request_timeout)),
+            addr: self.addr.clone(),
+            peer: self.metrics_peer().to_string(),
+            permit: Some(permit),
+            cancelled: cancel.map(|cancel| Box::pin(cancel.cancelled_owned())),
+        }
+    }
+
+    /// Resolves the address of the parent. The hostname is resolved to the address of the same
+    /// family as the ip of the endpoint, so the endpoint can reach it.
+    async fn resolve_addr(&self) -> ClientResult<SocketAddr> {
+        if let Ok(addr) = self.addr.parse::<SocketAddr>() {
+            return Ok(addr);
+        }
+
+        let local_ip = self.config.storage.server.ip;
+        tokio::net::lookup_host(&self.addr)
+            .await?
+            .find(|addr| local_ip.is_none_or(|local_ip| local_ip.is_ipv4() == addr.is_ipv4()))
+            .ok_or_else(|| {
+                error!("failed to resolve {}", self.addr);
+                ClientError::HostNotFound(self.addr.clone())
+            })
+    }
+
+    /// Returns the server name for verifying the certificate of the parent and SNI. The server
+    /// name overridden for the parent takes precedence over the configured server name, and the
+    /// host of the parent's address is used if neither is set, so the parent addressed by the
+    /// ip is verified against the ip addresses of its certificate.
+    fn server_name(&self) -> String {
+        if let Some(server_name) =
+            self.server_name
+                .as_ref()
+                .or(self.config.download.quic.server_name.as_ref())
+        {
+            return server_name.clone();
+        }
+
+        if let Ok(addr) = self.addr.parse::<SocketAddr>() {
+            return addr.ip().to_string();
+        }
+
+        match self.addr.rsplit_once(':') {
+            Some((host, _)) if !host.is_empty() => host.to_string(),
+            _ => DEFAULT_SERVER_NAME.to_string(),
+        }
+    }
+
+    /// Returns the peer label of the metrics, it is empty unless enable_peer_metrics is
+    /// enabled, so the metrics are aggregated over the parents.
+    fn metrics_peer(&self) -> &str {
+        if self.config.download.quic.enable_peer_metrics {
+            return &self.addr;
+        }
+
+        ""
+    }
+
+    /// Acquires a slot of the concurrent streams to the server before the stream is opened.
+    /// It waits for the in-flight streams to finish if the limit is reached, or fails with the
+    /// busy error if fail_fast_when_busy is enabled.
+    async fn acquire_stream_permit(&self) -> ClientResult<StreamPermit> {
+        let permit = match self.stream_semaphore.clone() {
+            Some(semaphore) if self.config.download.quic.fail_fast_when_busy => {
+                Some(semaphore.try_acquire_owned().map_err(|_| {
+                    debug!("concurrent streams to {} are exhausted", self.addr);
+                    ClientError::QUICBusy(format!("streams to {}", self.addr))
+                })?)
+            }
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire_owned()
+                    .await
+                    .map_err(|err| ClientError::Unknown(err.to_string()))?,
+            ),
+            None => None,
+        };
+
+        Ok(StreamPermit::new(permit, self.metrics_peer()))
+    }
+
+    /// Returns the hasher to verify the content of the piece against the digest responded by the
+    /// server, and checks the responded digest against the expected digest supplied by the
+    /// caller. It returns None if the verification is disabled.
+    fn piece_hasher(
+        &self,
+        number: u32,
+        task_id: &str,
+        digest: &str,
+        expected_digest: Option<&str>,
+        verify_digest: bool,
+    ) -> ClientResult<Option<Hasher>> {
+        if !verify_digest {
+            return Ok(None);
+        }
+
+        if let Some(expected_digest) = expected_digest {
+            if expected_digest != digest {
+                error!(
+                    "piece {}-{} from {} is corrupt, expected digest {}, responded digest {}",
+                    task_id, number, self.addr, expected_digest, digest
+                );
+                return Err(ClientError::CorruptPiece(
+                    format!("{}-{}", task_id, number),
+                    expected_digest.to_string(),
+                    digest.to_string(),
+                ));
+            }
+        }
+
+        let algorithm = digest
+            .split_once(SEPARATOR)
+            .and_then(|(algorithm, _)| algorithm.parse::<Algorithm>().ok())
+            .ok_or_else(|| {
+                ClientError::ValidationError(format!(
+                    "invalid digest {} of piece {}-{}",
+                    digest, task_id, number
+                ))
+            })?;
+
+        Ok(Some(Hasher::new(algorithm)))
+    }
+
+    /// Returns the error if the piece length declared by the parent exceeds the max piece
+    /// size, so the oversized piece is not downloaded.
+    fn check_piece_size(&self, number: u32, task_id: &str, length: u64) -> ClientResult<()> {
+        let max_piece_size = self.config.download.quic.max_piece_size.as_u64();
+        if length > max_piece_size {
+            return Err(ClientError::ValidationError(format!(
+                "piece {}-{} length {} exceeds the max piece size {}",
+                task_id, number, length, max_piece_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Reads and parses a vortex protocol header from the QUIC stream.
+    ///
+    /// The header contains metadata about the following message, including
+    /// the message type (tag) and payload length. This is critical for
+    /// proper protocol message framing. The stream reset by the server with
+    /// the overloaded or the quota exceeded code is returned as the typed error.
+    #[instrument(skip_all)]
+    async fn read_header(&self, reader: &mut RecvStream) -> ClientResult<Header> {
+        let mut header_bytes = BytesMut::with_capacity(HEADER_SIZE);
+        header_bytes.resize(HEADER_SIZE, 0);
+        reader.read_exact(&mut header_bytes).await.map_err(|err| {
+            error!("failed to receive header: {}", err);
+            match err {
+                ReadExactError::ReadError(ReadError::Reset(code))
+                    if code == VarInt::from_u32(QUIC_STREAM_CODE_OVERLOADED) =>
+                {
+                    ClientError::QUICOverloaded(format!("{} resets the stream", self.addr))
+                }
+                ReadExactError::ReadError(ReadError::Reset(code))
+                    if code == VarInt::from_u32(QUIC_STREAM_CODE_QUOTA_EXCEEDED) =>
+                {
+                    ClientError::QUICQuotaExceeded(format!(
+                        "{} resets the stream, reconnect to continue",
+                        self.addr
+                    ))
+                }
+                err => err.into(),
+            }
+        })?;
+
+        Header::try_from(header_bytes.freeze()).map_err(Into::into)
+    }
+
+    /// Reads and parses piece content with variable-length metadata.
+    ///
+    /// This generic function handles the two-stage reading process for
+    /// piece content: first reading the metadata length, then reading
+    /// the actual metadata, and finally constructing the complete message.
+    #[instrument(skip_all)]
+    async fn read_piece_content<T>(
+        &self,
+        reader: &mut RecvStream,
+        metadata_length_size: usize,
+    ) -> ClientResult<T>
+    where
+        T: TryFrom<Bytes, Error: Into<ClientError>>,
+    {
+        let mut metadata_length_bytes = BytesMut::with_capacity(metadata_length_size);
+        metadata_length_bytes.resize(metadata_length_size, 0);
+        reader
+            .read_exact(&mut metadata_length_bytes)
+            .await
+            .inspect_err(|err| error!("failed to receive metadata length: {}", err))?;
+        let metadata_length = u32::from_be_bytes(metadata_length_bytes[..].try_into()?) as usize;
+
+        let mut metadata_bytes = BytesMut::with_capacity(metadata_length);
+        metadata_bytes.resize(metadata_length, 0);
+        reader
+            .read_exact(&mut metadata_bytes)
+            .await
+            .inspect_err(|err| error!("failed to receive metadata: {}", err))?;
+
+        let mut content_bytes = BytesMut::with_capacity(metadata_length_size + metadata_length);
+        content_bytes.extend_from_slice(&metadata_length_bytes);
+        content_bytes.extend_from_slice(&metadata_bytes);
+        content_bytes.freeze().try_into().map_err(Into::into)
+    }
+
+    /// Reads and processes error responses from the server.
+    ///
+    /// When the server responds with an error tag, this function reads
+    /// the error payload and converts it into an appropriate client error.
+    /// This provides structured error handling for protocol-level failures,
+    /// the not found code is mapped to the piece not found error, so the caller
+    /// can tell the missing piece apart from the other failures of the parent.
+    /// The overloaded code is mapped to the overloaded error, so it is retried.
+    #[instrument(skip_all)]
+    async fn read_error(
+        &self,
+        reader: &mut RecvStream,
+        header_length: usize,
+        number: u32,
+        task_id: &str,
+    ) -> ClientError {
+        let mut error_bytes = BytesMut::with_capacity(header_length);
+        error_bytes.resize(header_length, 0);
+        if let Err(err) = reader.read_exact(&mut error_bytes).await {
+            error!("failed to receive error: {}", err);
+            return ClientError::Unknown(err.to_string());
+        };
+
+        error_bytes
+            .freeze()
+            .try_into()
+            .map(|error: VortexError| match error.code() {
+                Code::NotFound => ClientError::PieceNotFound(format!("{}-{}", task_id, number)),
+                Code::Reserved(VORTEX_ERROR_CODE_OVERLOADED) => ClientError::QUICOverloaded(
+                    format!("{} responds {}", self.addr, error.message()),
+                ),
+                code => ClientError::VortexProtocolStatus(code, error.message().to_string()),
+            })
+            .unwrap_or_else(|err| {
+                error!("failed to extract error: {}", err);
+                ClientError::Unknown(format!("failed to extract error: {}", err))
+            })
+    }
+}
+
+/// is_retryable returns whether the request is retried for the error. The lost connection, the
+/// timeout and the overloaded parent are transient, and the other errors, such as the missing
+/// piece and the invalid request, fail the same way on the retries.
+fn is_retryable(err: &ClientError) -> bool {
+    match err {
+        ClientError::QUICConnectionLost(_)
+        | ClientError::QUICHandshakeFailed(_)
+        | ClientError::QUICTimeout(_)
+        | ClientError::QUICConnectTimeout(_)
+        | ClientError::QUICOverloaded(_)
+        | ClientError::TokioTimeErrorElapsed(_) => true,
+        _ => false,
+    }
+}
+
+/// request_result returns the result label of the request metrics for the error of the request.
+fn request_result(err: &ClientError) -> &'static str {
+    match err {
+        ClientError::PieceNotFound(_) => "not_found",
+        ClientError::QUICTimeout(_)
+        | ClientError::QUICConnectTimeout(_)
+        | ClientError::TokioTimeErrorElapsed(_) => "timeout",
+        ClientError::QUICConnectionLost(_) => "connection_lost",
+        ClientError::QUICHandshakeFailed(_) => "handshake_failure",
+        ClientError::QUICBusy(_) => "busy",
+        ClientError::QUICOverloaded(_) => "overloaded",
+        ClientError::QUICQuotaExceeded(_) => "quota_exceeded",
+        ClientError::CorruptPiece(..) => "corrupt",
+        ClientError::VortexProtocolStatus(..) => "server_error",
+        ClientError::PeerUnavailable(_) => "unavailable",
+        ClientError::QUICRetryExhausted(_, err) => request_result(err),
+        _ => "failure",
+    }
+}
+
+/// is_peer_failure returns whether the error shows the parent is unhealthy. They are the
+/// transient errors which are retried, and they open the circuit to the parent if they happen
+/// consecutively.
+fn is_peer_failure(err: &ClientError) -> bool {
+    match err {
+        ClientError::QUICRetryExhausted(_, err) => is_peer_failure(err),
+        err => is_retryable(err),
+    }
+}
+
+/// is_quic_unavailable returns whether the error shows the parent doesn't serve quic, that is
+/// the connection to the parent can't be established, such as the handshake fails or times out.
+/// The caller falls back to the other protocols for the error.
+pub fn is_quic_unavailable(err: &ClientError) -> bool {
+    match err {
+        ClientError::QUICHandshakeFailed(_) | ClientError::QUICConnectTimeout(_) => true,
+        ClientError::QUICRetryExhausted(_, err) => is_quic_unavailable(err),
+        _ => false,
+    }
+}
+
+/// retry_backoff returns the backoff before the retry of the attempt, it grows exponentially
+/// from the initial backoff up to the max backoff. The jitter randomizes the backoff between the
+/// half and the full of it.
+fn retry_backoff(retry: &QUICRetry, attempts: u32) -> Duration {
+    let backoff = retry
+        .initial_backoff
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(retry.max_backoff);
+    if retry.jitter {
+        return backoff.mul_f64(0.5 + fastrand::f64() * 0.5);
+    }
+
+    backoff
+}
+
+/// ProgressTimeoutReader reads the piece content from the parent, and fails the read if no bytes
+/// are received within the timeout. The deadline is reset whenever the bytes are received, so
+/// the long transfers of the large pieces are not interrupted while they are making progress.
+/// The stream is stopped if the download is cancelled, or the reader is dropped before the piece
+/// content is fully read.
+struct ProgressTimeoutReader {
+    /// reader is the reader of the piece content.
+    reader: Take<RecvStream>,
+
+    /// typ is the type of the request, it labels the traffic metrics.
+    typ: &'static str,
+
+    /// timeout is the timeout to wait for the progress of the piece content.
+    timeout: Duration,
+
+    /// deadline fires if no bytes are received within the timeout.
+    deadline: Pin<Box<time::Sleep>>,
+
+    /// addr is the address of the parent.
+    addr: String,
+
+    /// peer is the peer label of the traffic metrics.
+    peer: String,
+
+    /// permit is the slot of the concurrent streams, it is released once the piece content is
+    /// fully read.
+    permit: Option<StreamPermit>,
+
+    /// cancelled fires once the download is cancelled, it is None if the download is not
+    /// cancellable.
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+}
+
+/// ProgressTimeoutReader implements the progress timeout reader.
+impl ProgressTimeoutReader {
+    /// Stops the stream if the piece content is not fully read, so the server stops sending the
+    /// rest of the piece, and releases the stream permit.
+    fn stop(&mut self) {
+        if self.permit.take().is_some() {
+            let _ = self
+                .reader
+                .get_mut()
+                .stop(VarInt::from_u32(QUIC_STREAM_CODE_CANCELLED));
+        }
+    }
+}
+
+/// ProgressTimeoutReader implements AsyncRead.
+impl AsyncRead for ProgressTimeoutReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if let Some(cancelled) = self.cancelled.as_mut() {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                debug!("request to {} is cancelled", self.addr);
+                self.cancelled = None;
+                self.stop();
+                return Poll::Ready(Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionAborted,
+                    ClientError::QUICCancelled(format!("request to {}", self.addr)).to_string(),
+                )));
+            }
+        }
+
+        let filled = buf.filled().len();
+        match Pin::new(&mut self.reader).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                // The stream is finished if no bytes are read into the non-empty buffer.
+                if result.is_ok() && buf.filled().len() == filled && buf.remaining() > 0 {
+                    self.permit = None;
+                }
+
+                let received = (buf.filled().len() - filled) as u64;
+                if received > 0 {
+                    collect_quic_client_download_traffic_metrics(self.typ, &self.peer, received);
+                }
+
+                let deadline = time::Instant::now() + self.timeout;
+                self.deadline.as_mut().reset(deadline);
+                Poll::Ready(result)
+            }
+            Poll::Pending => {
+                if self.deadline.as_mut().poll(cx).is_ready() {
+                    error!("request timeout to {}", self.addr);
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        ClientError::QUICTimeout(format!("request to {}", self.addr)).to_string(),
+                    )));
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// ProgressTimeoutReader implements Drop.
+impl Drop for ProgressTimeoutReader {
+    fn drop(&mut self) {
+        // The reader is dropped before the piece content is fully read, such as the future of
+        // the download is dropped by the caller.
+        self.stop();
+    }
+}
+
+/// DigestReader verifies the digest of the piece content while it is read, and fails the read at
+/// the end of the content if the digest mismatches, so the corrupt piece is not stored.
+struct DigestReader<R> {
+    /// reader is the reader of the piece content.
+    reader: R,
+
+    /// hasher calculates the digest of the read content, it is None if the verification is
+    /// disabled or finished.
+    hasher: Option<Hasher>,
+
+    /// digest is the digest of the piece responded by the server.
+    digest: String,
+
+    /// piece_id is the id of the piece.
+    piece_id: String,
+}
+
+/// DigestReader implements the digest reader.
+impl<R> DigestReader<R> {
+    /// Creates a new DigestReader instance.
+    fn new(reader: R, hasher: Option<Hasher>, digest: &str, piece_id: String) -> Self {
+        Self {
+            reader,
+            hasher,
+            digest: digest.to_string(),
+            piece_id,
+        }
+    }
+}
+
+/// DigestReader implements AsyncRead.
+impl<R: AsyncRead + Unpin> AsyncRead for DigestReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut self.reader).poll_read(cx, buf))?;
+        if buf.filled().len() > filled {
+            if let Some(hasher) = self.hasher.as_mut() {
+                hasher.update(&buf.filled()[filled..]);
+            }
+        } else if buf.remaining() > 0 {
+            // The content is finished if no bytes are read into the non-empty buffer.
+            if let Some(hasher) = self.hasher.take() {
+                let digest = hasher.finalize().to_string();
+                if digest != self.digest {
+                    error!(
+                        "piece {} is corrupt, expected digest {}, actual digest {}",
+                        self.piece_id, self.digest, digest
+                    );
+                    let err = ClientError::CorruptPiece(
+                        self.piece_id.clone(),
+                        self.digest.clone(),
+                        digest,
+                    );
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        err.to_string(),
+                    )));
+                }
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// StreamPermit holds a slot of the concurrent streams to the server, and it is counted by the
+/// in-flight streams metrics until it is released.
+struct StreamPermit {
+    /// _permit is the permit of the stream semaphore, it is None if the concurrent streams are
+    /// unlimited.
+    _permit: Option<OwnedSemaphorePermit>,
+
+    /// peer is the peer label of the in-flight streams metrics.
+    peer: String,
+}
+
+/// StreamPermit implements the stream permit.
+impl StreamPermit {
+    /// Creates a new StreamPermit instance.
+    fn new(permit: Option<OwnedSemaphorePermit>, peer: &str) -> Self {
+        collect_quic_client_inflight_streams_metrics(peer, 1);
+        Self {
+            _permit: permit,
+            peer: peer.to_string(),
+        }
+    }
+}
+
+/// StreamPermit implements Drop.
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        collect_quic_client_inflight_streams_metrics(&self.peer, -1);
+    }
+}
+
+/// SharedConnection is the connection shared by the requests to the server, and it is counted
+/// by the connection metrics until it is replaced or the client is dropped.
+struct SharedConnection {
+    /// connection is the connection to the server.
+    connection: Connection,
+
+    /// peer is the peer label of the connection metrics.
+    peer: String,
+}
+
+/// SharedConnection implements the shared connection.
+impl SharedConnection {
+    /// Creates a new SharedConnection instance.
+    fn new(connection: Connection, peer: &str) -> Self {
+        collect_quic_client_connection_metrics(peer, 1);
+        Self {
+            connection,
+            peer: peer.to_string(),
+        }
+    }
+}
+
+/// SharedConnection implements Drop.
+impl Drop for SharedConnection {
+    fn drop(&mut self) {
+        collect_quic_client_connection_metrics(&self.peer, -1);
+    }
+}
+
+/// CircuitState is the state of the circuit to the parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Closed is the state that the requests are sent to the parent.
+    Closed,
+
+    /// Open is the state that the requests to the parent fail immediately until the cooldown
+    /// elapses.
+    Open,
+
+    /// HalfOpen is the state that the cooldown has elapsed, and a probe request decides whether
+    /// to close the circuit.
+    HalfOpen,
+}
+
+/// CircuitBreaker is the circuit breaker of the parent.
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    /// consecutive_failures is the number of the consecutive failures of the requests.
+    consecutive_failures: u32,
+
+    /// opened_at is the time the circuit is opened, it is None if the circuit is closed.
+    opened_at: Option<Instant>,
+
+    /// probing indicates whether the probe request of the half-open circuit is in flight.
+    probing: bool,
+}
+
+/// CircuitBreaker implements the circuit breaker.
+impl CircuitBreaker {
+    /// Returns the state of the circuit.
+    fn state(&self, cooldown: Duration) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Returns whether the request is allowed. Only one request is allowed as the probe if the
+    /// circuit is half-open.
+    fn acquire(&mut self, cooldown: Duration) -> bool {
+        match self.state(cooldown) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.probing => false,
+            CircuitState::HalfOpen => {
+                self.probing = true;
+                true
+            }
+        }
+    }
+
+    /// Records the failure of the request, and returns whether the circuit is opened by it. The
+    /// failed probe opens the circuit again for another cooldown.
+    fn record_failure(&mut self, failure_threshold: u32) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.probing || self.consecutive_failures >= failure_threshold {
+            self.probing = false;
+            self.opened_at = Some(Instant::now());
+            return true;
+        }
+
+        false
+    }
+}
+
+/// CircuitGuard is the circuit acquired by a request, the probe of the half-open circuit is
+/// released if the request is dropped before its result is recorded.
+struct CircuitGuard {
+    /// endpoint is the endpoint holding the circuit breakers.
+    endpoint: QUICEndpoint,
+
+    /// addr is the address of the parent.
+    addr: String,
+
+    /// recorded indicates whether the result of the request is recorded.
+    recorded: bool,
+}
+
+/// CircuitGuard implements the circuit guard.
+impl CircuitGuard {
+    /// Records the result of the request. Only the failures of the parent are counted, such as
+    /// the timeout and the lost connection, and the other errors, such as the missing piece,
+    /// show the parent is healthy.
+    fn record<T>(mut self, result: &ClientResult<T>) {
+        let failed = result.as_ref().err().is_some_and(is_peer_failure);
+        self.endpoint.record_circuit(&self.addr, failed);
+        self.recorded = true;
+    }
+}
+
+/// CircuitGuard implements Drop.
+impl Drop for CircuitGuard {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.endpoint.release_circuit(&self.addr);
+        }
+    }
+}
+
+/// RttEstimate is the moving estimate of the round trip time and the loss rate of the pings.
+#[derive(Debug, Default)]
+struct RttEstimate {
+    /// rtt is the smoothed round trip time, it is None if no ping has succeeded.
+    rtt: Option<Duration>,
+
+    /// loss_rate is the smoothed ratio of the lost pings.
+    loss_rate: f64,
+}
+
+/// RttEstimate implements the moving estimate.
+impl RttEstimate {
+    /// record folds the sample of a ping into the estimate, the sample is None if the ping is
+    /// lost.
+    fn record(&mut self, sample: Option<Duration>) {
+        let loss = if sample.is_some() { 0.0 } else { 1.0 };
+        self.loss_rate += (loss - self.loss_rate) * RTT_ESTIMATE_WEIGHT;
+
+        if let Some(sample) = sample {
+            self.rtt = Some(match self.rtt {
+                Some(rtt) => {
+                    rtt.mul_f64(1.0 - RTT_ESTIMATE_WEIGHT) + sample.mul_f64(RTT_ESTIMATE_WEIGHT)
+                }
+                None => sample,
+            });
+        }
+    }
+}
+
+/// client_crypto creates the TLS config of the quic clients, it loads the trusted CA
+/// certificates and the client certificate, so the invalid files fail the creation of the
+/// clients instead of their first requests.
+fn client_crypto(config: &Config) -> ClientResult<quinn::rustls::ClientConfig> {
+    // Only offer the configured cipher suites, QUIC requires TLS 1.3.
+    let provider = crypto_provider(&config.download.quic.cipher_suites)?;
+    let builder = quinn::rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&quinn::rustls::version::TLS13])
+        .or_err(ErrorType::TLSConfigError)?;
+
+    // Verify the parent's certificate by the trusted CA certificates, the verification is
+    // only skipped if it is explicitly configured.
+    let builder = match config.download.quic.verify_mode {
+        QUICVerifyMode::Full => {
+            builder.with_webpki_verifier(server_cert_verifier(config, provider)?)
+        }
+        QUICVerifyMode::Skip => {
+            SKIP_VERIFY_WARNING.call_once(|| {
+                warn!(
+                    "quic client skips the verification of the parents' certificates, it is \
+                     insecure and must not be used in production"
+                )
+            });
+
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(NoVerifier::new())
+        }
+    };
+
+    // Present the client certificate if the parent's quic server requires mutual TLS.
+    let mut client_crypto = match client_auth_cert(config)? {
+        Some((certs, key)) => builder.with_client_auth_cert(certs, key).map_err(|err| {
+            ClientError::ValidationError(format!(
+                "invalid quic client cert {:?} or key {:?}: {}",
+                config.download.quic.cert, config.download.quic.key, err
+            ))
+        })?,
+        None => builder.with_no_client_auth(),
+    };
+
+    // Negotiate the vortex protocol, the server rejects the handshakes without it.
+    client_crypto.alpn_protocols = vec![QUIC_ALPN.to_vec()];
+
+    // Share the session store across the clients, so the reconnections to the same parent
+    // resume the TLS session instead of paying the full handshake.
+    let session_store = SESSION_STORE
+        .get_or_init(|| Arc::new(ClientSessionMemoryCache::new(DEFAULT_SESSION_CACHE_SIZE)));
+    client_crypto.resumption = Resumption::store(session_store.clone());
+
+    // Send the request as the early data if the session is resumed, the download requests
+    // are read-only so they are safe to be replayed.
+    client_crypto.enable_early_data = config.download.quic.enable_zero_rtt;
+    Ok(client_crypto)
+}
+
+/// client_auth_cert loads the client certificate and key presented to the parents requiring
+/// mutual TLS, it returns None if the client certificate is not configured.
+#[allow(clippy::type_complexity)]
+fn client_auth_cert(
+    config: &Config,
+) -> ClientResult<Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>> {
+    let (cert_path, key_path) = match (
+        config.download.quic.cert.as_ref(),
+        config.download.quic.key.as_ref(),
+    ) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        (None, None) => return Ok(None),
+        _ => {
+            return Err(ClientError::ValidationError(
+                "quic client cert and key must be configured together".to_string(),
+            ))
+        }
+    };
+
+    let certs = generate_cert_from_pem(cert_path).map_err(|err| {
+        ClientError::ValidationError(format!(
+            "failed to load quic client cert {:?}: {}",
+            cert_path, err
+        ))
+    })?;
+    if certs.is_empty() {
+        return Err(ClientError::ValidationError(format!(
+            "no certificate found in quic client cert {:?}",
+            cert_path
+        )));
+    }
+
+    let key = generate_key_from_pem(key_path).map_err(|err| {
+        ClientError::ValidationError(format!(
+            "failed to load quic client key {:?}: {}",
+            key_path, err
+        ))
+    })?;
+
+    Ok(Some((certs, key)))
+}
+
+/// server_cert_verifier returns the verifier of the parents' certificates, which trusts the CA
+/// certificates in the configured bundle and the root certificates of the platform if they are
+/// enabled. It fails if there is no trusted certificate.
+fn server_cert_verifier(
+    config: &Config,
+    provider: Arc<CryptoProvider>,
+) -> ClientResult<Arc<WebPkiServerVerifier>> {
+    let mut roots = RootCertStore::empty();
+    if let Some(ca_cert_path) = config.download.quic.ca_cert.as_ref() {
+        let ca_certs = generate_cert_from_pem(ca_cert_path).map_err(|err| {
+            ClientError::ValidationError(format!(
+                "failed to load quic CA cert {:?}: {}",
+                ca_cert_path, err
+            ))
+        })?;
+
+        for ca_cert in ca_certs {
+            roots.add(ca_cert).map_err(|err| {
+                ClientError::ValidationError(format!(
+                    "invalid quic CA cert {:?}: {}",
+                    ca_cert_path, err
+                ))
+            })?;
+        }
+    }
+
+    if config.download.quic.enable_native_roots {
+        let native_certs = rustls_native_certs::load_native_certs();
+        for err in native_certs.errors {
+            warn!("failed to load native root certificates: {}", err);
+        }
+
+        let (added, ignored) = roots.add_parsable_certificates(native_certs.certs);
+        debug!(
+            "loaded {} native root certificates, ignored {}",
+            added, ignored
+        );
+    }
+
+    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+        .build()
+        .map_err(|err| {
+            ClientError::ValidationError(format!(
+                "failed to create quic server certificate verifier, configure the caCert or \
+                 enableNativeRoots: {}",
+                err
+            ))
+        })
+}
+
+/// NoVerifier is a verifier for QUIC Client that does not verify the server certificate.
+/// It is used for testing and should not be used in production.
+#[derive(Debug)]
+struct NoVerifier(Arc<quinn::rustls::crypto::CryptoProvider>);
 
 /// NoVerifier implements a no-op server certificate verifier.
 impl NoVerifier {
@@ -364,3 +2023,1271 @@ impl quinn::rustls::client::danger::ServerCertVerifier for NoVerifier {
         self.0.signature_verification_algorithms.supported_schemes()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::quic::tests::{new_quic_server_on, quic_server_config, QUICServerFixture};
+    use bytesize::ByteSize;
+    use dragonfly_api::common::v2::TrafficType;
+    use dragonfly_client_metric::{
+        QUIC_CLIENT_CONNECTION_GAUGE, QUIC_CLIENT_CONNECT_COUNT, QUIC_CLIENT_DOWNLOAD_TRAFFIC,
+        QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE, QUIC_CLIENT_REQUEST_COUNT,
+        QUIC_CLIENT_REQUEST_DURATION,
+    };
+    use dragonfly_client_util::shutdown;
+    use quinn::ServerConfig;
+    use std::path::{Path, PathBuf};
+    use tokio::time::timeout;
+    use vortex_protocol::tlv::piece_content::PieceContent;
+
+    #[test]
+    fn test_quic_endpoint_default_config() {
+        // The certificates are fully verified by default, so the endpoint fails to be created
+        // without any trusted CA instead of silently skipping the verification.
+        assert!(matches!(
+            QUICEndpoint::new(Arc::new(Config::default())),
+            Err(ClientError::ValidationError(_))
+        ));
+
+        // The verification is only skipped if it is explicitly configured.
+        let mut config = Config::default();
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        assert!(QUICEndpoint::new(Arc::new(config)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_keep_alive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+
+        // The server times out the silent connection quickly and doesn't send the keep-alive
+        // packets itself.
+        config.storage.server.quic.transport.max_idle_timeout = Duration::from_millis(300);
+        config.storage.server.quic.transport.keep_alive_interval = Duration::ZERO;
+        let server = QUICServerFixture::start(Arc::new(config.clone())).await;
+        let local_addr = server.local_addr;
+
+        // The connection survives the quiet period longer than the idle timeout with the
+        // keep-alive of the client.
+        config.download.quic.transport.keep_alive_interval = Duration::from_millis(100);
+        let client = QUICClient::new(Arc::new(config.clone()), local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(connection.close_reason().is_none());
+
+        // The connection dies without the keep-alive.
+        config.download.quic.transport.keep_alive_interval = Duration::ZERO;
+        let client = QUICClient::new(Arc::new(config), local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        assert!(matches!(
+            timeout(Duration::from_secs(5), connection.closed())
+                .await
+                .unwrap(),
+            quinn::ConnectionError::TimedOut
+        ));
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_connect_timeout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.connect_timeout = Duration::from_millis(200);
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+
+        // The socket never answers the handshake.
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client =
+            QUICClient::new(Arc::new(config), socket.local_addr().unwrap().to_string()).unwrap();
+        match timeout(Duration::from_secs(5), client.connect())
+            .await
+            .unwrap()
+        {
+            Err(err) => {
+                assert!(matches!(err, ClientError::QUICConnectTimeout(_)));
+                assert!(is_quic_unavailable(&err));
+            }
+            Ok(_) => panic!("unexpected connection"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_verify_server_cert() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (ca_cert_path, cert_path, key_path) =
+            generate_ca_signed_certs(temp_dir.path(), "127.0.0.1");
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.cert = Some(cert_path);
+        config.storage.server.quic.key = Some(key_path);
+        config.download.quic.retry.max_attempts = 1;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let local_addr = server.local_addr;
+
+        // The certificate signed by the trusted CA is verified.
+        let mut client_config = (*config).clone();
+        client_config.download.quic.ca_cert = Some(ca_cert_path);
+        let client = QUICClient::new(Arc::new(client_config), local_addr.to_string()).unwrap();
+        assert!(client.ping().await.is_ok());
+
+        // The handshake fails if the CA isn't trusted.
+        let other_temp_dir = tempfile::tempdir().unwrap();
+        let (other_ca_cert_path, _, _) =
+            generate_ca_signed_certs(other_temp_dir.path(), "127.0.0.1");
+        let mut client_config = (*config).clone();
+        client_config.download.quic.ca_cert = Some(other_ca_cert_path);
+        let client = QUICClient::new(Arc::new(client_config), local_addr.to_string()).unwrap();
+        assert!(is_quic_unavailable(&client.ping().await.unwrap_err()));
+
+        // The client fails to be created without any trusted CA.
+        assert!(matches!(
+            QUICClient::new(config, local_addr.to_string()),
+            Err(ClientError::ValidationError(_))
+        ));
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_server_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (ca_cert_path, cert_path, key_path) =
+            generate_ca_signed_certs(temp_dir.path(), "localhost");
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.cert = Some(cert_path);
+        config.storage.server.quic.key = Some(key_path);
+        config.download.quic.ca_cert = Some(ca_cert_path);
+        config.download.quic.retry.max_attempts = 1;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let local_addr = server.local_addr;
+
+        // The parent addressed by the ip is verified by the ip, which is not in the certificate.
+        let client = QUICClient::new(config.clone(), local_addr.to_string()).unwrap();
+        assert!(is_quic_unavailable(&client.ping().await.unwrap_err()));
+
+        // The handshake fails if the server name doesn't match the certificate.
+        let client = QUICClient::new(config.clone(), local_addr.to_string())
+            .unwrap()
+            .with_server_name("other.example.com");
+        assert!(is_quic_unavailable(&client.ping().await.unwrap_err()));
+
+        // The parent addressed by the hostname is verified by the hostname.
+        let client =
+            QUICClient::new(config.clone(), format!("localhost:{}", local_addr.port())).unwrap();
+        assert!(client.ping().await.is_ok());
+
+        // The parent addressed by the ip is verified by the overridden server name.
+        let client = QUICClient::new(config.clone(), local_addr.to_string())
+            .unwrap()
+            .with_server_name("localhost");
+        assert!(client.ping().await.is_ok());
+
+        // The parent addressed by the ip is verified by the configured server name.
+        let mut client_config = (*config).clone();
+        client_config.download.quic.server_name = Some("localhost".to_string());
+        let client = QUICClient::new(Arc::new(client_config), local_addr.to_string()).unwrap();
+        assert!(client.ping().await.is_ok());
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_mutual_tls() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (ca_cert_path, cert_path, key_path) =
+            generate_ca_signed_certs(temp_dir.path(), "127.0.0.1");
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.ca_cert = Some(ca_cert_path.clone());
+        config.storage.server.quic.cert = Some(cert_path.clone());
+        config.storage.server.quic.key = Some(key_path.clone());
+        config.download.quic.ca_cert = Some(ca_cert_path);
+        config.download.quic.retry.max_attempts = 1;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let local_addr = server.local_addr;
+
+        // The client presenting the certificate signed by the CA is accepted.
+        let mut client_config = (*config).clone();
+        client_config.download.quic.cert = Some(cert_path.clone());
+        client_config.download.quic.key = Some(key_path);
+        let client = QUICClient::new(Arc::new(client_config), local_addr.to_string()).unwrap();
+        assert!(client.ping().await.is_ok());
+
+        // The client without the certificate is rejected.
+        let client = QUICClient::new(config.clone(), local_addr.to_string()).unwrap();
+        assert!(client.ping().await.is_err());
+
+        // The client fails to be created if the key of the certificate is missing.
+        let mut client_config = (*config).clone();
+        client_config.download.quic.cert = Some(cert_path);
+        client_config.download.quic.key = Some(temp_dir.path().join("missing.key"));
+        assert!(matches!(
+            QUICClient::new(Arc::new(client_config), local_addr.to_string()),
+            Err(ClientError::ValidationError(_))
+        ));
+
+        server.stop().await;
+    }
+
+    /// generate_ca_signed_certs generates the test CA and the certificate of the subject
+    /// alternative name signed by it, and returns the paths of the CA certificate, the
+    /// certificate and its key. The name is issued as the ip address entry if it is an ip.
+    fn generate_ca_signed_certs(dir: &Path, subject_alt_name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let mut ca_params = rcgen::CertificateParams::new(Vec::new());
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "Test CA");
+        let ca = rcgen::Certificate::from_params(ca_params).unwrap();
+
+        let mut params = rcgen::CertificateParams::new(Vec::new());
+        params.subject_alt_names = vec![match subject_alt_name.parse::<IpAddr>() {
+            Ok(ip) => rcgen::SanType::IpAddress(ip),
+            Err(_) => rcgen::SanType::DnsName(subject_alt_name.to_string()),
+        }];
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+
+        let ca_cert_path = dir.join("ca.crt");
+        let cert_path = dir.join("server.crt");
+        let key_path = dir.join("server.key");
+        std::fs::write(&ca_cert_path, ca.serialize_pem().unwrap()).unwrap();
+        std::fs::write(&cert_path, cert.serialize_pem_with_signer(&ca).unwrap()).unwrap();
+        std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        (ca_cert_path, cert_path, key_path)
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_request_timeout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.request_timeout = Duration::from_millis(200);
+        config.download.quic.retry.max_attempts = 1;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server_config = quic_server_config(config.clone()).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        // The server accepts the request but never responds.
+        let addr = stalled_quic_server(server_config.clone(), Bytes::new());
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        match timeout(Duration::from_secs(5), client.download_piece(0, task_id))
+            .await
+            .unwrap()
+        {
+            Err(err) => {
+                // The parent serves quic, so the request timeout doesn't fall back.
+                assert!(matches!(err, ClientError::QUICTimeout(_)));
+                assert!(!is_quic_unavailable(&err));
+            }
+            Ok(_) => panic!("download piece from the stalled server"),
+        }
+
+        // The server stalls after the first bytes of the piece content, the content which is
+        // making progress is read until it stalls.
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            8,
+            "crc32:00000000".to_string(),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut response = BytesMut::new();
+        response.extend_from_slice(&header);
+        response.extend_from_slice(&piece_content);
+        response.extend_from_slice(b"data");
+
+        let addr = stalled_quic_server(server_config, response.freeze());
+        let client = QUICClient::new(config, addr.to_string()).unwrap();
+        let (mut reader, _, _) = timeout(Duration::from_secs(5), client.download_piece(0, task_id))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut content = [0u8; 4];
+        reader.read_exact(&mut content).await.unwrap();
+        assert_eq!(&content, b"data");
+        let err = timeout(Duration::from_secs(5), reader.read_u8())
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_shared_endpoint() {
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        let mut temp_dirs = Vec::new();
+        let mut servers = Vec::new();
+        for _ in 0..2 {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let mut config = Config::default();
+            config.storage.dir = temp_dir.path().to_path_buf();
+            config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+            config.download.quic.verify_mode = QUICVerifyMode::Skip;
+            let config = Arc::new(config);
+
+            let server = QUICServerFixture::start(config).await;
+            let storage = server.storage.clone();
+            storage
+                .download_task_started(task_id, 4, 4, None)
+                .await
+                .unwrap();
+            let piece_id = storage.piece_id(task_id, 0);
+            storage.download_piece_started(&piece_id, 0).await.unwrap();
+            storage
+                .download_piece_from_source_finished(
+                    &piece_id,
+                    task_id,
+                    0,
+                    4,
+                    &mut &b"data"[..],
+                    Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+
+            servers.push(server);
+            temp_dirs.push(temp_dir);
+        }
+
+        // The clients of both servers share the endpoint, and the concurrent first requests of
+        // each client share a single connection.
+        let mut config = Config::default();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+        let endpoint = QUICEndpoint::new(config.clone()).unwrap();
+        let mut handles = Vec::new();
+        for server in &servers {
+            let client = QUICClient::with_endpoint(
+                config.clone(),
+                endpoint.clone(),
+                server.local_addr.to_string(),
+            );
+            for _ in 0..8 {
+                let client = client.clone();
+                handles.push(tokio::spawn(async move {
+                    let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+                    let mut content = Vec::new();
+                    reader.read_to_end(&mut content).await.unwrap();
+                    assert_eq!(content, b"data");
+                }));
+            }
+        }
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        for server in servers {
+            assert_eq!(server.stats.snapshot().connections.len(), 1);
+            server.stop().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_reconnect() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.retry.max_attempts = 1;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let storage = server.storage.clone();
+        let local_addr = server.local_addr;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 4, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                4,
+                &mut &b"data"[..],
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let client = QUICClient::new(config.clone(), local_addr.to_string()).unwrap();
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+
+        // Restart the server on the same address, the client replaces the lost connection
+        // without retrying the request.
+        server.stop().await;
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server_on(config, local_addr, storage, shutdown.clone());
+        timeout(Duration::from_secs(5), async {
+            while server.bind().is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let server = tokio::spawn(async move { server.run().await });
+
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_retry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.retry.initial_backoff = Duration::from_millis(10);
+        config.download.quic.retry.jitter = false;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server_config = quic_server_config(config.clone()).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        let error_response = |code, message: &str| -> Bytes {
+            let err = VortexError::new(code, message.to_string());
+            Vortex::Error(Header::new_error(err.len() as u32), err).into()
+        };
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            4,
+            format!("crc32:{}", crc32fast::hash(b"data")),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut piece = BytesMut::new();
+        piece.extend_from_slice(&header);
+        piece.extend_from_slice(&piece_content);
+        piece.extend_from_slice(b"data");
+        let piece = piece.freeze();
+
+        // The piece is downloaded by the third attempt after the parent is overloaded twice.
+        let addr = mock_quic_server(
+            server_config.clone(),
+            vec![
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Bytes(piece.clone()),
+            ],
+        );
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+
+        // The retry backs off for at least the retry-after hint of the overloaded error.
+        let addr = mock_quic_server(
+            server_config.clone(),
+            vec![
+                MockResponse::Bytes(error_response(
+                    Code::Reserved(VORTEX_ERROR_CODE_OVERLOADED),
+                    "peer 127.0.0.1 exceeds the rate limit, retry after 200ms",
+                )),
+                MockResponse::Bytes(piece.clone()),
+            ],
+        );
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        let started_at = Instant::now();
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        assert!(started_at.elapsed() >= Duration::from_millis(200));
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+
+        // The error is returned with the number of the attempts if all attempts fail.
+        let addr = mock_quic_server(
+            server_config.clone(),
+            vec![
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+            ],
+        );
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        match client.download_piece(0, task_id).await {
+            Err(ClientError::QUICRetryExhausted(attempts, err)) => {
+                assert_eq!(attempts, 3);
+                assert!(matches!(*err, ClientError::QUICOverloaded(_)));
+            }
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("download piece from the overloaded server"),
+        }
+
+        // The missing piece is not retried.
+        let addr = mock_quic_server(
+            server_config,
+            vec![
+                MockResponse::Bytes(error_response(Code::NotFound, "piece not found")),
+                MockResponse::Bytes(piece),
+            ],
+        );
+        let client = QUICClient::new(config, addr.to_string()).unwrap();
+        match client.download_piece(0, task_id).await {
+            Err(err) => assert!(matches!(err, ClientError::PieceNotFound(_))),
+            Ok(_) => panic!("retry the missing piece"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_circuit_breaker() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.retry.max_attempts = 1;
+        config.download.quic.circuit_breaker.failure_threshold = 2;
+        config.download.quic.circuit_breaker.cooldown = Duration::from_millis(200);
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server_config = quic_server_config(config.clone()).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            4,
+            format!("crc32:{}", crc32fast::hash(b"data")),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut piece = BytesMut::new();
+        piece.extend_from_slice(&header);
+        piece.extend_from_slice(&piece_content);
+        piece.extend_from_slice(b"data");
+        let piece = piece.freeze();
+
+        let addr = mock_quic_server(
+            server_config,
+            vec![
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Reset(QUIC_STREAM_CODE_OVERLOADED),
+                MockResponse::Bytes(piece.clone()),
+                MockResponse::Bytes(piece),
+            ],
+        );
+        let endpoint = QUICEndpoint::new(config.clone()).unwrap();
+        let client = QUICClient::with_endpoint(config.clone(), endpoint.clone(), addr.to_string());
+
+        // The circuit is opened after the consecutive failures, and the requests fail
+        // immediately without reaching the parent.
+        assert!(client.download_piece(0, task_id).await.is_err());
+        assert_eq!(client.circuit_state(), CircuitState::Closed);
+        assert!(client.download_piece(0, task_id).await.is_err());
+        assert_eq!(client.circuit_state(), CircuitState::Open);
+        assert_eq!(
+            endpoint.tripped_peers(),
+            vec![(addr.to_string(), CircuitState::Open)]
+        );
+        assert!(matches!(
+            client.download_piece(0, task_id).await,
+            Err(ClientError::PeerUnavailable(_))
+        ));
+
+        // The failed probe opens the circuit again after the cooldown.
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(client.circuit_state(), CircuitState::HalfOpen);
+        assert!(matches!(
+            client.download_piece(0, task_id).await,
+            Err(ClientError::QUICOverloaded(_))
+        ));
+        assert_eq!(client.circuit_state(), CircuitState::Open);
+
+        // The succeeded probe closes the circuit, and the following requests are sent to the
+        // parent.
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(client.circuit_state(), CircuitState::HalfOpen);
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+        assert_eq!(client.circuit_state(), CircuitState::Closed);
+        assert!(endpoint.tripped_peers().is_empty());
+        assert!(client.download_piece(0, task_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_download_piece_into() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let storage = server.storage.clone();
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        let content: Vec<u8> = (0..ByteSize::mib(4).as_u64()).map(|i| i as u8).collect();
+        storage
+            .download_task_started(task_id, content.len() as u64, content.len() as u64, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                content.len() as u64,
+                &mut content.as_slice(),
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        let digest = storage.get_piece(&piece_id).unwrap().unwrap().digest;
+
+        let local_addr = server.local_addr;
+
+        // The piece is streamed into the file, and the digest is calculated over the streamed
+        // bytes.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let path = temp_dir.path().join("piece");
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let (offset, length, streamed_digest) = client
+            .download_piece_into(0, task_id, &mut file)
+            .await
+            .unwrap();
+        assert_eq!(offset, 0);
+        assert_eq!(length, content.len() as u64);
+        assert_eq!(streamed_digest, digest);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), content);
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_verify_digest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server_config = quic_server_config(config.clone()).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        // The parent responds the digest of the content, but a byte of the content is flipped.
+        let digest = format!("crc32:{}", crc32fast::hash(b"data"));
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            4,
+            digest.clone(),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut piece = BytesMut::new();
+        piece.extend_from_slice(&header);
+        piece.extend_from_slice(&piece_content);
+        piece.extend_from_slice(b"dbta");
+        let piece = piece.freeze();
+
+        // The read of the corrupt piece fails at the end of the content.
+        let addr = mock_quic_server(
+            server_config.clone(),
+            vec![MockResponse::Bytes(piece.clone())],
+        );
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        let err = reader.read_to_end(&mut content).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // The corrupt piece is returned as the corrupt piece error if it is streamed into the
+        // writer.
+        let addr = mock_quic_server(
+            server_config.clone(),
+            vec![MockResponse::Bytes(piece.clone())],
+        );
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        let mut writer = Vec::new();
+        match client.download_piece_into(0, task_id, &mut writer).await {
+            Err(ClientError::CorruptPiece(_, expected, _)) => assert_eq!(expected, digest),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("download the corrupt piece"),
+        }
+
+        // The responded digest mismatches the expected digest of the caller.
+        let addr = mock_quic_server(
+            server_config.clone(),
+            vec![MockResponse::Bytes(piece.clone())],
+        );
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        assert!(matches!(
+            client
+                .download_piece_with_digest(0, task_id, Some("crc32:0"))
+                .await,
+            Err(ClientError::CorruptPiece(..))
+        ));
+
+        // The corrupt piece is read if the verification is disabled.
+        let mut client_config = (*config).clone();
+        client_config.download.quic.verify_digest = false;
+        let addr = mock_quic_server(server_config, vec![MockResponse::Bytes(piece)]);
+        let client = QUICClient::new(Arc::new(client_config), addr.to_string()).unwrap();
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"dbta");
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_cancel() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server_config = quic_server_config(config.clone()).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            ByteSize::mib(16).as_u64(),
+            "crc32:0".to_string(),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut response = BytesMut::new();
+        response.extend_from_slice(&header);
+        response.extend_from_slice(&piece_content);
+        let response = response.freeze();
+
+        // The download is cancelled in the middle of the piece content, the read returns
+        // promptly and the server observes the stream is stopped.
+        let (addr, stopped) = slow_quic_server(server_config.clone(), response.clone());
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        let cancel = CancellationToken::new();
+        let (mut reader, _, _) = client
+            .download_piece_with_cancel(0, task_id, cancel.clone())
+            .await
+            .unwrap();
+        let mut buffer = vec![0; 1024];
+        assert!(reader.read(&mut buffer).await.unwrap() > 0);
+
+        cancel.cancel();
+        let err = timeout(Duration::from_secs(1), reader.read_to_end(&mut Vec::new()))
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionAborted);
+        assert_eq!(
+            timeout(Duration::from_secs(5), stopped)
+                .await
+                .unwrap()
+                .unwrap(),
+            VarInt::from_u32(QUIC_STREAM_CODE_CANCELLED)
+        );
+
+        // The stream is stopped as well if the reader is dropped in the middle of the piece
+        // content.
+        let (addr, stopped) = slow_quic_server(server_config.clone(), response);
+        let client = QUICClient::new(config.clone(), addr.to_string()).unwrap();
+        let (mut reader, _, _) = client
+            .download_piece_with_cancel(0, task_id, CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(reader.read(&mut buffer).await.unwrap() > 0);
+
+        drop(reader);
+        assert_eq!(
+            timeout(Duration::from_secs(5), stopped)
+                .await
+                .unwrap()
+                .unwrap(),
+            VarInt::from_u32(QUIC_STREAM_CODE_CANCELLED)
+        );
+
+        // The request is cancelled before the server responds.
+        let addr = stalled_quic_server(server_config, Bytes::new());
+        let client = QUICClient::new(config, addr.to_string()).unwrap();
+        let cancel = CancellationToken::new();
+        tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                cancel.cancel();
+            }
+        });
+
+        match timeout(
+            Duration::from_secs(1),
+            client.download_piece_with_cancel(0, task_id, cancel),
+        )
+        .await
+        .unwrap()
+        {
+            Err(err) => assert!(matches!(err, ClientError::QUICCancelled(_))),
+            Ok(_) => panic!("download piece after the cancellation"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_metrics() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.enable_peer_metrics = true;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let storage = server.storage.clone();
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 4, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                4,
+                &mut b"data".as_slice(),
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let local_addr = server.local_addr;
+
+        // The metrics are labeled with the address of the parent, so they are not mixed with
+        // the metrics of the other tests.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let peer = local_addr.to_string();
+        for _ in 0..2 {
+            let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content).await.unwrap();
+        }
+        assert!(client.download_piece(1, task_id).await.is_err());
+
+        assert_eq!(
+            QUIC_CLIENT_REQUEST_COUNT
+                .with_label_values(&["download_piece", &peer, "success"])
+                .get(),
+            2
+        );
+        assert_eq!(
+            QUIC_CLIENT_REQUEST_COUNT
+                .with_label_values(&["download_piece", &peer, "not_found"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            QUIC_CLIENT_REQUEST_DURATION
+                .with_label_values(&["download_piece", &peer])
+                .get_sample_count(),
+            3
+        );
+        assert_eq!(
+            QUIC_CLIENT_DOWNLOAD_TRAFFIC
+                .with_label_values(&["download_piece", &peer])
+                .get(),
+            8
+        );
+        assert_eq!(
+            QUIC_CLIENT_CONNECT_COUNT
+                .with_label_values(&[&peer, "success"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            QUIC_CLIENT_CONNECTION_GAUGE
+                .with_label_values(&[&peer])
+                .get(),
+            1
+        );
+        assert_eq!(
+            QUIC_CLIENT_INFLIGHT_STREAMS_GAUGE
+                .with_label_values(&[&peer])
+                .get(),
+            0
+        );
+
+        // The shared connection is no longer counted once the client is dropped.
+        drop(client);
+        assert_eq!(
+            QUIC_CLIENT_CONNECTION_GAUGE
+                .with_label_values(&[&peer])
+                .get(),
+            0
+        );
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_download_pieces() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server = QUICServerFixture::start(config.clone()).await;
+        let storage = server.storage.clone();
+
+        // Write a task with 20 pieces of 4 bytes to the storage, except the piece 7.
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 80, None)
+            .await
+            .unwrap();
+        for number in (0..20).filter(|number| *number != 7) {
+            let piece_id = storage.piece_id(task_id, number);
+            storage
+                .download_piece_started(&piece_id, number)
+                .await
+                .unwrap();
+            storage
+                .download_piece_from_source_finished(
+                    &piece_id,
+                    task_id,
+                    number as u64 * 4,
+                    4,
+                    &mut format!("{:04}", number).as_bytes(),
+                    Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+        }
+
+        let local_addr = server.local_addr;
+
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let results: HashMap<u32, ClientResult<(Bytes, u64, String)>> = client
+            .download_pieces(task_id, (0..20).collect(), 4)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect();
+        assert_eq!(results.len(), 20);
+        for (number, result) in results {
+            match result {
+                Ok((content, offset, _)) => {
+                    assert_eq!(content, format!("{:04}", number).as_bytes());
+                    assert_eq!(offset, number as u64 * 4);
+                }
+                Err(err) => {
+                    assert_eq!(number, 7);
+                    assert!(matches!(err, ClientError::PieceNotFound(_)));
+                }
+            }
+        }
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_concurrent_requests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let server_config = quic_server_config(config.clone()).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            4,
+            format!("crc32:{}", crc32fast::hash(b"data")),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut piece = BytesMut::new();
+        piece.extend_from_slice(&header);
+        piece.extend_from_slice(&piece_content);
+        piece.extend_from_slice(b"data");
+
+        // Each response is delayed, so the serialized requests would take 256 times the delay.
+        let delay = Duration::from_millis(100);
+        let addr = delayed_quic_server(server_config, piece.freeze(), delay);
+        let client = QUICClient::new(config, addr.to_string()).unwrap();
+        let started_at = Instant::now();
+        let mut handles = Vec::new();
+        for _ in 0..256 {
+            let client = client.clone();
+            handles.push(tokio::spawn(async move {
+                let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content).await.unwrap();
+                assert_eq!(content, b"data");
+            }));
+        }
+
+        for handle in handles {
+            timeout(Duration::from_secs(10), handle)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        assert!(started_at.elapsed() < delay * 256 / 4);
+    }
+
+    #[tokio::test]
+    async fn test_quic_client_max_concurrent_streams() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.max_concurrent_streams = 4;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let server_config = quic_server_config(Arc::new(config.clone())).await;
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+
+        let piece_content: Bytes = PieceContent::new(
+            0,
+            0,
+            4,
+            format!("crc32:{}", crc32fast::hash(b"data")),
+            String::new(),
+            TrafficType::RemotePeer as u8,
+            Duration::ZERO,
+            chrono::Utc::now().naive_utc(),
+        )
+        .into();
+        let header: Bytes = Header::new_piece_content(piece_content.len() as u32).into();
+        let mut piece = BytesMut::new();
+        piece.extend_from_slice(&header);
+        piece.extend_from_slice(&piece_content);
+        piece.extend_from_slice(b"data");
+        let piece = piece.freeze();
+
+        // The requests beyond the limit wait, so 12 requests complete in 3 waves of 4.
+        let delay = Duration::from_millis(200);
+        let addr = delayed_quic_server(server_config.clone(), piece.clone(), delay);
+        let client = QUICClient::new(Arc::new(config.clone()), addr.to_string()).unwrap();
+        let started_at = Instant::now();
+        let mut handles = Vec::new();
+        for _ in 0..12 {
+            let client = client.clone();
+            handles.push(tokio::spawn(async move {
+                let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content).await.unwrap();
+                assert_eq!(content, b"data");
+            }));
+        }
+
+        for handle in handles {
+            timeout(Duration::from_secs(10), handle)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= delay * 3);
+        assert!(elapsed < delay * 9);
+
+        // The request beyond the limit fails with the busy error if fail fast is enabled, and
+        // the stream is released once the content is fully read.
+        config.download.quic.max_concurrent_streams = 1;
+        config.download.quic.fail_fast_when_busy = true;
+        let addr = delayed_quic_server(server_config, piece, delay);
+        let client = QUICClient::new(Arc::new(config), addr.to_string()).unwrap();
+        let (mut reader, _, _) = client.download_piece(0, task_id).await.unwrap();
+        match client.download_piece(0, task_id).await {
+            Err(err) => assert!(matches!(err, ClientError::QUICBusy(_))),
+            Ok(_) => panic!("exceed the max concurrent streams"),
+        }
+
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+        assert!(client.download_piece(0, task_id).await.is_ok());
+    }
+
+    /// delayed_quic_server starts the quic server which accepts a connection, and writes the
+    /// response to each stream of the connection after the delay. The streams are served
+    /// concurrently.
+    fn delayed_quic_server(
+        server_config: ServerConfig,
+        response: Bytes,
+        delay: Duration,
+    ) -> SocketAddr {
+        let endpoint = Endpoint::server(server_config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            while let Ok((mut writer, reader)) = connection.accept_bi().await {
+                let response = response.clone();
+                tokio::spawn(async move {
+                    let _reader = reader;
+                    tokio::time::sleep(delay).await;
+                    writer.write_all(&response).await.unwrap();
+                    writer.finish().unwrap();
+                    let _ = writer.stopped().await;
+                });
+            }
+        });
+
+        addr
+    }
+
+    /// MockResponse is the response of a stream of the mock quic server.
+    enum MockResponse {
+        /// Bytes writes the bytes and finishes the stream.
+        Bytes(Bytes),
+
+        /// Reset resets the stream with the error code.
+        Reset(u32),
+    }
+
+    /// mock_quic_server starts the quic server which accepts a connection, and writes the
+    /// responses in order, one for each stream of the connection.
+    fn mock_quic_server(server_config: ServerConfig, responses: Vec<MockResponse>) -> SocketAddr {
+        let endpoint = Endpoint::server(server_config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            let mut readers = Vec::new();
+            for response in responses {
+                let (mut writer, reader) = connection.accept_bi().await.unwrap();
+                match response {
+                    MockResponse::Bytes(response) => {
+                        writer.write_all(&response).await.unwrap();
+                        writer.finish().unwrap();
+                    }
+                    MockResponse::Reset(code) => writer.reset(VarInt::from_u32(code)).unwrap(),
+                }
+
+                // Hold the request stream, so the client reads the response before the stream
+                // is stopped.
+                readers.push(reader);
+            }
+
+            std::future::pending::<()>().await;
+        });
+
+        addr
+    }
+
+    /// slow_quic_server starts the quic server which writes the response to the first stream,
+    /// and then writes the piece content slowly until the stream is stopped by the client. The
+    /// error code of the stop is sent to the returned receiver.
+    fn slow_quic_server(
+        server_config: ServerConfig,
+        response: Bytes,
+    ) -> (SocketAddr, tokio::sync::oneshot::Receiver<VarInt>) {
+        let endpoint = Endpoint::server(server_config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        let (stopped_tx, stopped_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            let (mut writer, _reader) = connection.accept_bi().await.unwrap();
+            writer.write_all(&response).await.unwrap();
+            loop {
+                match writer.write_all(&[0; 4096]).await {
+                    Ok(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                    Err(quinn::WriteError::Stopped(code)) => {
+                        let _ = stopped_tx.send(code);
+                        break;
+                    }
+                    Err(err) => panic!("unexpected error {}", err),
+                }
+            }
+
+            std::future::pending::<()>().await;
+        });
+
+        (addr, stopped_rx)
+    }
+
+    /// stalled_quic_server starts the quic server which writes the response to the first
+    /// stream and then stalls without finishing it.
+    fn stalled_quic_server(server_config: ServerConfig, response: Bytes) -> SocketAddr {
+        let endpoint = Endpoint::server(server_config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            let (mut writer, _reader) = connection.accept_bi().await.unwrap();
+            writer.write_all(&response).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        addr
+    }
+}
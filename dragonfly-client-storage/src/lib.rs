@@ -19,11 +19,11 @@ use chrono::NaiveDateTime;
 use dragonfly_api::common::v2::Range;
 use dragonfly_client_config::dfdaemon::Config;
 use dragonfly_client_core::{Error, Result};
-use dragonfly_client_util::digest::{Algorithm, Digest};
+use dragonfly_client_util::digest::{verify_reader_digest, Algorithm, Digest};
 use reqwest::header::HeaderMap;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
@@ -42,12 +42,19 @@ pub mod cache;
 pub mod client;
 pub mod content;
 pub mod metadata;
+pub mod pin;
+pub mod ping;
 pub mod server;
 pub mod storage_engine;
+pub mod tls;
 
 /// DEFAULT_WAIT_FOR_PIECE_FINISHED_INTERVAL is the default interval for waiting for the piece to be finished.
 pub const DEFAULT_WAIT_FOR_PIECE_FINISHED_INTERVAL: Duration = Duration::from_millis(100);
 
+/// DEFAULT_EVICTED_PIECES_CAPACITY is the default capacity of the metadata of the evicted pieces
+/// kept for the persistent cache fallback.
+const DEFAULT_EVICTED_PIECES_CAPACITY: usize = 65536;
+
 /// Storage is the storage of the task.
 pub struct Storage {
     /// config is the configuration of the dfdaemon.
@@ -61,6 +68,13 @@ pub struct Storage {
 
     /// cache implements the cache storage.
     cache: cache::Cache,
+
+    /// pins coordinates the pieces being served with the task deletion.
+    pins: Arc<pin::TaskPins>,
+
+    /// evicted_pieces keeps the metadata of the finished pieces of the deleted tasks, so the
+    /// evicted pieces can still be served from the identical persistent cache pieces.
+    evicted_pieces: Mutex<cache::lru_cache::LruCache<String, metadata::Piece>>,
 }
 
 /// Storage implements the storage.
@@ -76,6 +90,10 @@ impl Storage {
             metadata,
             content,
             cache,
+            pins: pin::TaskPins::new(),
+            evicted_pieces: Mutex::new(cache::lru_cache::LruCache::new(
+                DEFAULT_EVICTED_PIECES_CAPACITY,
+            )),
         })
     }
 
@@ -186,9 +204,35 @@ impl Storage {
         self.metadata.get_tasks()
     }
 
-    /// delete_task deletes the task metadatas, task content and piece metadatas.
+    /// pin_task pins the task while its pieces are being served, so the task is not deleted
+    /// until the returned guard is dropped. It returns the task not found error if the task is
+    /// being deleted, instead of serving the piece halfway.
+    pub fn pin_task(&self, id: &str) -> Result<pin::TaskPinGuard> {
+        self.pins
+            .pin(id)
+            .ok_or_else(|| Error::TaskNotFound(id.to_string()))
+    }
+
+    /// delete_task deletes the task metadatas, task content and piece metadatas. It waits for
+    /// the pieces of the task being served to finish before deleting the task.
     #[instrument(skip_all)]
     pub async fn delete_task(&self, id: &str) {
+        self.pins.start_eviction(id).await;
+
+        // Keep the metadata of the evicted pieces, so they can be served from the identical
+        // persistent cache pieces.
+        if self.config.storage.server.quic.persistent_cache_fallback {
+            match self.metadata.get_pieces(id) {
+                Ok(pieces) => {
+                    let mut evicted_pieces = self.evicted_pieces.lock().unwrap();
+                    for piece in pieces.into_iter().filter(|piece| piece.is_finished()) {
+                        evicted_pieces.put(self.metadata.piece_id(id, piece.number), piece);
+                    }
+                }
+                Err(err) => error!("get piece metadatas failed: {}", err),
+            }
+        }
+
         self.metadata
             .delete_task(id)
             .unwrap_or_else(|err| error!("delete task metadata failed: {}", err));
@@ -205,6 +249,8 @@ impl Storage {
         cache.delete_task(id).await.unwrap_or_else(|err| {
             info!("delete task from cache failed: {}", err);
         });
+
+        self.pins.finish_eviction(id);
     }
 
     /// hard_link_persistent_cache_task hard links the persistent cache task content to the destination.
@@ -354,8 +400,10 @@ impl Storage {
     }
 
     /// delete_persistent_cache_task deletes the persistent cache task metadatas, persistent cache task content and piece metadatas.
+    /// It waits for the pieces of the persistent cache task being served to finish before deleting the task.
     #[instrument(skip_all)]
     pub async fn delete_persistent_cache_task(&self, id: &str) {
+        self.pins.start_eviction(id).await;
         self.metadata
             .delete_persistent_cache_task(id)
             .unwrap_or_else(|err| {
@@ -372,6 +420,8 @@ impl Storage {
             .unwrap_or_else(|err| {
                 error!("delete persistent cache task content failed: {}", err);
             });
+
+        self.pins.finish_eviction(id);
     }
 
     /// prepare_download_cache_task_started prepares the metadata of the cache task when the cache task downloads
@@ -693,6 +743,30 @@ impl Storage {
         }
     }
 
+    /// verify_piece_digest reads the content of the piece and verifies it against the digest
+    /// of the piece metadata, it does not change the upload state of the task.
+    #[instrument(skip_all)]
+    pub async fn verify_piece_digest(&self, piece_id: &str, task_id: &str) -> Result<()> {
+        let piece = self
+            .metadata
+            .get_piece(piece_id)?
+            .ok_or_else(|| Error::PieceNotFound(piece_id.to_string()))?;
+
+        if self.cache.contains_piece(task_id, piece_id).await {
+            let mut reader = self
+                .cache
+                .read_piece(task_id, piece_id, piece.clone(), None)
+                .await?;
+            return verify_reader_digest(piece.digest.as_str(), &mut reader).await;
+        }
+
+        let mut reader = self
+            .content
+            .read_piece(task_id, piece.offset, piece.length, None)
+            .await?;
+        verify_reader_digest(piece.digest.as_str(), &mut reader).await
+    }
+
     /// get_piece returns the piece metadata.
     pub fn get_piece(&self, piece_id: &str) -> Result<Option<metadata::Piece>> {
         self.metadata.get_piece(piece_id)
@@ -710,6 +784,12 @@ impl Storage {
         self.metadata.get_pieces(task_id)
     }
 
+    /// get_evicted_piece returns the metadata of the finished piece of the deleted task, it is
+    /// kept only if the persistent cache fallback is enabled.
+    pub fn get_evicted_piece(&self, piece_id: &str) -> Option<metadata::Piece> {
+        self.evicted_pieces.lock().unwrap().peek(piece_id).cloned()
+    }
+
     /// piece_id returns the piece id.
     #[inline]
     pub fn piece_id(&self, task_id: &str, number: u32) -> String {
@@ -832,6 +912,27 @@ impl Storage {
         }
     }
 
+    /// verify_persistent_cache_piece_digest reads the content of the persistent cache piece and
+    /// verifies it against the digest of the piece metadata, it does not change the upload
+    /// state of the persistent cache task.
+    #[instrument(skip_all)]
+    pub async fn verify_persistent_cache_piece_digest(
+        &self,
+        piece_id: &str,
+        task_id: &str,
+    ) -> Result<()> {
+        let piece = self
+            .metadata
+            .get_piece(piece_id)?
+            .ok_or_else(|| Error::PieceNotFound(piece_id.to_string()))?;
+
+        let mut reader = self
+            .content
+            .read_persistent_cache_piece(task_id, piece.offset, piece.length, None)
+            .await?;
+        verify_reader_digest(piece.digest.as_str(), &mut reader).await
+    }
+
     /// get_persistent_cache_piece returns the persistent cache piece metadata.
     #[instrument(skip_all)]
     pub fn get_persistent_cache_piece(&self, piece_id: &str) -> Result<Option<metadata::Piece>> {
@@ -849,6 +950,40 @@ impl Storage {
         self.metadata.get_pieces(task_id)
     }
 
+    /// find_persistent_cache_piece returns the finished persistent cache piece whose digest and
+    /// length are identical to the given ones, and the id of its persistent cache task. It scans
+    /// the pieces of the finished persistent cache tasks, so it is only used as the fallback of
+    /// the missing pieces.
+    #[instrument(skip_all)]
+    pub fn find_persistent_cache_piece(
+        &self,
+        digest: &str,
+        length: u64,
+    ) -> Result<Option<(String, metadata::Piece)>> {
+        if digest.is_empty() {
+            return Ok(None);
+        }
+
+        for task in self.metadata.get_persistent_cache_tasks()? {
+            if !task.is_finished() {
+                continue;
+            }
+
+            if let Some(piece) = self
+                .metadata
+                .get_pieces(&task.id)?
+                .into_iter()
+                .find(|piece| {
+                    piece.is_finished() && piece.length == length && piece.digest == digest
+                })
+            {
+                return Ok(Some((task.id, piece)));
+            }
+        }
+
+        Ok(None)
+    }
+
     /// persistent_cache_piece_id returns the persistent cache piece id.
     #[inline]
     pub fn persistent_cache_piece_id(&self, task_id: &str, number: u32) -> String {
@@ -0,0 +1,178 @@
+/*
+ *     Copyright 2025 The Dragonfly Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::debug;
+
+/// TaskPin is the pin state of a task.
+#[derive(Debug, Default)]
+struct TaskPin {
+    /// count is the number of the pieces of the task being served.
+    count: usize,
+
+    /// evicting indicates whether the task is being deleted, the task can't be pinned
+    /// while it is evicting.
+    evicting: bool,
+}
+
+/// TaskPins coordinates the pieces being served with the task deletion. The pieces being
+/// served pin their task, so the deletion waits for them to finish before removing the
+/// content, and the task being deleted can't be pinned, so its pieces are not served halfway.
+#[derive(Debug, Default)]
+pub struct TaskPins {
+    /// tasks is the pin state of the pinned or evicting tasks.
+    tasks: Mutex<HashMap<String, TaskPin>>,
+
+    /// unpinned is notified when the last pin of a task is released.
+    unpinned: Notify,
+}
+
+/// TaskPins implements the pins of the tasks.
+impl TaskPins {
+    /// new creates a new TaskPins.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// pin pins the task until the returned guard is dropped, it returns None if the task is
+    /// being evicted.
+    pub fn pin(self: &Arc<Self>, id: &str) -> Option<TaskPinGuard> {
+        let mut tasks = self.tasks.lock().unwrap();
+        let pin = tasks.entry(id.to_string()).or_default();
+        if pin.evicting {
+            return None;
+        }
+
+        pin.count += 1;
+        Some(TaskPinGuard {
+            pins: self.clone(),
+            id: id.to_string(),
+        })
+    }
+
+    /// is_pinned returns whether the task is pinned.
+    pub fn is_pinned(&self, id: &str) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|pin| pin.count > 0)
+    }
+
+    /// start_eviction marks the task as evicting, and waits for the pins of the task to be
+    /// released, so the content can be deleted safely after it returns.
+    pub async fn start_eviction(&self, id: &str) {
+        self.tasks
+            .lock()
+            .unwrap()
+            .entry(id.to_string())
+            .or_default()
+            .evicting = true;
+
+        loop {
+            // Register the waiter before checking the pins, so the release between them is
+            // not missed.
+            let unpinned = self.unpinned.notified();
+            if !self.is_pinned(id) {
+                return;
+            }
+
+            debug!("wait for the pieces of task {} being served to finish", id);
+            unpinned.await;
+        }
+    }
+
+    /// finish_eviction clears the evicting mark of the deleted task.
+    pub fn finish_eviction(&self, id: &str) {
+        self.tasks.lock().unwrap().remove(id);
+    }
+
+    /// unpin releases a pin of the task.
+    fn unpin(&self, id: &str) {
+        let mut tasks = self.tasks.lock().unwrap();
+        let Some(pin) = tasks.get_mut(id) else {
+            return;
+        };
+
+        pin.count = pin.count.saturating_sub(1);
+        if pin.count == 0 {
+            if !pin.evicting {
+                tasks.remove(id);
+            }
+
+            self.unpinned.notify_waiters();
+        }
+    }
+}
+
+/// TaskPinGuard holds a pin of the task, and releases it when dropped.
+#[derive(Debug)]
+pub struct TaskPinGuard {
+    /// pins is the pins of the tasks.
+    pins: Arc<TaskPins>,
+
+    /// id is the id of the pinned task.
+    id: String,
+}
+
+/// TaskPinGuard implements Drop.
+impl Drop for TaskPinGuard {
+    fn drop(&mut self) {
+        self.pins.unpin(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_task_pins() {
+        let pins = TaskPins::new();
+        let first_pin = pins.pin("task").unwrap();
+        let second_pin = pins.pin("task").unwrap();
+        assert!(pins.is_pinned("task"));
+
+        // The eviction waits for all pins of the task to be released.
+        let eviction = tokio::spawn({
+            let pins = pins.clone();
+            async move { pins.start_eviction("task").await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!eviction.is_finished());
+
+        // The evicting task can't be pinned, and the other tasks are not affected.
+        assert!(pins.pin("task").is_none());
+        assert!(pins.pin("other_task").is_some());
+        assert!(!pins.is_pinned("other_task"));
+
+        drop(first_pin);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!eviction.is_finished());
+
+        drop(second_pin);
+        eviction.await.unwrap();
+        assert!(pins.pin("task").is_none());
+
+        // The task can be pinned again after it is deleted.
+        pins.finish_eviction("task");
+        assert!(pins.pin("task").is_some());
+    }
+}
@@ -0,0 +1,290 @@
+/*
+ *     Copyright 2025 The Dragonfly Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use dragonfly_client_config::{CARGO_PKG_VERSION, GIT_COMMIT_SHORT_HASH};
+use dragonfly_client_core::{Error, Result};
+use std::time::Duration;
+
+/// PING_TYPE is the type of the ping datagram sent by the quic client.
+const PING_TYPE: u8 = 0x1;
+
+/// PONG_TYPE is the type of the pong datagram echoed by the quic server.
+const PONG_TYPE: u8 = 0x2;
+
+/// DATAGRAM_SIZE is the size of the ping and pong datagrams, including the type, the sequence
+/// number and the timestamp. The build information is appended after them optionally, so the
+/// peers which don't send it still interop.
+pub const DATAGRAM_SIZE: usize = 1 + 8 + 8;
+
+/// BuildInfo is the build information of the peer on the other end of the connection, it is
+/// used to debug the mixed-version fleets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// version is the crate version of the peer.
+    pub version: String,
+
+    /// commit is the short git commit hash of the peer.
+    pub commit: String,
+
+    /// uptime is the uptime of the peer, it is only sent by the server.
+    pub uptime: Option<Duration>,
+}
+
+/// BuildInfo implements the build information.
+impl BuildInfo {
+    /// new returns the build information of the current binary.
+    pub fn new(uptime: Option<Duration>) -> Self {
+        Self {
+            version: CARGO_PKG_VERSION.to_string(),
+            commit: GIT_COMMIT_SHORT_HASH.to_string(),
+            uptime,
+        }
+    }
+
+    /// encode appends the build information to the bytes.
+    fn encode(&self, bytes: &mut BytesMut) {
+        bytes.put_u64(self.uptime.map_or(u64::MAX, |uptime| uptime.as_secs()));
+        for field in [&self.version, &self.commit] {
+            let field = &field.as_bytes()[..field.len().min(u8::MAX as usize)];
+            bytes.put_u8(field.len() as u8);
+            bytes.put_slice(field);
+        }
+    }
+
+    /// decode reads the build information from the bytes.
+    fn decode(bytes: &mut Bytes) -> Result<Self> {
+        if bytes.len() < 8 {
+            return Err(Error::ValidationError(
+                "invalid build info of datagram".to_string(),
+            ));
+        }
+
+        let uptime = match bytes.get_u64() {
+            u64::MAX => None,
+            uptime => Some(Duration::from_secs(uptime)),
+        };
+
+        let mut fields = Vec::with_capacity(2);
+        for _ in 0..2 {
+            if bytes.is_empty() || bytes.len() < 1 + bytes[0] as usize {
+                return Err(Error::ValidationError(
+                    "invalid build info of datagram".to_string(),
+                ));
+            }
+
+            let length = bytes.get_u8() as usize;
+            fields.push(String::from_utf8_lossy(&bytes.split_to(length)).into_owned());
+        }
+
+        let commit = fields.pop().unwrap_or_default();
+        let version = fields.pop().unwrap_or_default();
+        Ok(Self {
+            version,
+            commit,
+            uptime,
+        })
+    }
+}
+
+/// Datagram is the ping and pong datagram over quic, it is used to check the liveness of the
+/// connection and sample the round trip time without opening a stream. The datagrams are
+/// unreliable, so the lost ping is just a lost sample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Datagram {
+    /// Ping is sent by the client with the sequence number and the timestamp in microseconds,
+    /// and optionally the build information of the client.
+    Ping {
+        sequence: u64,
+        timestamp: u64,
+        build_info: Option<BuildInfo>,
+    },
+
+    /// Pong is echoed by the server with the sequence number and the timestamp of the ping,
+    /// and optionally the build information and the uptime of the server.
+    Pong {
+        sequence: u64,
+        timestamp: u64,
+        build_info: Option<BuildInfo>,
+    },
+}
+
+/// Datagram implements the ping and pong datagram.
+impl Datagram {
+    /// pong returns the pong of the ping with the build information of the server, it returns
+    /// None if the datagram is not a ping.
+    pub fn pong(&self, build_info: Option<BuildInfo>) -> Option<Self> {
+        match *self {
+            Datagram::Ping {
+                sequence,
+                timestamp,
+                ..
+            } => Some(Datagram::Pong {
+                sequence,
+                timestamp,
+                build_info,
+            }),
+            Datagram::Pong { .. } => None,
+        }
+    }
+}
+
+/// Datagram implements the conversion to Bytes.
+impl From<Datagram> for Bytes {
+    fn from(datagram: Datagram) -> Self {
+        let (typ, sequence, timestamp, build_info) = match datagram {
+            Datagram::Ping {
+                sequence,
+                timestamp,
+                build_info,
+            } => (PING_TYPE, sequence, timestamp, build_info),
+            Datagram::Pong {
+                sequence,
+                timestamp,
+                build_info,
+            } => (PONG_TYPE, sequence, timestamp, build_info),
+        };
+
+        let mut bytes = BytesMut::with_capacity(DATAGRAM_SIZE);
+        bytes.put_u8(typ);
+        bytes.put_u64(sequence);
+        bytes.put_u64(timestamp);
+        if let Some(build_info) = build_info {
+            build_info.encode(&mut bytes);
+        }
+
+        bytes.freeze()
+    }
+}
+
+/// Datagram implements the conversion from Bytes.
+impl TryFrom<Bytes> for Datagram {
+    type Error = Error;
+
+    fn try_from(mut bytes: Bytes) -> Result<Self> {
+        if bytes.len() < DATAGRAM_SIZE {
+            return Err(Error::ValidationError(format!(
+                "invalid datagram size {}",
+                bytes.len()
+            )));
+        }
+
+        let typ = bytes.get_u8();
+        let sequence = bytes.get_u64();
+        let timestamp = bytes.get_u64();
+        let build_info = match bytes.is_empty() {
+            true => None,
+            false => Some(BuildInfo::decode(&mut bytes)?),
+        };
+
+        match typ {
+            PING_TYPE => Ok(Datagram::Ping {
+                sequence,
+                timestamp,
+                build_info,
+            }),
+            PONG_TYPE => Ok(Datagram::Pong {
+                sequence,
+                timestamp,
+                build_info,
+            }),
+            _ => Err(Error::ValidationError(format!(
+                "invalid datagram type {}",
+                typ
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datagram_conversion() {
+        let ping = Datagram::Ping {
+            sequence: 42,
+            timestamp: 1_700_000_000_000_000,
+            build_info: None,
+        };
+        let bytes: Bytes = ping.clone().into();
+        assert_eq!(bytes.len(), DATAGRAM_SIZE);
+        assert_eq!(Datagram::try_from(bytes).unwrap(), ping);
+
+        let pong = ping.pong(None).unwrap();
+        assert_eq!(
+            pong,
+            Datagram::Pong {
+                sequence: 42,
+                timestamp: 1_700_000_000_000_000,
+                build_info: None,
+            }
+        );
+        assert!(pong.pong(None).is_none());
+        assert_eq!(Datagram::try_from(Bytes::from(pong.clone())).unwrap(), pong);
+    }
+
+    #[test]
+    fn test_datagram_build_info() {
+        let ping = Datagram::Ping {
+            sequence: 1,
+            timestamp: 1,
+            build_info: Some(BuildInfo::new(None)),
+        };
+        assert_eq!(Datagram::try_from(Bytes::from(ping.clone())).unwrap(), ping);
+
+        let pong = ping
+            .pong(Some(BuildInfo::new(Some(Duration::from_secs(60)))))
+            .unwrap();
+        match Datagram::try_from(Bytes::from(pong)).unwrap() {
+            Datagram::Pong {
+                build_info: Some(build_info),
+                ..
+            } => {
+                assert_eq!(build_info.version, env!("CARGO_PKG_VERSION"));
+                assert_eq!(build_info.commit, GIT_COMMIT_SHORT_HASH);
+                assert_eq!(build_info.uptime, Some(Duration::from_secs(60)));
+            }
+            datagram => panic!("unexpected datagram {:?}", datagram),
+        }
+    }
+
+    #[test]
+    fn test_invalid_datagram() {
+        assert!(Datagram::try_from(Bytes::from_static(b"ping")).is_err());
+
+        let mut bytes = BytesMut::from(
+            &Bytes::from(Datagram::Ping {
+                sequence: 1,
+                timestamp: 1,
+                build_info: None,
+            })[..],
+        );
+        bytes[0] = 0xff;
+        assert!(Datagram::try_from(bytes.freeze()).is_err());
+
+        // The truncated build information is invalid.
+        let mut bytes = BytesMut::from(
+            &Bytes::from(Datagram::Ping {
+                sequence: 1,
+                timestamp: 1,
+                build_info: Some(BuildInfo::new(None)),
+            })[..],
+        );
+        bytes.truncate(bytes.len() - 1);
+        assert!(Datagram::try_from(bytes.freeze()).is_err());
+    }
+}
@@ -14,23 +14,63 @@
  * limitations under the License.
  */
 
+use crate::metadata;
+use crate::pin::TaskPinGuard;
+use crate::ping::{BuildInfo, Datagram};
+use crate::tls::{crypto_provider, initial_suite, QUIC_ALPN};
 use crate::Storage;
 use bytes::{Bytes, BytesMut};
 use dragonfly_api::common::v2::TrafficType;
-use dragonfly_client_core::{Error as ClientError, Result as ClientResult};
+use dragonfly_client_config::dfdaemon::{Config, CongestionControl, QUICTransport};
+use dragonfly_client_core::{
+    error::{ErrorType, OrErr},
+    Error as ClientError, Result as ClientResult,
+};
 use dragonfly_client_metric::{
-    collect_upload_piece_failure_metrics, collect_upload_piece_started_metrics,
+    collect_quic_banned_peer_metrics, collect_quic_buffered_bytes_metrics,
+    collect_quic_cert_reload_metrics, collect_quic_connection_closed_metrics,
+    collect_quic_connection_finished_metrics, collect_quic_connection_handshake_failure_metrics,
+    collect_quic_connection_path_metrics, collect_quic_connection_refused_metrics,
+    collect_quic_connection_started_metrics, collect_quic_dispatch_queue_depth_metrics,
+    collect_quic_dispatch_rejected_metrics, collect_quic_request_buffered_bytes_metrics,
+    collect_quic_storage_worker_busy_metrics, collect_quic_storage_worker_queue_depth_metrics,
+    collect_quic_stream_finished_metrics, collect_quic_stream_phase_metrics,
+    collect_upload_piece_failure_metrics, collect_upload_piece_finished_metrics,
+    collect_upload_piece_started_metrics,
 };
 use dragonfly_client_util::{
-    id_generator::IDGenerator, shutdown, tls::generate_simple_self_signed_certs,
+    id_generator::IDGenerator,
+    ratelimiter::{KeyedConcurrencyLimiter, KeyedPermit, KeyedRateLimiter},
+    shutdown,
+    tls::{
+        cert_fingerprint, generate_cert_from_pem, generate_key_from_pem,
+        load_or_generate_self_signed_certs, order_cert_chain, parse_cert_subject,
+    },
 };
 use leaky_bucket::RateLimiter;
-use quinn::{congestion::BbrConfig, AckFrequencyConfig, Endpoint, ServerConfig, TransportConfig};
-use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::io::{copy, AsyncRead};
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, instrument, Span};
+use quinn::crypto::rustls::{HandshakeData, QuicServerConfig};
+use quinn::rustls::{self, server::WebPkiClientVerifier};
+use quinn::{
+    congestion::{BbrConfig, CubicConfig, NewRenoConfig},
+    AckFrequencyConfig, Endpoint, EndpointConfig, MtuDiscoveryConfig, ServerConfig,
+    TransportConfig, VarInt,
+};
+use rustls_pki_types::CertificateDer;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio::time::timeout;
+use tokio_util::either::Either;
+use tokio_util::sync::PollSemaphore;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, instrument, warn, Span};
 use vortex_protocol::{
     tlv::{
         download_persistent_cache_piece::DownloadPersistentCachePiece,
@@ -43,17 +83,130 @@ use vortex_protocol::{
     Header, Vortex, HEADER_SIZE,
 };
 
+/// QUIC_CLOSE_CODE_GOAWAY is the application close code sent to the peers when the QUIC server
+/// is shutting down, the peers should reconnect to the other parents.
+const QUIC_CLOSE_CODE_GOAWAY: u32 = 0x1;
+
+/// QUIC_CLOSE_CODE_IDLE is the application close code sent to the peers when the connection is
+/// closed for being idle, the peers should reconnect when they need to download again.
+const QUIC_CLOSE_CODE_IDLE: u32 = 0x5;
+
+/// QUIC_CLOSE_CODE_POLICY_VIOLATION is the application close code sent to the peers which are
+/// banned for repeated protocol violations.
+const QUIC_CLOSE_CODE_POLICY_VIOLATION: u32 = 0x6;
+
+/// QUIC_STREAM_CODE_TIMEOUT is the error code to reset the stream when the request or response
+/// makes no progress within the request timeout.
+const QUIC_STREAM_CODE_TIMEOUT: u32 = 0x2;
+
+/// QUIC_STREAM_CODE_REQUEST_TOO_LARGE is the error code to stop the stream when the declared
+/// size of the request exceeds the max request size.
+const QUIC_STREAM_CODE_REQUEST_TOO_LARGE: u32 = 0x3;
+
+/// QUIC_STREAM_CODE_OVERLOADED is the error code to reset the stream when the server sheds the
+/// load, so the peer backs off and retries.
+pub(crate) const QUIC_STREAM_CODE_OVERLOADED: u32 = 0x4;
+
+/// VORTEX_ERROR_CODE_OVERLOADED is the reserved vortex error code responded when the server
+/// sheds the load, such as the peer exceeds the rate limit or the queues are full, and the
+/// error message carries the retry-after hint, so the peer backs off for at least the hint
+/// before retrying.
+pub(crate) const VORTEX_ERROR_CODE_OVERLOADED: u8 = 4;
+
+/// DEFAULT_OVERLOADED_RETRY_AFTER is the retry-after hint of the overloaded error when the
+/// server can not tell when the load is shed, such as the task reaches the streams limit.
+const DEFAULT_OVERLOADED_RETRY_AFTER: Duration = Duration::from_millis(100);
+
+/// QUIC_STREAM_CODE_CANCELLED is the error code to stop the stream when the download is
+/// cancelled by the client, so the server stops sending the rest of the piece.
+pub(crate) const QUIC_STREAM_CODE_CANCELLED: u32 = 0x7;
+
+/// QUIC_STREAM_CODE_QUOTA_EXCEEDED is the error code to reset the stream when the connection
+/// exceeds the byte quota, so the peer reconnects to continue.
+pub(crate) const QUIC_STREAM_CODE_QUOTA_EXCEEDED: u32 = 0x8;
+
+/// DEFAULT_PEER_RATE_LIMITER_IDLE_TIMEOUT is the default timeout to evict the rate limiter of
+/// the idle peer.
+const DEFAULT_PEER_RATE_LIMITER_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// DEFAULT_DRAIN_LOG_INTERVAL is the default interval to log the remaining streams when the QUIC
+/// server is draining.
+const DEFAULT_DRAIN_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// DEFAULT_INCOMING_CHANNEL_SIZE is the default size of the channel to multiplex the incoming
+/// connections of the endpoints.
+const DEFAULT_INCOMING_CHANNEL_SIZE: usize = 1024;
+
+/// DEFAULT_MIN_CERT_RELOAD_INTERVAL is the default minimum interval to check whether the
+/// certificate files are rotated.
+const DEFAULT_MIN_CERT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// SELF_SIGNED_CERT_DIR is the directory under the storage directory to persist the self-signed
+/// certificate of the QUIC server.
+const SELF_SIGNED_CERT_DIR: &str = "quic";
+
+/// STREAM_RESULT_OK is the result of the stream which is served successfully.
+const STREAM_RESULT_OK: &str = "ok";
+
+/// STREAM_PHASE_REQUEST is the phase of the stream to receive the request.
+const STREAM_PHASE_REQUEST: &str = "request";
+
+/// STREAM_PHASE_STORAGE is the phase of the stream to read the piece from the storage.
+const STREAM_PHASE_STORAGE: &str = "storage";
+
+/// STREAM_PHASE_NETWORK is the phase of the stream to send the response to the peer.
+const STREAM_PHASE_NETWORK: &str = "network";
+
+/// STREAM_RESULT_CANCELLED is the result of the stream which is stopped by the peer before the
+/// piece content is sent completely.
+const STREAM_RESULT_CANCELLED: &str = "cancelled";
+
+/// AUDIT_LOG_TARGET is the tracing target of the audit log, so it can be routed to its own sink.
+const AUDIT_LOG_TARGET: &str = "dragonfly::audit";
+
+/// DEFAULT_REBIND_INITIAL_BACKOFF is the initial backoff to rebind the closed endpoint.
+const DEFAULT_REBIND_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// DEFAULT_REBIND_MAX_BACKOFF is the maximum backoff to rebind the closed endpoint.
+const DEFAULT_REBIND_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// DEFAULT_WAIT_IDLE_TIMEOUT is the default timeout to wait for the closed connections to be
+/// drained after the endpoint is closed.
+const DEFAULT_WAIT_IDLE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// DEFAULT_MAX_UDP_PAYLOAD_SIZE is the default maximum udp payload size advertised to the peers,
+/// it fits the ethernet mtu.
+const DEFAULT_MAX_UDP_PAYLOAD_SIZE: u16 = 1472;
+
 /// QUICServer is a QUIC-based server for dfdaemon upload service.
 pub struct QUICServer {
+    /// config is the configuration of the dfdaemon.
+    config: Arc<Config>,
+
     /// addr is the address of the QUIC server.
     addr: SocketAddr,
 
+    /// bound is the server config and the endpoints bound by bind, they are taken by run.
+    bound: Option<(ServerConfig, Vec<Endpoint>)>,
+
+    /// local_addrs is the local addresses of the bound endpoints.
+    local_addrs: Vec<SocketAddr>,
+
     /// handler is the request handler.
     handler: QUICServerHandler,
 
+    /// connection_semaphore limits the number of concurrent connections.
+    connection_semaphore: Arc<Semaphore>,
+
+    /// stats is the statistics of the QUIC server.
+    stats: Arc<QUICServerStats>,
+
     /// shutdown is used to shutdown the QUIC server.
     shutdown: shutdown::Shutdown,
 
+    /// drain is used to drain the QUIC server for the rolling restarts.
+    drain: shutdown::Shutdown,
+
     /// _shutdown_complete is used to notify the QUIC server is shutdown.
     _shutdown_complete: mpsc::UnboundedSender<()>,
 }
@@ -62,6 +215,7 @@ pub struct QUICServer {
 impl QUICServer {
     /// Creates a new QUICServer.
     pub fn new(
+        config: Arc<Config>,
         addr: SocketAddr,
         id_generator: Arc<IDGenerator>,
         storage: Arc<Storage>,
@@ -69,52 +223,283 @@ impl QUICServer {
         shutdown: shutdown::Shutdown,
         shutdown_complete_tx: mpsc::UnboundedSender<()>,
     ) -> Self {
+        let connection_semaphore = Arc::new(Semaphore::new(
+            config.storage.server.quic.max_concurrent_connections,
+        ));
+
+        // The rate limit of each peer is disabled if the rate is zero.
+        let peer_rate_limiter = match config.storage.server.quic.peer_rate_limit {
+            0 => None,
+            rate => Some(Arc::new(KeyedRateLimiter::new(
+                rate,
+                config.storage.server.quic.peer_rate_limit_burst,
+                DEFAULT_PEER_RATE_LIMITER_IDLE_TIMEOUT,
+            ))),
+        };
+
+        // The concurrency limit of each task is disabled if the limit is zero.
+        let task_stream_limiter = match config.storage.server.quic.max_concurrent_streams_per_task {
+            0 => None,
+            limit => Some(Arc::new(KeyedConcurrencyLimiter::new(limit as usize))),
+        };
+
+        // The storage workers are disabled if the number of the workers is zero.
+        let storage_workers = match config.storage.server.quic.storage_workers {
+            0 => None,
+            workers => Some(Arc::new(DispatchQueue::new(
+                workers,
+                config.storage.server.quic.storage_worker_queue_depth,
+                collect_quic_storage_worker_queue_depth_metrics,
+            ))),
+        };
+
+        // The shared reads of the pieces are disabled if the buffer size is zero.
+        let piece_flights = match config.storage.server.quic.shared_read_buffer_size.as_u64() {
+            0 => None,
+            size => Some(PieceFlights::new(size)),
+        };
+
+        // The ban of the peers is disabled if the threshold is zero.
+        let peer_bans = match config.storage.server.quic.peer_ban_threshold {
+            0 => None,
+            threshold => Some(Arc::new(PeerBans::new(
+                threshold as usize,
+                config.storage.server.quic.peer_ban_window,
+                config.storage.server.quic.peer_ban_duration,
+            ))),
+        };
+
+        let dispatch_queue = Arc::new(DispatchQueue::new(
+            config.storage.server.quic.max_concurrent_dispatches,
+            config.storage.server.quic.dispatch_queue_depth,
+            collect_quic_dispatch_queue_depth_metrics,
+        ));
+
+        // The buffered bytes of the requests are bounded across all connections.
+        let request_budget = Arc::new(Semaphore::new(
+            (config
+                .storage
+                .server
+                .quic
+                .max_buffered_request_size
+                .as_u64() as usize)
+                .min(Semaphore::MAX_PERMITS),
+        ));
+
+        let stats = Arc::new(QUICServerStats::new());
         Self {
+            config: config.clone(),
             addr,
+            bound: None,
+            local_addrs: Vec::new(),
+            connection_semaphore,
+            stats: stats.clone(),
             handler: QUICServerHandler {
+                config,
                 id_generator,
                 storage,
                 upload_rate_limiter,
+                peer_rate_limiter,
+                peer_bans,
+                task_stream_limiter,
+                dispatch_queue,
+                storage_workers,
+                piece_flights,
+                request_budget,
+                stream_tracker: TaskTracker::new(),
+                stats,
+                shutdown: shutdown::Shutdown::new(),
             },
             shutdown,
+            drain: shutdown::Shutdown::new(),
             _shutdown_complete: shutdown_complete_tx,
         }
     }
 
+    /// Binds the endpoints of the storage quic server and returns the local address of the
+    /// primary endpoint. It is called by run if the server is not bound yet, and it can be
+    /// called before run to learn the port assigned by the OS if the configured port is zero,
+    /// the workers of the address share the same port.
+    pub fn bind(&mut self) -> ClientResult<SocketAddr> {
+        if self.bound.is_none() {
+            let server_config = self.server_config()?;
+            let endpoints = self.bind_endpoints(server_config.clone())?;
+            self.local_addrs = endpoints
+                .iter()
+                .map(|endpoint| endpoint.local_addr())
+                .collect::<Result<Vec<_>, _>>()?;
+            self.bound = Some((server_config, endpoints));
+        }
+
+        self.local_addr()
+    }
+
+    /// Returns the local address of the primary endpoint, it is available after the server
+    /// is bound.
+    pub fn local_addr(&self) -> ClientResult<SocketAddr> {
+        self.local_addrs
+            .first()
+            .copied()
+            .ok_or_else(|| ClientError::Unknown("storage quic server is not bound".to_string()))
+    }
+
     /// Starts the storage quic server.
     pub async fn run(&mut self) -> ClientResult<()> {
-        let (certs, key) = generate_simple_self_signed_certs("d7y", vec!["d7y".into()])?;
-        let mut server_config = ServerConfig::with_single_cert(certs, key).map_err(|err| {
-            ClientError::Unknown(format!("failed to create server config: {}", err))
-        })?;
+        info!(
+            "storage quic server uses {} congestion control",
+            self.config.storage.server.quic.transport.congestion_control
+        );
 
-        let mut transport = TransportConfig::default();
-        transport.congestion_controller_factory(Arc::new(BbrConfig::default()));
-        transport.keep_alive_interval(Some(super::DEFAULT_KEEPALIVE_INTERVAL));
-        transport.max_idle_timeout(Some(super::DEFAULT_MAX_IDLE_TIMEOUT.try_into().unwrap()));
-        transport.ack_frequency_config(Some(AckFrequencyConfig::default()));
-        transport.send_window(super::DEFAULT_SEND_BUFFER_SIZE as u64);
-        transport.receive_window((super::DEFAULT_RECV_BUFFER_SIZE as u32).into());
-        transport.stream_receive_window((super::DEFAULT_RECV_BUFFER_SIZE as u32).into());
-        server_config.transport_config(Arc::new(transport));
+        // Multiplex the incoming connections of all endpoints into the same handling path, the
+        // index of the endpoint is reported if it is closed unexpectedly, such as its socket
+        // fails, so the endpoint can be rebound on the address it was bound to.
+        self.bind()?;
+        let (server_config, mut endpoints) = self
+            .bound
+            .take()
+            .ok_or_else(|| ClientError::Unknown("storage quic server is not bound".to_string()))?;
+        let endpoint_addrs = self.local_addrs.clone();
+        let (incoming_tx, mut incoming_rx) = mpsc::channel(DEFAULT_INCOMING_CHANNEL_SIZE);
+        let (closed_tx, mut closed_rx) = mpsc::unbounded_channel();
+        for (index, endpoint) in endpoints.iter().enumerate() {
+            forward_incoming(
+                index,
+                endpoint.clone(),
+                incoming_tx.clone(),
+                closed_tx.clone(),
+            );
+        }
 
-        let endpoint = Endpoint::server(server_config, self.addr)?;
-        info!("storage quic server listening on {}", self.addr);
+        let (rebind_tx, mut rebind_rx) = mpsc::unbounded_channel();
+        let mut server_config = server_config;
+        let mut fatal = None;
 
+        // Reload the certificates if the certificate files are rotated, the new handshakes use
+        // the new certificates and the existing connections are not affected.
+        let cert_reload_enabled = self.config.storage.server.quic.cert.is_some()
+            && self.config.storage.server.quic.key.is_some();
+        let mut certs_modified_at = self.certs_modified_at();
+        let mut cert_reload_interval = tokio::time::interval(
+            self.config
+                .storage
+                .server
+                .quic
+                .cert_reload_interval
+                .max(DEFAULT_MIN_CERT_RELOAD_INTERVAL),
+        );
+        cert_reload_interval.tick().await;
+
+        let connection_tracker = TaskTracker::new();
+        let mut draining = false;
         loop {
             tokio::select! {
-                Some(quic_accepted) = endpoint.accept() => {
-                    let quic = quic_accepted.await?;
-                    let remote_address = quic.remote_address();
-                    debug!("accepted connection from {}", remote_address);
+                _ = cert_reload_interval.tick(), if cert_reload_enabled => {
+                    let modified_at = self.certs_modified_at();
+                    if modified_at == certs_modified_at {
+                        continue;
+                    }
 
-                    let handler = self.handler.clone();
-                    tokio::spawn(async move {
-                       if let Err(err) = handler.handle(quic, remote_address).await {
-                            error!("failed to handle connection from {}: {}", remote_address, err);
+                    match self.server_config() {
+                        Ok(new_server_config) => {
+                            for endpoint in endpoints.iter() {
+                                endpoint.set_server_config(Some(new_server_config.clone()));
+                            }
+                            server_config = new_server_config;
+
+                            info!("quic server reloaded certificates");
+                            collect_quic_cert_reload_metrics("success");
+                            certs_modified_at = modified_at;
                         }
+                        Err(err) => {
+                            // Keep serving with the previous certificates, and retry in the next
+                            // interval in case the files are being written.
+                            error!("quic server failed to reload certificates: {}", err);
+                            collect_quic_cert_reload_metrics("failure");
+                        }
+                    }
+                },
+                Some(incoming) = incoming_rx.recv() => {
+                    let remote_address = incoming.remote_address();
+
+                    // Refuse the connection before the handshake if the peer is not allowed.
+                    if !self.config.storage.server.quic.is_peer_allowed(remote_address.ip()) {
+                        warn!("refuse connection from {}, peer is not allowed", remote_address);
+                        collect_quic_connection_refused_metrics("denied");
+                        self.stats.refused_connections.fetch_add(1, Ordering::Relaxed);
+                        incoming.refuse();
+                        continue;
+                    }
+
+                    // Refuse the connection before the handshake if the peer is banned.
+                    if self.handler.is_peer_banned(remote_address.ip()) {
+                        warn!("refuse connection from {}, peer is banned", remote_address);
+                        collect_quic_connection_refused_metrics("banned");
+                        self.stats.refused_connections.fetch_add(1, Ordering::Relaxed);
+                        incoming.refuse();
+                        continue;
+                    }
+
+                    // Refuse the connection if the number of connections reaches the limit, the
+                    // permit is released when the connection is closed.
+                    let permit = match self.connection_semaphore.clone().try_acquire_owned() {
+                        Ok(permit) => permit,
+                        Err(_) => {
+                            warn!(
+                                "refuse connection from {}, max concurrent connections {} reached",
+                                remote_address,
+                                self.config.storage.server.quic.max_concurrent_connections
+                            );
+                            collect_quic_connection_refused_metrics("max_connections_reached");
+                            self.stats.refused_connections.fetch_add(1, Ordering::Relaxed);
+                            incoming.refuse();
+                            continue;
+                        }
+                    };
+                    debug!(
+                        "accepted connection from {}, current connections: {}",
+                        remote_address,
+                        self.connection_count()
+                    );
+
+                    let handler = self.handler.clone();
+                    connection_tracker.spawn(async move {
+                        let _permit = permit;
+                        handler.serve_connection(incoming, remote_address).await;
                     });
                 },
+                Some(index) = closed_rx.recv() => {
+                    let addr = endpoint_addrs[index];
+                    error!("storage quic endpoint on {} is closed unexpectedly, rebinding", addr);
+                    self.rebind_endpoint(index, addr, server_config.clone(), rebind_tx.clone());
+                }
+                Some((index, result)) = rebind_rx.recv() => {
+                    let addr = endpoint_addrs[index];
+                    match result {
+                        Ok(endpoint) => {
+                            info!("storage quic endpoint is rebound on {}", addr);
+
+                            // Apply the certificates reloaded while the endpoint was rebinding.
+                            endpoint.set_server_config(Some(server_config.clone()));
+                            forward_incoming(
+                                index,
+                                endpoint.clone(),
+                                incoming_tx.clone(),
+                                closed_tx.clone(),
+                            );
+                            endpoints[index] = endpoint;
+                        }
+                        Err(err) => {
+                            error!("storage quic endpoint failed to rebind on {}: {}", addr, err);
+                            fatal = Some(err);
+                            break;
+                        }
+                    }
+                }
+                _ = self.drain.recv() => {
+                    info!("quic server draining");
+                    draining = true;
+                    break;
+                }
                 _ = self.shutdown.recv() => {
                     info!("quic server shutting down");
                     break;
@@ -122,13 +507,468 @@ impl QUICServer {
             }
         }
 
-        Ok(())
+        // Refuse the new connections and the new streams of the existing connections, the
+        // connections are closed with GOAWAY once their in-flight streams finish, then wait for
+        // the in-flight streams of all connections to finish.
+        for endpoint in endpoints.iter() {
+            endpoint.set_server_config(None);
+        }
+        drop(incoming_rx);
+        drop(rebind_rx);
+        connection_tracker.close();
+        self.handler.stream_tracker.close();
+        self.handler.shutdown.trigger();
+        if draining {
+            self.wait_for_draining().await;
+        } else {
+            let grace_period = self.config.storage.server.quic.shutdown_grace_period;
+            if timeout(grace_period, self.handler.stream_tracker.wait())
+                .await
+                .is_err()
+            {
+                warn!(
+                    "quic server has {} in-flight streams after grace period {:?}, force to close",
+                    self.handler.stream_tracker.len(),
+                    grace_period
+                );
+            }
+        }
+
+        // Notify the connected peers whose streams are still in-flight that the server is going
+        // away.
+        for endpoint in endpoints.iter() {
+            endpoint.close(
+                VarInt::from_u32(QUIC_CLOSE_CODE_GOAWAY),
+                b"server shutting down",
+            );
+        }
+        connection_tracker.wait().await;
+        for endpoint in endpoints.iter() {
+            if timeout(DEFAULT_WAIT_IDLE_TIMEOUT, endpoint.wait_idle())
+                .await
+                .is_err()
+            {
+                warn!("quic server wait idle timeout");
+            }
+        }
+
+        info!("quic server shutdown complete");
+        match fatal {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Binds the endpoints on the listen addresses. If the partial listen is allowed, the
+    /// binding failures are reported but not fatal as long as one endpoint is bound.
+    fn bind_endpoints(&self, server_config: ServerConfig) -> ClientResult<Vec<Endpoint>> {
+        let addrs = self.listen_addrs();
+        let workers = self.config.storage.server.quic.workers;
+        let mut endpoints = Vec::with_capacity(addrs.len() * workers);
+        for addr in addrs {
+            match self.bind_workers(server_config.clone(), addr, workers) {
+                Ok(workers) => {
+                    info!(
+                        "storage quic server listening on {} with {} workers",
+                        addr,
+                        workers.len()
+                    );
+                    endpoints.extend(workers);
+                }
+                Err(err) if self.config.storage.server.quic.allow_partial_listen => {
+                    error!("storage quic server failed to listen on {}: {}", addr, err);
+                }
+                Err(err) => {
+                    error!("storage quic server failed to listen on {}: {}", addr, err);
+                    return Err(err);
+                }
+            }
+        }
+
+        if endpoints.is_empty() {
+            return Err(ClientError::Unknown(
+                "storage quic server failed to listen on any address".to_string(),
+            ));
+        }
+
+        Ok(endpoints)
+    }
+
+    /// Returns the listen addresses of the server, including the storage server ip and quic
+    /// port, and the additional listen addresses.
+    fn listen_addrs(&self) -> Vec<SocketAddr> {
+        let mut addrs = vec![self.addr];
+        addrs.extend(
+            self.config
+                .storage
+                .server
+                .quic
+                .additional_listen_addrs
+                .iter()
+                .copied(),
+        );
+
+        addrs
+    }
+
+    /// Returns whether the IPv6 address only accepts the IPv6 packets. The IPv6 address is dual
+    /// stack unless an IPv4 address on the same port is also listened, otherwise binding the
+    /// IPv4 address conflicts with the IPv4-mapped addresses of the IPv6 address.
+    fn only_v6(&self, addr: SocketAddr) -> bool {
+        addr.is_ipv6()
+            && addr.port() != 0
+            && self
+                .listen_addrs()
+                .iter()
+                .any(|other| other.is_ipv4() && other.port() == addr.port())
+    }
+
+    /// Binds the endpoints of the workers on the address. If there are multiple workers, the
+    /// endpoints are bound to the same address with SO_REUSEPORT, so the kernel spreads the
+    /// UDP packets of the connections across the endpoints. If the port is zero, the rest of
+    /// the workers are bound to the port assigned to the first worker.
+    fn bind_workers(
+        &self,
+        server_config: ServerConfig,
+        addr: SocketAddr,
+        workers: usize,
+    ) -> ClientResult<Vec<Endpoint>> {
+        let endpoint_config = endpoint_config(&self.config.storage.server.quic.transport)?;
+        let only_v6 = self.only_v6(addr);
+        if workers <= 1 {
+            return Ok(vec![bind_endpoint(
+                endpoint_config,
+                server_config,
+                addr,
+                false,
+                only_v6,
+            )?]);
+        }
+
+        let mut addr = addr;
+        let mut endpoints = Vec::with_capacity(workers);
+        for _ in 0..workers {
+            let endpoint = bind_endpoint(
+                endpoint_config.clone(),
+                server_config.clone(),
+                addr,
+                true,
+                only_v6,
+            )?;
+            if addr.port() == 0 {
+                addr.set_port(endpoint.local_addr()?.port());
+            }
+
+            endpoints.push(endpoint);
+        }
+
+        Ok(endpoints)
+    }
+
+    /// Rebinds the endpoint on the address with the exponential backoff in the background,
+    /// the result is sent back to the accept loop after the endpoint is bound or the retries
+    /// are exhausted. The existing connections of the closed endpoint can't be preserved, the
+    /// new connections are accepted as soon as the endpoint is rebound.
+    fn rebind_endpoint(
+        &self,
+        index: usize,
+        addr: SocketAddr,
+        server_config: ServerConfig,
+        rebind_tx: mpsc::UnboundedSender<(usize, ClientResult<Endpoint>)>,
+    ) {
+        let max_retries = self.config.storage.server.quic.rebind_max_retries;
+        let reuse_port = self.config.storage.server.quic.workers > 1;
+        let only_v6 = self.only_v6(addr);
+        let transport = self.config.storage.server.quic.transport.clone();
+        let mut shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            let mut backoff = DEFAULT_REBIND_INITIAL_BACKOFF;
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                let result = endpoint_config(&transport).and_then(|endpoint_config| {
+                    bind_endpoint(
+                        endpoint_config,
+                        server_config.clone(),
+                        addr,
+                        reuse_port,
+                        only_v6,
+                    )
+                });
+
+                match result {
+                    Ok(endpoint) => {
+                        let _ = rebind_tx.send((index, Ok(endpoint)));
+                        return;
+                    }
+                    Err(err) if attempt > max_retries => {
+                        let _ = rebind_tx.send((index, Err(err)));
+                        return;
+                    }
+                    Err(err) => {
+                        warn!(
+                            "storage quic endpoint failed to rebind on {}, attempt {}/{}, retry after {:?}: {}",
+                            addr, attempt, max_retries, backoff, err
+                        );
+                    }
+                }
+
+                // Stop rebinding if the server is shutting down.
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = shutdown.recv() => return,
+                    _ = rebind_tx.closed() => return,
+                }
+                backoff = (backoff * 2).min(DEFAULT_REBIND_MAX_BACKOFF);
+            }
+        });
+    }
+
+    /// Returns the statistics of the QUIC server, the in-process consumers keep it to take
+    /// the snapshots while the server is running.
+    pub fn stats(&self) -> Arc<QUICServerStats> {
+        self.stats.clone()
+    }
+
+    /// Returns the handle to drain the QUIC server, the server refuses the new connections
+    /// and streams when the handle is triggered, closes each connection with GOAWAY once its
+    /// in-flight streams finish, and exits after the in-flight streams finish within the drain
+    /// timeout. It is different from the shutdown, which only waits for the in-flight streams
+    /// within the shutdown grace period.
+    pub fn drain_handle(&self) -> shutdown::Shutdown {
+        self.drain.clone()
+    }
+
+    /// Waits for the in-flight streams to finish within the drain timeout, and logs the number
+    /// of the remaining streams periodically. It stops waiting if the server is shut down.
+    async fn wait_for_draining(&mut self) {
+        let drain_timeout = self.config.storage.server.quic.drain_timeout;
+        let deadline = tokio::time::sleep(drain_timeout);
+        tokio::pin!(deadline);
+
+        let mut interval = tokio::time::interval(DEFAULT_DRAIN_LOG_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.handler.stream_tracker.wait() => {
+                    info!("quic server drained");
+                    return;
+                }
+                _ = interval.tick() => {
+                    info!(
+                        "quic server draining, {} in-flight streams remaining",
+                        self.handler.stream_tracker.len()
+                    );
+                }
+                _ = &mut deadline => {
+                    warn!(
+                        "quic server has {} in-flight streams after drain timeout {:?}, force to close",
+                        self.handler.stream_tracker.len(),
+                        drain_timeout
+                    );
+                    return;
+                }
+                _ = self.shutdown.recv() => {
+                    warn!(
+                        "quic server shutting down while draining, {} in-flight streams remaining",
+                        self.handler.stream_tracker.len()
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of the current connections.
+    pub fn connection_count(&self) -> usize {
+        self.config.storage.server.quic.max_concurrent_connections
+            - self.connection_semaphore.available_permits()
+    }
+
+    /// Returns the latest modified time of the certificate files, it is used to detect the
+    /// certificate rotation.
+    fn certs_modified_at(&self) -> Option<SystemTime> {
+        let config = &self.config.storage.server.quic;
+        [
+            config.ca_cert.as_ref(),
+            config.cert.as_ref(),
+            config.key.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+    }
+
+    /// Returns the subject alternative names of the self-signed certificate, including the
+    /// hostname and the advertise ip of the host, the listen ips of the server and the extra
+    /// names in the config, so the peers can verify the server by any of them.
+    fn subject_alt_names(&self) -> Vec<String> {
+        let config = &self.config;
+        let ips = [config.host.ip, config.storage.server.ip]
+            .into_iter()
+            .flatten()
+            .chain(
+                config
+                    .storage
+                    .server
+                    .quic
+                    .additional_listen_addrs
+                    .iter()
+                    .map(|addr| addr.ip()),
+            )
+            .filter(|ip| !ip.is_unspecified())
+            .map(|ip| ip.to_string());
+
+        let mut subject_alt_names: Vec<String> = Vec::new();
+        for name in ["d7y".to_string(), config.host.hostname.clone()]
+            .into_iter()
+            .chain(ips)
+            .chain(config.storage.server.quic.subject_alt_names.iter().cloned())
+        {
+            if !name.is_empty() && !subject_alt_names.contains(&name) {
+                subject_alt_names.push(name);
+            }
+        }
+
+        subject_alt_names
+    }
+
+    /// Creates the server config with TLS 1.3. If the CA certificate is configured, the server
+    /// requires the client certificates signed by the CA for mutual TLS.
+    fn server_config(&self) -> ClientResult<ServerConfig> {
+        let config = &self.config.storage.server.quic;
+        let (certs, key) = match (config.cert.as_ref(), config.key.as_ref()) {
+            // Present the full chain of the leaf and the intermediates, so the strict peers can
+            // build the path to the trusted root.
+            (Some(cert_path), Some(key_path)) => (
+                order_cert_chain(generate_cert_from_pem(cert_path)?)?,
+                generate_key_from_pem(key_path)?,
+            ),
+            // Persist the self-signed certificate, so the identity is stable across restarts
+            // for the pinning and the session resumption of the peers.
+            _ => load_or_generate_self_signed_certs(
+                &self.config.storage.dir.join(SELF_SIGNED_CERT_DIR),
+                self.subject_alt_names(),
+            )?,
+        };
+
+        if let Some(cert) = certs.first() {
+            info!(
+                "quic server certificate fingerprint: {}",
+                cert_fingerprint(cert)
+            );
+        }
+
+        // Only negotiate the configured cipher suites, QUIC requires TLS 1.3.
+        let provider = crypto_provider(&config.cipher_suites)?;
+        debug!(
+            "quic server accepts cipher suites: {:?}",
+            provider
+                .cipher_suites
+                .iter()
+                .map(|cipher_suite| cipher_suite.suite())
+                .collect::<Vec<_>>()
+        );
+
+        let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .or_err(ErrorType::TLSConfigError)?;
+
+        let mut server_crypto = match config.ca_cert.as_ref() {
+            Some(ca_cert_path) => {
+                let mut roots = rustls::RootCertStore::empty();
+                for ca_cert in generate_cert_from_pem(ca_cert_path)? {
+                    roots.add(ca_cert).or_err(ErrorType::CertificateError)?;
+                }
+
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .or_err(ErrorType::TLSConfigError)?;
+
+                info!("quic server enables mutual TLS");
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        }
+        .with_single_cert(certs, key)
+        .or_err(ErrorType::TLSConfigError)?;
+
+        // Reject the handshakes which don't negotiate the vortex protocol.
+        server_crypto.alpn_protocols = vec![QUIC_ALPN.to_vec()];
+
+        // Issue the stateless session tickets, so the reconnecting peers resume the session in
+        // one round trip. The resumed connections still go through the CIDR check and the
+        // connection limit, and the client certificate is carried in the resumed session.
+        server_crypto.ticketer =
+            rustls::crypto::ring::Ticketer::new().or_err(ErrorType::TLSConfigError)?;
+
+        // Accept the early data of the resumed sessions, quic requires the maximum size to be
+        // u32::MAX if it is enabled. The replay-safe requests are enforced per stream.
+        if config.enable_zero_rtt {
+            server_crypto.max_early_data_size = u32::MAX;
+        }
+
+        // The initial packets are protected by the initial suite regardless of the configured
+        // cipher suites.
+        let mut server_config = ServerConfig::with_crypto(Arc::new(
+            QuicServerConfig::with_initial(Arc::new(server_crypto), initial_suite())
+                .or_err(ErrorType::TLSConfigError)?,
+        ));
+
+        let mut transport = TransportConfig::default();
+        let transport_config = &config.transport;
+        transport.keep_alive_interval(
+            (!transport_config.keep_alive_interval.is_zero())
+                .then_some(transport_config.keep_alive_interval),
+        );
+        transport.max_idle_timeout(transport_config.max_idle_timeout.try_into().ok());
+        transport.ack_frequency_config(Some(AckFrequencyConfig::default()));
+        transport.send_window(transport_config.send_window.as_u64());
+        transport.receive_window(
+            VarInt::from_u64(transport_config.receive_window.as_u64()).unwrap_or(VarInt::MAX),
+        );
+        transport.stream_receive_window(
+            VarInt::from_u64(transport_config.stream_receive_window.as_u64())
+                .unwrap_or(VarInt::MAX),
+        );
+        transport.max_concurrent_uni_streams(transport_config.max_concurrent_uni_streams.into());
+        transport.initial_rtt(transport_config.initial_rtt);
+        let datagram_receive_buffer_size =
+            transport_config.datagram_receive_buffer_size.as_u64() as usize;
+        transport.datagram_receive_buffer_size(
+            (datagram_receive_buffer_size > 0).then_some(datagram_receive_buffer_size),
+        );
+        match transport_config.congestion_control {
+            CongestionControl::Cubic => {
+                transport.congestion_controller_factory(Arc::new(CubicConfig::default()))
+            }
+            CongestionControl::NewReno => {
+                transport.congestion_controller_factory(Arc::new(NewRenoConfig::default()))
+            }
+            CongestionControl::Bbr => {
+                transport.congestion_controller_factory(Arc::new(BbrConfig::default()))
+            }
+        };
+        transport.mtu_discovery_config(transport_config.mtu_discovery.then(|| {
+            let mut mtu_discovery_config = MtuDiscoveryConfig::default();
+            mtu_discovery_config.upper_bound(transport_config.mtu_discovery_upper_bound);
+            mtu_discovery_config
+        }));
+        transport.enable_segmentation_offload(transport_config.segmentation_offload);
+        transport.max_concurrent_bidi_streams(config.max_concurrent_streams_per_connection.into());
+        server_config.transport_config(Arc::new(transport));
+
+        Ok(server_config)
     }
 }
 
 /// QUICServerHandler handles QUIC connections and requests.
 #[derive(Clone)]
 pub struct QUICServerHandler {
+    /// config is the configuration of the dfdaemon.
+    config: Arc<Config>,
+
     /// id_generator is the id generator.
     id_generator: Arc<IDGenerator>,
 
@@ -137,10 +977,100 @@ pub struct QUICServerHandler {
 
     /// upload_rate_limiter is the rate limiter of the upload speed in bps(bytes per second).
     upload_rate_limiter: Arc<RateLimiter>,
+
+    /// peer_rate_limiter is the rate limiter of the requests per peer, keyed by the remote ip.
+    peer_rate_limiter: Option<Arc<KeyedRateLimiter<IpAddr>>>,
+
+    /// peer_bans tracks the protocol violations of the peers and bans the abusive ones.
+    peer_bans: Option<Arc<PeerBans>>,
+
+    /// task_stream_limiter limits the in-flight streams per task, keyed by the task id.
+    task_stream_limiter: Option<Arc<KeyedConcurrencyLimiter<String>>>,
+
+    /// dispatch_queue bounds the streams dispatched to the handlers and waiting to be
+    /// dispatched across all connections.
+    dispatch_queue: Arc<DispatchQueue>,
+
+    /// storage_workers bounds the pieces read from the storage and waiting to be read across
+    /// all connections, it is None if the storage workers are disabled.
+    storage_workers: Option<Arc<DispatchQueue>>,
+
+    /// piece_flights deduplicates the concurrent reads of the same piece, it is None if the
+    /// shared reads are disabled.
+    piece_flights: Option<Arc<PieceFlights>>,
+
+    /// request_budget limits the total bytes of the requests buffered by all in-flight
+    /// streams, each permit is a byte.
+    request_budget: Arc<Semaphore>,
+
+    /// stream_tracker tracks the in-flight streams of all connections.
+    stream_tracker: TaskTracker,
+
+    /// stats is the statistics of the QUIC server.
+    stats: Arc<QUICServerStats>,
+
+    /// shutdown is used to stop accepting new streams when the server is draining or
+    /// shutting down.
+    shutdown: shutdown::Shutdown,
 }
 
 /// QUICServerHandler implements the request handler.
 impl QUICServerHandler {
+    /// serve_connection completes the handshake of the incoming connection and handles it.
+    /// The connection permit and the CIDR check are applied before it by the accept loop,
+    /// so handle only deals with the streams of the established connection.
+    async fn serve_connection(&self, incoming: quinn::Incoming, remote_address: SocketAddr) {
+        let mut shutdown = self.shutdown.clone();
+        let connecting = match incoming.accept() {
+            Ok(connecting) => connecting,
+            Err(err) => {
+                error!(
+                    "failed to accept connection from {}: {}",
+                    remote_address, err
+                );
+                collect_quic_connection_handshake_failure_metrics();
+                return;
+            }
+        };
+
+        // Stop the handshake if the server is shutting down.
+        let quic = tokio::select! {
+            quic = connecting => match quic {
+                Ok(quic) => quic,
+                Err(err) => {
+                    error!("failed to establish connection from {}: {}", remote_address, err);
+                    collect_quic_connection_handshake_failure_metrics();
+
+                    // The failed TLS handshake, such as the invalid client certificate, is a
+                    // protocol violation of the peer.
+                    if let quinn::ConnectionError::TransportError(_) = err {
+                        self.record_violation(remote_address.ip());
+                    }
+                    return;
+                }
+            },
+            _ = shutdown.recv() => {
+                debug!("abort handshake from {} for draining or shutting down", remote_address);
+                return;
+            }
+        };
+
+        collect_quic_connection_started_metrics();
+        self.stats
+            .active_connections
+            .fetch_add(1, Ordering::Relaxed);
+        if let Err(err) = self.handle(quic, remote_address).await {
+            error!(
+                "failed to handle connection from {}: {}",
+                remote_address, err
+            );
+        }
+        self.stats
+            .active_connections
+            .fetch_sub(1, Ordering::Relaxed);
+        collect_quic_connection_finished_metrics();
+    }
+
     /// handle handles a single QUIC connection.
     #[instrument(skip_all)]
     async fn handle(
@@ -148,19 +1078,171 @@ impl QUICServerHandler {
         connection: quinn::Connection,
         remote_address: SocketAddr,
     ) -> ClientResult<()> {
+        if let Some(handshake_data) = connection.handshake_data() {
+            if let Ok(handshake_data) = handshake_data.downcast::<HandshakeData>() {
+                debug!(
+                    "connection from {} negotiated TLSv1.3 and protocol {:?}",
+                    remote_address,
+                    handshake_data
+                        .protocol
+                        .as_deref()
+                        .map(String::from_utf8_lossy)
+                );
+            }
+        }
+
+        // Log the peer's certificate for auditing if the mutual TLS is enabled, and its subject
+        // identifies the peer in the audit log.
+        let mut peer_identity: Option<Arc<str>> = None;
+        if let Some(identity) = connection.peer_identity() {
+            if let Ok(certs) = identity.downcast::<Vec<CertificateDer<'static>>>() {
+                if let Some(cert) = certs.first() {
+                    match parse_cert_subject(cert) {
+                        Ok((subject, subject_alt_names)) => {
+                            info!(
+                                "accepted client certificate from {}, subject: {}, subject alternative names: {:?}",
+                                remote_address, subject, subject_alt_names
+                            );
+                            peer_identity = Some(subject.into());
+                        }
+                        Err(err) => warn!(
+                            "failed to parse client certificate from {}: {}",
+                            remote_address, err
+                        ),
+                    }
+                }
+            }
+        }
+
+        // Track the bytes served on the connection for the quota and the stats snapshot, the
+        // stable id of the connection is unique while the connection is alive.
+        let connection_id = connection.stable_id();
+        let connection_stats = self
+            .stats
+            .add_connection(connection.clone(), remote_address);
+
+        // Echo the pings of the peer if the datagrams are negotiated, so the peer can check the
+        // liveness and sample the round trip time without opening a stream.
+        if connection.max_datagram_size().is_some() {
+            tokio::spawn(echo_pings(
+                connection.clone(),
+                remote_address,
+                self.stats.clone(),
+            ));
+        }
+
+        // Limit the concurrent streams of the connection, the streams beyond the limit wait
+        // for the in-flight streams to finish.
+        let max_concurrent_streams = self
+            .config
+            .storage
+            .server
+            .quic
+            .max_concurrent_streams_per_connection as usize;
+        let stream_semaphore = Arc::new(Semaphore::new(max_concurrent_streams));
+
+        // Close the connection if no stream is served within the idle timeout, it is refreshed
+        // when a stream is accepted or finished.
+        let idle_timeout = self.config.storage.server.quic.connection_idle_timeout;
+        let last_active_at = Arc::new(Mutex::new(Instant::now()));
+
+        let mut shutdown = self.shutdown.clone();
         loop {
-            match connection.accept_bi().await {
+            let idle_deadline = *last_active_at.lock().unwrap() + idle_timeout;
+            let accepted = tokio::select! {
+                accepted = connection.accept_bi() => accepted,
+                _ = tokio::time::sleep_until(idle_deadline.into()), if !idle_timeout.is_zero() => {
+                    // The connection is still active if there are in-flight streams.
+                    if stream_semaphore.available_permits() < max_concurrent_streams {
+                        *last_active_at.lock().unwrap() = Instant::now();
+                        continue;
+                    }
+
+                    debug!("close idle connection from {}", remote_address);
+                    collect_quic_connection_closed_metrics("idle");
+                    connection.close(VarInt::from_u32(QUIC_CLOSE_CODE_IDLE), b"idle");
+                    break;
+                }
+                _ = shutdown.recv() => {
+                    debug!("stop accepting streams from {} for draining or shutting down", remote_address);
+
+                    // Refuse the new streams of the peer at the start of the drain, and notify
+                    // the peer that the server is going away as soon as the in-flight streams
+                    // of the connection finish, so the peer reconnects to the other parents
+                    // instead of waiting for the drain timeout.
+                    connection.set_max_concurrent_bi_streams(VarInt::from_u32(0));
+                    tokio::select! {
+                        _ = stream_semaphore.acquire_many(max_concurrent_streams as u32) => {
+                            connection.close(
+                                VarInt::from_u32(QUIC_CLOSE_CODE_GOAWAY),
+                                b"server shutting down",
+                            );
+                        }
+                        _ = connection.closed() => {}
+                    }
+                    break;
+                }
+            };
+
+            match accepted {
                 Ok((send, recv)) => {
+                    *last_active_at.lock().unwrap() = Instant::now();
+                    let permit = match stream_semaphore.clone().acquire_owned().await {
+                        Ok(permit) => permit,
+                        Err(err) => {
+                            error!("failed to acquire stream permit: {}", err);
+                            break;
+                        }
+                    };
+
                     let handler = self.clone();
-                    tokio::spawn(async move {
-                        if let Err(err) = handler.handle_stream(recv, send, remote_address).await {
+                    let connection = connection.clone();
+                    let connection_stats = connection_stats.clone();
+                    let peer_identity = peer_identity.clone();
+                    let last_active_at = last_active_at.clone();
+                    self.stream_tracker.spawn(async move {
+                        let _permit = permit;
+                        if let Err(err) = handler
+                            .handle_stream(
+                                recv,
+                                send,
+                                remote_address,
+                                peer_identity.as_deref(),
+                                &connection_stats,
+                            )
+                            .await
+                        {
                             error!("failed to handle stream: {}", err);
                         }
+
+                        // Cut off the peer if it is banned by the violations of the stream.
+                        if handler.is_peer_banned(remote_address.ip()) {
+                            collect_quic_connection_closed_metrics("banned");
+                            connection.close(
+                                VarInt::from_u32(QUIC_CLOSE_CODE_POLICY_VIOLATION),
+                                b"banned",
+                            );
+                        }
+
+                        *last_active_at.lock().unwrap() = Instant::now();
                     });
                 }
                 Err(err) => {
-                    // Downgrade common close cases to debug to reduce noisy logs.
+                    // The failures of a single stream, such as the peer resetting it, are
+                    // handled by its own task, so accept_bi only fails when the connection
+                    // itself is closed and no more streams can be accepted.
+                    collect_quic_connection_closed_metrics(connection_closed_reason(&err));
+
+                    // Downgrade common close cases to debug to reduce noisy logs. The idle
+                    // timeout means the peer is silent beyond the keep-alive pings, so it is
+                    // counted separately from the errors.
                     match err {
+                        quinn::ConnectionError::TimedOut => {
+                            self.stats
+                                .idle_closed_connections
+                                .fetch_add(1, Ordering::Relaxed);
+                            info!("connection from {} closed by idle timeout", remote_address);
+                        }
                         quinn::ConnectionError::ApplicationClosed(_)
                         | quinn::ConnectionError::LocallyClosed => {
                             debug!("connection closed: {}", err);
@@ -174,6 +1256,23 @@ impl QUICServerHandler {
             }
         }
 
+        // The path mtu is discovered after the handshake and the round trip time and the loss
+        // converge over the lifetime of the connection, so they are sampled when the connection
+        // is closed.
+        let path = connection.stats().path;
+        let loss_rate = connection_loss_rate(path.lost_packets, path.sent_packets);
+        collect_quic_connection_path_metrics(path.rtt, loss_rate);
+        debug!(
+            "connection from {} closed with path mtu {}, rtt {:?}, cwnd {}, lost packets {}/{}, loss rate {:.4}",
+            remote_address,
+            path.current_mtu,
+            path.rtt,
+            path.cwnd,
+            path.lost_packets,
+            path.sent_packets,
+            loss_rate
+        );
+        self.stats.remove_connection(connection_id);
         Ok(())
     }
 
@@ -189,22 +1288,270 @@ impl QUICServerHandler {
         mut reader: quinn::RecvStream,
         mut writer: quinn::SendStream,
         remote_address: SocketAddr,
+        peer_identity: Option<&str>,
+        connection_stats: &QUICConnectionStats,
     ) -> ClientResult<()> {
-        let header = self.read_header(&mut reader).await?;
-        match header.tag() {
-            Tag::DownloadPiece => {
-                let download_piece: DownloadPiece = self
-                    .read_download_piece(&mut reader, header.length() as usize)
-                    .await?;
+        let mut record = StreamRecord::default();
+        self.stats.active_streams.fetch_add(1, Ordering::Relaxed);
+        let result = self
+            .serve_stream(
+                &mut reader,
+                &mut writer,
+                remote_address,
+                connection_stats,
+                &mut record,
+            )
+            .await;
+        self.stats.active_streams.fetch_sub(1, Ordering::Relaxed);
+        self.stats.record_stream(&record);
+        connection_stats
+            .served_bytes
+            .fetch_add(record.sent_bytes, Ordering::Relaxed);
 
-                // Generate the host id.
-                let host_id = self.id_generator.host_id();
+        // Account the uploaded traffic per peer, it is drained and reported as the upload
+        // traffic like the gRPC path by the announcer on its interval.
+        if record.sent_bytes > 0 {
+            let traffic_task_id = record.fallback_task_id.as_ref().unwrap_or(&record.task_id);
+            self.stats
+                .record_peer_traffic(remote_address.ip(), traffic_task_id, record.sent_bytes);
+        }
 
-                // Get the task id from the request.
-                let task_id = download_piece.task_id();
+        match result {
+            Ok(_) => {}
+            // Reset the stream if the peer stalls, so the peer can tell the timeout apart from
+            // the other failures.
+            Err(ClientError::TokioTimeErrorElapsed(_)) => {
+                warn!(
+                    "stream from {} timed out after {:?}",
+                    remote_address,
+                    record.started_at.elapsed()
+                );
+
+                record.result = "timeout";
+                let _ = writer.reset(VarInt::from_u32(QUIC_STREAM_CODE_TIMEOUT));
+                let _ = reader.stop(VarInt::from_u32(QUIC_STREAM_CODE_TIMEOUT));
+            }
+            // The malformed requests and the unsupported tags are the protocol violations of
+            // the peer, the errors of the requested pieces like not found are not.
+            Err(ClientError::VortexProtocolError(_)) | Err(ClientError::Unsupported(_)) => {
+                record.result = "malformed";
+                self.record_violation(remote_address.ip());
+            }
+            Err(_) => {
+                // Keep the result if the error has been responded to the peer.
+                if record.result == STREAM_RESULT_OK {
+                    record.result = "error";
+                }
+            }
+        }
+
+        let cost = record.started_at.elapsed();
+        collect_quic_stream_finished_metrics(record.typ, record.result, record.sent_bytes, cost);
+
+        // The phases are only observed for the piece requests, the other streams are finished
+        // before reading the storage.
+        if record.piece_number.is_some() {
+            for (phase, phase_cost) in record.phases() {
+                collect_quic_stream_phase_metrics(record.typ, phase, phase_cost);
+            }
+        }
+
+        let slow_request_threshold = self.config.storage.server.quic.slow_request_threshold;
+        if !slow_request_threshold.is_zero() && cost > slow_request_threshold {
+            slow_request_log(remote_address, &record, cost);
+        }
+
+        // Emit the access log once per stream, it never includes the piece content.
+        if !self.config.storage.server.quic.disable_access_log {
+            info!(
+                remote_address = %remote_address,
+                typ = record.typ,
+                task_id = record.task_id.as_str(),
+                piece_number = record.piece_number,
+                sent_bytes = record.sent_bytes,
+                cost_ms = cost.as_millis() as u64,
+                result = record.result,
+                "quic access log"
+            );
+        }
+
+        // Audit the access of the persistent cache pieces, which may hold the proprietary
+        // content, the regular pieces are not audited.
+        if self.config.storage.server.quic.enable_audit_log
+            && record.typ == "download_persistent_cache_piece"
+        {
+            audit_log(remote_address, peer_identity, &record);
+        }
+
+        result
+    }
+
+    /// Serves the request of the QUIC stream, it reads the request and writes the response.
+    async fn serve_stream(
+        &self,
+        reader: &mut quinn::RecvStream,
+        writer: &mut quinn::SendStream,
+        remote_address: SocketAddr,
+        connection_stats: &QUICConnectionStats,
+        record: &mut StreamRecord,
+    ) -> ClientResult<()> {
+        // Refuse the new streams of the connection which has exceeded the byte quota, the
+        // in-flight streams are still finished. The quota is reset when the peer reconnects.
+        let connection_byte_quota = self
+            .config
+            .storage
+            .server
+            .quic
+            .connection_byte_quota
+            .as_u64();
+        if connection_byte_quota > 0 {
+            let served_bytes = connection_stats.served_bytes.load(Ordering::Relaxed);
+            if served_bytes >= connection_byte_quota {
+                warn!(
+                    "connection from {} served {} bytes, exceeds the quota {}",
+                    remote_address, served_bytes, connection_byte_quota
+                );
+                record.result = "quota_exceeded";
+                reset_stream(QUIC_STREAM_CODE_QUOTA_EXCEEDED, reader, writer);
+                return Ok(());
+            }
+        }
+
+        // Respond the overloaded error with the retry-after hint if the peer exceeds the rate
+        // limit, so the peer backs off for the hint instead of retrying immediately.
+        if let Some(peer_rate_limiter) = self.peer_rate_limiter.as_ref() {
+            if let Err(retry_after) = peer_rate_limiter.try_acquire(remote_address.ip()) {
+                warn!(
+                    "peer {} exceeds the rate limit, retry after {:?}",
+                    remote_address, retry_after
+                );
+                record.result = "overloaded";
+                return self
+                    .write_error(
+                        overloaded_error(
+                            format!("peer {} exceeds the rate limit", remote_address.ip()),
+                            retry_after,
+                        ),
+                        writer,
+                    )
+                    .await;
+            }
+        }
+
+        // Shed the load when the dispatch queue is full, instead of letting all streams
+        // contend on the storage and time out together.
+        let Some(_dispatch_permit) = self.dispatch_queue.admit().await else {
+            warn!(
+                "dispatch queue is full, reject request from {}",
+                remote_address
+            );
+            record.result = "overloaded";
+            collect_quic_dispatch_rejected_metrics();
+            return self
+                .write_error(
+                    overloaded_error(
+                        "dispatch queue is full".to_string(),
+                        DEFAULT_OVERLOADED_RETRY_AFTER,
+                    ),
+                    writer,
+                )
+                .await;
+        };
+
+        let header = self.read_header(reader).await?;
+
+        // The early data can be replayed by the attackers, so only the replay-safe requests
+        // are served from the 0-RTT streams.
+        if reader.is_0rtt() && !is_replay_safe(header.tag()) {
+            warn!(
+                "reject {:?} request from {} in 0-RTT",
+                header.tag(),
+                remote_address
+            );
+            record.result = "rejected";
+            return self
+                .write_error(
+                    Error::new(
+                        Code::InvalidArgument,
+                        format!("{:?} request is not allowed in 0-RTT", header.tag()),
+                    ),
+                    writer,
+                )
+                .await;
+        }
+
+        // Reject the oversized request based on the declared size, so the message is never
+        // buffered and the peer is told to stop sending it.
+        let max_request_size = self.config.storage.server.quic.max_request_size.as_u64();
+        if header.length() as u64 > max_request_size {
+            warn!(
+                "request size {} from {} exceeds the limit {}",
+                header.length(),
+                remote_address,
+                max_request_size
+            );
+            record.result = "too_large";
+            let _ = reader.stop(VarInt::from_u32(QUIC_STREAM_CODE_REQUEST_TOO_LARGE));
+            return self
+                .write_error(
+                    Error::new(
+                        Code::InvalidArgument,
+                        format!(
+                            "request size {} exceeds the limit {}",
+                            header.length(),
+                            max_request_size
+                        ),
+                    ),
+                    writer,
+                )
+                .await;
+        }
+
+        // Reserve the declared size of the request from the budget shared by all streams, it
+        // is released when the stream completes.
+        let _request_permit = match self
+            .request_budget
+            .clone()
+            .try_acquire_many_owned(header.length())
+        {
+            Ok(permit) => RequestPermit::new(permit, header.length()),
+            Err(_) => {
+                warn!(
+                    "request budget is exhausted, reject request from {}",
+                    remote_address
+                );
+                record.result = "overloaded";
+                return self
+                    .write_error(
+                        overloaded_error(
+                            "request budget is exhausted".to_string(),
+                            DEFAULT_OVERLOADED_RETRY_AFTER,
+                        ),
+                        writer,
+                    )
+                    .await;
+            }
+        };
+        record.received_bytes = (HEADER_SIZE + header.length() as usize) as u64;
+
+        match header.tag() {
+            Tag::DownloadPiece => {
+                record.typ = "download_piece";
+                let download_piece: DownloadPiece = self
+                    .read_download_piece(reader, header.length() as usize)
+                    .await?;
+                record.request_cost = record.started_at.elapsed();
+
+                // Generate the host id.
+                let host_id = self.id_generator.host_id();
+
+                // Get the task id from the request.
+                let task_id = download_piece.task_id();
 
                 // Get the interested piece number from the request.
                 let piece_number = download_piece.piece_number();
+                record.task_id = task_id.to_string();
+                record.piece_number = Some(piece_number);
 
                 // Generate the piece id.
                 let piece_id = self.storage.piece_id(task_id, piece_number);
@@ -214,12 +1561,57 @@ impl QUICServerHandler {
                 Span::current().record("task_id", task_id);
                 Span::current().record("piece_id", piece_id.as_str());
 
+                // Hold the permit of the task until the piece is served.
+                let _task_permit = match self.acquire_task_permit(task_id).await {
+                    Ok(permit) => permit,
+                    Err(err) => {
+                        warn!("{}", err.message());
+                        record.result = "overloaded";
+                        return self.write_error(err, writer).await;
+                    }
+                };
+
+                // Pin the task until the piece is served, so the task is not deleted halfway.
+                let _task_pin = match self.pin_task(task_id) {
+                    Ok(pin) => pin,
+                    Err(err) => {
+                        record.result = code_label(err.code());
+                        return self.write_error(err, writer).await;
+                    }
+                };
+
+                // Hold the storage worker until the piece is read, so the concurrent reads of
+                // the storage are bounded.
+                let storage_worker = match self.acquire_storage_worker().await {
+                    Ok(worker) => worker,
+                    Err(err) => {
+                        warn!("{}", err.message());
+                        record.result = "overloaded";
+                        return self.write_error(err, writer).await;
+                    }
+                };
+
                 // Collect upload piece started metrics.
                 collect_upload_piece_started_metrics();
-                info!("start upload piece content");
+                debug!("start upload piece content");
+
+                let storage_started_at = Instant::now();
+                let result = match self.handle_piece(piece_id.as_str(), task_id).await {
+                    Ok((piece_content, content_reader, fallback)) => Ok((
+                        piece_content,
+                        self.storage_worker_reader(content_reader, storage_worker),
+                        fallback,
+                    )),
+                    Err(err) => Err(err),
+                };
+                record.storage_cost += storage_started_at.elapsed();
+                match result {
+                    Ok((piece_content, mut content_reader, fallback)) => {
+                        // The traffic of the piece served from the persistent cache is accounted
+                        // to the persistent cache task.
+                        record.fallback_task_id =
+                            fallback.as_ref().map(|fallback| fallback.task_id.clone());
 
-                match self.handle_piece(piece_id.as_str(), task_id).await {
-                    Ok((piece_content, mut content_reader)) => {
                         let piece_content_bytes: Bytes = piece_content.into();
 
                         let header = Header::new_piece_content(piece_content_bytes.len() as u32);
@@ -230,33 +1622,38 @@ impl QUICServerHandler {
                         response.extend_from_slice(&header_bytes);
                         response.extend_from_slice(&piece_content_bytes);
 
-                        self.write_response(response.freeze(), &mut writer).await?;
-                        self.write_stream(&mut content_reader, &mut writer).await?;
-
-                        if let Err(err) = writer.finish() {
-                            error!("failed to finish stream: {}", err);
+                        // Collect upload piece finished or failure metrics, the failed writes
+                        // also release the concurrent upload piece gauge.
+                        if let Err(err) = self
+                            .write_piece_content(
+                                response.freeze(),
+                                &mut content_reader,
+                                writer,
+                                record,
+                            )
+                            .await
+                        {
+                            collect_upload_piece_failure_metrics();
+                            return Err(err);
                         }
+                        collect_upload_piece_finished_metrics();
                     }
                     Err(err) => {
                         // Collect upload piece failure metrics.
                         collect_upload_piece_failure_metrics();
-
-                        let error_response: Bytes =
-                            Vortex::Error(Header::new_error(err.len() as u32), err).into();
-                        self.write_response(error_response, &mut writer).await?;
-
-                        if let Err(err) = writer.finish() {
-                            error!("failed to finish stream: {}", err);
-                        }
+                        record.result = code_label(err.code());
+                        self.write_error(err, writer).await?;
                     }
                 }
 
                 Ok(())
             }
             Tag::DownloadPersistentCachePiece => {
+                record.typ = "download_persistent_cache_piece";
                 let download_persistent_cache_piece: DownloadPersistentCachePiece = self
-                    .read_download_piece(&mut reader, header.length() as usize)
+                    .read_download_piece(reader, header.length() as usize)
                     .await?;
+                record.request_cost = record.started_at.elapsed();
 
                 // Generate the host id.
                 let host_id = self.id_generator.host_id();
@@ -266,6 +1663,8 @@ impl QUICServerHandler {
 
                 // Get the interested piece number from the request.
                 let piece_number = download_persistent_cache_piece.piece_number();
+                record.task_id = task_id.to_string();
+                record.piece_number = Some(piece_number);
 
                 // Generate the piece id.
                 let piece_id = self.storage.piece_id(task_id, piece_number);
@@ -275,14 +1674,53 @@ impl QUICServerHandler {
                 Span::current().record("task_id", task_id);
                 Span::current().record("piece_id", piece_id.as_str());
 
+                // Hold the permit of the task until the piece is served.
+                let _task_permit = match self.acquire_task_permit(task_id).await {
+                    Ok(permit) => permit,
+                    Err(err) => {
+                        warn!("{}", err.message());
+                        record.result = "overloaded";
+                        return self.write_error(err, writer).await;
+                    }
+                };
+
+                // Pin the task until the piece is served, so the task is not deleted halfway.
+                let _task_pin = match self.pin_task(task_id) {
+                    Ok(pin) => pin,
+                    Err(err) => {
+                        record.result = code_label(err.code());
+                        return self.write_error(err, writer).await;
+                    }
+                };
+
+                // Hold the storage worker until the piece is read, so the concurrent reads of
+                // the storage are bounded.
+                let storage_worker = match self.acquire_storage_worker().await {
+                    Ok(worker) => worker,
+                    Err(err) => {
+                        warn!("{}", err.message());
+                        record.result = "overloaded";
+                        return self.write_error(err, writer).await;
+                    }
+                };
+
                 // Collect upload piece started metrics.
                 collect_upload_piece_started_metrics();
-                info!("start upload persistent cache piece content");
+                debug!("start upload persistent cache piece content");
 
-                match self
+                let storage_started_at = Instant::now();
+                let result = match self
                     .handle_persistent_cache_piece(piece_id.as_str(), task_id)
                     .await
                 {
+                    Ok((persistent_cache_piece_content, content_reader)) => Ok((
+                        persistent_cache_piece_content,
+                        self.storage_worker_reader(content_reader, storage_worker),
+                    )),
+                    Err(err) => Err(err),
+                };
+                record.storage_cost += storage_started_at.elapsed();
+                match result {
                     Ok((persistent_cache_piece_content, mut content_reader)) => {
                         let persistent_cache_piece_content_bytes: Bytes =
                             persistent_cache_piece_content.into();
@@ -298,33 +1736,61 @@ impl QUICServerHandler {
                         response.extend_from_slice(&header_bytes);
                         response.extend_from_slice(&persistent_cache_piece_content_bytes);
 
-                        self.write_response(response.freeze(), &mut writer).await?;
-                        self.write_stream(&mut content_reader, &mut writer).await?;
-
-                        if let Err(err) = writer.finish() {
-                            error!("failed to finish stream: {}", err);
+                        // Collect upload piece finished or failure metrics, the failed writes
+                        // also release the concurrent upload piece gauge.
+                        if let Err(err) = self
+                            .write_piece_content(
+                                response.freeze(),
+                                &mut content_reader,
+                                writer,
+                                record,
+                            )
+                            .await
+                        {
+                            collect_upload_piece_failure_metrics();
+                            return Err(err);
                         }
+                        collect_upload_piece_finished_metrics();
                     }
                     Err(err) => {
                         // Collect upload piece failure metrics.
                         collect_upload_piece_failure_metrics();
-
-                        let error_response: Bytes =
-                            Vortex::Error(Header::new_error(err.len() as u32), err).into();
-                        self.write_response(error_response, &mut writer).await?;
-
-                        if let Err(err) = writer.finish() {
-                            error!("failed to finish stream: {}", err);
-                        }
+                        record.result = code_label(err.code());
+                        self.write_error(err, writer).await?;
                     }
                 }
 
                 Ok(())
             }
-            _ => Err(ClientError::Unsupported(format!(
-                "unsupported tag: {:?}",
-                header.tag()
-            ))),
+            _ => {
+                // Respond the error to the peer instead of dropping the stream silently, so
+                // the peer can tell the unsupported request apart from the transport failures.
+                let message = format!("unsupported tag: {:?}", header.tag());
+                record.result = code_label(Code::InvalidArgument);
+                self.write_error(Error::new(Code::InvalidArgument, message.clone()), writer)
+                    .await?;
+
+                Err(ClientError::Unsupported(message))
+            }
+        }
+    }
+
+    /// Wraps the piece content to hold the storage worker only while a chunk is read from the
+    /// storage, so the peer reading slowly doesn't hold the storage worker between the chunks
+    /// and block the reads of the other pieces. The piece content is not wrapped if the storage
+    /// workers are disabled.
+    fn storage_worker_reader<R: AsyncRead + Unpin>(
+        &self,
+        content_reader: R,
+        storage_worker: Option<StorageWorkerPermit>,
+    ) -> Either<StorageWorkerReader<R>, R> {
+        match (self.storage_workers.as_ref(), storage_worker) {
+            (Some(storage_workers), Some(storage_worker)) => Either::Left(StorageWorkerReader {
+                inner: content_reader,
+                storage_workers: PollSemaphore::new(storage_workers.semaphore.clone()),
+                storage_worker: Some(storage_worker),
+            }),
+            _ => Either::Right(content_reader),
         }
     }
 
@@ -339,17 +1805,32 @@ impl QUICServerHandler {
         &self,
         piece_id: &str,
         task_id: &str,
-    ) -> Result<(PieceContent, impl AsyncRead), Error> {
-        // Get the piece metadata from the local storage.
-        let piece = match self.storage.get_piece(piece_id) {
-            Ok(Some(piece)) => piece,
-            Ok(None) => {
-                error!("piece {} not found in local storage", piece_id);
-                return Err(Error::new(
-                    Code::NotFound,
-                    format!("piece {} not found", piece_id),
-                ));
-            }
+    ) -> Result<
+        (
+            PieceContent,
+            impl AsyncRead,
+            Option<PersistentCacheFallback>,
+        ),
+        Error,
+    > {
+        // Get the piece metadata from the local storage, the metadata of the evicted piece is
+        // used to find the identical persistent cache piece.
+        let (piece, evicted) = match self.storage.get_piece(piece_id) {
+            Ok(Some(piece)) => (piece, false),
+            Ok(None) => match self
+                .storage
+                .get_evicted_piece(piece_id)
+                .filter(|_| self.config.storage.server.quic.persistent_cache_fallback)
+            {
+                Some(piece) => (piece, true),
+                None => {
+                    error!("piece {} not found in local storage", piece_id);
+                    return Err(Error::new(
+                        Code::NotFound,
+                        format!("piece {} not found", piece_id),
+                    ));
+                }
+            },
             Err(err) => {
                 error!("get piece {} from local storage error: {:?}", piece_id, err);
                 return Err(Error::new(
@@ -359,23 +1840,45 @@ impl QUICServerHandler {
             }
         };
 
-        // Acquire the upload rate limiter.
-        self.upload_rate_limiter
-            .acquire(piece.length as usize)
-            .await;
+        // Refuse to serve the piece which is larger than the limit, such as the piece of a
+        // misconfigured task with a huge piece length.
+        self.check_piece_size(piece_id, piece.length)?;
 
-        // Upload the piece content.
-        let reader = self
-            .storage
-            .upload_piece(piece_id, task_id, None)
-            .await
-            .map_err(|err| {
-                error!("failed to get piece content: {}", err);
-                Error::new(
-                    Code::Internal,
-                    format!("failed to get piece {} content: {}", piece_id, err),
-                )
-            })?;
+        // The concurrent requests of the piece share a single read of the storage.
+        let result = if evicted {
+            Err(Error::new(
+                Code::NotFound,
+                format!("piece {} not found", piece_id),
+            ))
+        } else {
+            match self
+                .piece_flights
+                .as_ref()
+                .and_then(|piece_flights| piece_flights.join(piece_id, piece.length))
+            {
+                Some(flight) => self
+                    .read_shared_piece(piece_id, task_id, flight)
+                    .await
+                    .map(Either::Left),
+                None => self.read_piece(piece_id, task_id).await.map(Either::Right),
+            }
+        };
+
+        // Serve the identical piece from the persistent cache only if the piece is missing, such
+        // as it is evicted, the other errors such as the corrupted piece are returned as is.
+        let (reader, fallback) = match result {
+            Ok(reader) => (Either::Left(reader), None),
+            Err(err) if matches!(err.code(), Code::NotFound) => {
+                match self.read_persistent_cache_fallback(piece_id, &piece).await {
+                    Some((reader, fallback)) => (Either::Right(reader), Some(fallback)),
+                    None => return Err(err),
+                }
+            }
+            Err(err) => return Err(err),
+        };
+        let traffic_type = fallback
+            .as_ref()
+            .map_or(TrafficType::RemotePeer, |fallback| fallback.traffic_type);
 
         Ok((
             PieceContent::new(
@@ -384,14 +1887,168 @@ impl QUICServerHandler {
                 piece.length,
                 piece.digest.clone(),
                 piece.parent_id.clone().unwrap_or_default(),
-                TrafficType::RemotePeer as u8,
+                traffic_type as u8,
                 piece.cost().unwrap_or_default(),
                 piece.created_at,
             ),
             reader,
+            fallback,
         ))
     }
 
+    /// Reads the content of the piece from the local storage, the digest of the piece is
+    /// verified before reading if it is enabled.
+    async fn read_piece(&self, piece_id: &str, task_id: &str) -> Result<impl AsyncRead, Error> {
+        // Verify the digest of the piece content before serving it, so the corrupted piece
+        // is not propagated to the other peers.
+        if self.config.storage.server.quic.verify_digest {
+            if let Err(err) = self.storage.verify_piece_digest(piece_id, task_id).await {
+                error!("piece {} is corrupted: {}", piece_id, err);
+                if let ClientError::DigestMismatch(_, _) = err {
+                    // Remove the corrupted piece, so it is downloaded again. The corrupted piece
+                    // is responded as the internal error, instead of the missing piece.
+                    if let Err(err) = self.storage.download_piece_failed(piece_id) {
+                        error!("failed to remove corrupted piece {}: {}", piece_id, err);
+                    }
+
+                    return Err(Error::new(
+                        Code::Internal,
+                        format!("piece {} is corrupted: {}", piece_id, err),
+                    ));
+                }
+
+                return Err(Error::new(
+                    storage_error_code(&err),
+                    format!("failed to verify piece {}: {}", piece_id, err),
+                ));
+            }
+        }
+
+        // Upload the piece content.
+        self.storage
+            .upload_piece(piece_id, task_id, None)
+            .await
+            .map_err(|err| {
+                error!("failed to get piece content: {}", err);
+                Error::new(
+                    storage_error_code(&err),
+                    format!("failed to get piece {} content: {}", piece_id, err),
+                )
+            })
+    }
+
+    /// Reads the piece into the buffer shared by the concurrent requests of the piece, the piece
+    /// is read from the storage only once for them.
+    async fn read_shared_piece(
+        &self,
+        piece_id: &str,
+        task_id: &str,
+        flight: PieceFlightGuard,
+    ) -> Result<SharedPieceReader, Error> {
+        let content = flight
+            .content(|| async {
+                let mut reader = self.read_piece(piece_id, task_id).await?;
+                let mut content = Vec::with_capacity(flight.length() as usize);
+                reader.read_to_end(&mut content).await.map_err(|err| {
+                    error!("failed to read piece {} content: {}", piece_id, err);
+                    Error::new(
+                        Code::Internal,
+                        format!("failed to read piece {} content: {}", piece_id, err),
+                    )
+                })?;
+
+                Ok(Bytes::from(content))
+            })
+            .await?;
+
+        Ok(SharedPieceReader {
+            content: std::io::Cursor::new(content),
+            _flight: flight,
+        })
+    }
+
+    /// Reads the content of the persistent cache piece which is identical to the missing piece,
+    /// it returns None if the fallback is disabled or no identical piece is stored. The
+    /// persistent cache task is pinned until the piece is served.
+    async fn read_persistent_cache_fallback(
+        &self,
+        piece_id: &str,
+        piece: &metadata::Piece,
+    ) -> Option<(impl AsyncRead, PersistentCacheFallback)> {
+        if !self.config.storage.server.quic.persistent_cache_fallback {
+            return None;
+        }
+
+        // Only the persistent cache piece with the identical digest and length is served.
+        let (fallback_task_id, fallback_piece) = match self
+            .storage
+            .find_persistent_cache_piece(piece.digest.as_str(), piece.length)
+        {
+            Ok(Some(found)) => found,
+            Ok(None) => return None,
+            Err(err) => {
+                error!(
+                    "failed to find persistent cache piece of {}: {}",
+                    piece_id, err
+                );
+                return None;
+            }
+        };
+
+        let pin = self.storage.pin_task(&fallback_task_id).ok()?;
+        let fallback_piece_id = self
+            .storage
+            .persistent_cache_piece_id(&fallback_task_id, fallback_piece.number);
+        if self.config.storage.server.quic.verify_digest {
+            if let Err(err) = self
+                .storage
+                .verify_persistent_cache_piece_digest(&fallback_piece_id, &fallback_task_id)
+                .await
+            {
+                error!(
+                    "persistent cache piece {} is corrupted: {}",
+                    fallback_piece_id, err
+                );
+                return None;
+            }
+        }
+
+        match self
+            .storage
+            .upload_persistent_cache_piece(&fallback_piece_id, &fallback_task_id, None)
+            .await
+        {
+            Ok(reader) => {
+                info!(
+                    "serve piece {} from persistent cache piece {}",
+                    piece_id, fallback_piece_id
+                );
+                // The piece is served from the persistent cache piece, so the traffic type is
+                // derived from how the persistent cache piece is downloaded.
+                let traffic_type = match fallback_piece.parent_id {
+                    None => TrafficType::BackToSource,
+                    Some(_) => TrafficType::RemotePeer,
+                };
+
+                Some((
+                    reader,
+                    PersistentCacheFallback {
+                        task_id: fallback_task_id,
+                        traffic_type,
+                        _pin: pin,
+                    },
+                ))
+            }
+            Err(err) => {
+                error!(
+                    "failed to get persistent cache piece {} content: {}",
+                    fallback_piece_id, err
+                );
+                None
+            }
+        }
+    }
+
     /// Handles download persistent cache piece request and retrieves content.
     ///
     /// Similar to handle_piece but specifically for persistent cache pieces
@@ -423,10 +2080,41 @@ impl QUICServerHandler {
             }
         };
 
-        // Acquire the upload rate limiter.
-        self.upload_rate_limiter
-            .acquire(piece.length as usize)
-            .await;
+        // Refuse to serve the piece which is larger than the limit, such as the piece of a
+        // misconfigured task with a huge piece length.
+        self.check_piece_size(piece_id, piece.length)?;
+
+        // Verify the digest of the piece content before serving it, so the corrupted piece
+        // is not propagated to the other peers.
+        if self.config.storage.server.quic.verify_digest {
+            if let Err(err) = self
+                .storage
+                .verify_persistent_cache_piece_digest(piece_id, task_id)
+                .await
+            {
+                error!("persistent cache piece {} is corrupted: {}", piece_id, err);
+                if let ClientError::DigestMismatch(_, _) = err {
+                    // Remove the corrupted piece, so it is downloaded again.
+                    if let Err(err) = self
+                        .storage
+                        .download_persistent_cache_piece_failed(piece_id)
+                    {
+                        error!(
+                            "failed to remove corrupted persistent cache piece {}: {}",
+                            piece_id, err
+                        );
+                    }
+                }
+
+                return Err(Error::new(
+                    storage_error_code(&err),
+                    format!(
+                        "failed to verify persistent cache piece {}: {}",
+                        piece_id, err
+                    ),
+                ));
+            }
+        }
 
         // Upload the piece content.
         let reader = self
@@ -436,7 +2124,7 @@ impl QUICServerHandler {
             .map_err(|err| {
                 error!("failed to get piece content: {}", err);
                 Error::new(
-                    Code::Internal,
+                    storage_error_code(&err),
                     format!("failed to get piece {} content: {}", piece_id, err),
                 )
             })?;
@@ -464,9 +2152,8 @@ impl QUICServerHandler {
     async fn read_header(&self, reader: &mut quinn::RecvStream) -> ClientResult<Header> {
         let mut header_bytes = BytesMut::with_capacity(HEADER_SIZE);
         header_bytes.resize(HEADER_SIZE, 0);
-        reader
-            .read_exact(&mut header_bytes)
-            .await
+        timeout(self.request_timeout(), reader.read_exact(&mut header_bytes))
+            .await?
             .inspect_err(|err| error!("failed to receive header: {}", err))?;
 
         Header::try_from(header_bytes.freeze()).map_err(Into::into)
@@ -488,10 +2175,12 @@ impl QUICServerHandler {
         let mut download_piece_bytes = BytesMut::with_capacity(header_length);
         download_piece_bytes.resize(header_length, 0);
 
-        reader
-            .read_exact(&mut download_piece_bytes)
-            .await
-            .inspect_err(|err| error!("failed to receive download piece: {}", err))?;
+        timeout(
+            self.request_timeout(),
+            reader.read_exact(&mut download_piece_bytes),
+        )
+        .await?
+        .inspect_err(|err| error!("failed to receive download piece: {}", err))?;
 
         download_piece_bytes.freeze().try_into().map_err(Into::into)
     }
@@ -507,30 +2196,2723 @@ impl QUICServerHandler {
         request: Bytes,
         writer: &mut quinn::SendStream,
     ) -> ClientResult<()> {
-        writer
-            .write_all(&request)
-            .await
+        timeout(self.request_timeout(), writer.write_all(&request))
+            .await?
             .inspect_err(|err| error!("failed to send request: {}", err))?;
 
         Ok(())
     }
 
+    /// Writes the error response to the QUIC stream and finishes the stream.
+    ///
+    /// The error carries the status code of the failure, so the peer can map it
+    /// to the corresponding client error instead of observing a dropped stream.
+    #[instrument(skip_all)]
+    async fn write_error(&self, err: Error, writer: &mut quinn::SendStream) -> ClientResult<()> {
+        let error_response: Bytes = Vortex::Error(Header::new_error(err.len() as u32), err).into();
+        self.write_response(error_response, writer).await?;
+
+        if let Err(err) = writer.finish() {
+            error!("failed to finish stream: {}", err);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the response of the piece and streams the piece content, then finishes the
+    /// stream unless the peer has stopped it.
+    #[instrument(skip_all)]
+    async fn write_piece_content<R: AsyncRead + Unpin + ?Sized>(
+        &self,
+        response: Bytes,
+        content_reader: &mut R,
+        writer: &mut quinn::SendStream,
+        record: &mut StreamRecord,
+    ) -> ClientResult<()> {
+        let network_started_at = Instant::now();
+        self.write_response(response, writer).await?;
+        record.network_cost += network_started_at.elapsed();
+
+        self.write_stream(content_reader, writer, record).await?;
+        if record.result == STREAM_RESULT_CANCELLED {
+            return Ok(());
+        }
+
+        if let Err(err) = writer.finish() {
+            error!("failed to finish stream: {}", err);
+        }
+
+        Ok(())
+    }
+
     /// Streams data from a reader directly to the QUIC writer.
     ///
-    /// This function efficiently copies all data from the provided stream
-    /// to the QUIC connection using tokio's copy utility. It's designed for
-    /// streaming large piece content without loading everything into memory.
-    /// The operation is flushed to ensure data delivery.
+    /// This function reads the data from the provided stream chunk by chunk and
+    /// hands each chunk over to the QUIC connection, recording the number of the
+    /// written bytes. The chunks are frozen into Bytes and written with write_chunk,
+    /// so they are not copied again into the send buffer of the stream, and the
+    /// memory of a piece is bounded by the write buffer size and the send window instead
+    /// of the piece length. The request timeout applies to each chunk, so the large
+    /// pieces which are still making progress are not interrupted. If the peer stops the
+    /// stream, such as it has got the piece from a faster parent, the storage read is
+    /// aborted and the result of the stream is recorded as cancelled.
     #[instrument(skip_all)]
     async fn write_stream<R: AsyncRead + Unpin + ?Sized>(
         &self,
         stream: &mut R,
         writer: &mut quinn::SendStream,
+        record: &mut StreamRecord,
     ) -> ClientResult<()> {
-        copy(stream, writer)
-            .await
-            .inspect_err(|err| error!("copy failed: {}", err))?;
+        // The empty buffer reads nothing and would end the piece early, so it is clamped.
+        let write_buffer_size =
+            (self.config.storage.server.quic.write_buffer_size.as_u64() as usize).max(1);
+        let mut buffer = BytesMut::with_capacity(write_buffer_size);
+
+        // The stopped future doesn't borrow the stream, so it is polled along with the reads.
+        let stopped = writer.stopped();
+        tokio::pin!(stopped);
+        loop {
+            // The split chunks are owned by the send stream until they are acknowledged,
+            // so reserve reuses the buffer once they are released.
+            buffer.reserve(write_buffer_size);
+            let storage_started_at = Instant::now();
+            let n = tokio::select! {
+                biased;
+                _ = &mut stopped => {
+                    debug!("stream is stopped by the peer after {} bytes sent", record.sent_bytes);
+                    record.result = STREAM_RESULT_CANCELLED;
+                    return Ok(());
+                }
+                n = timeout(
+                    self.request_timeout(),
+                    (&mut *stream).take(write_buffer_size as u64).read_buf(&mut buffer),
+                ) => n?.inspect_err(|err| error!("failed to read piece content: {}", err))?,
+            };
+            record.storage_cost += storage_started_at.elapsed();
+            if n == 0 {
+                break;
+            }
+
+            // The chunk is buffered until the stream accepts it, the next chunk is not read
+            // from the storage before that.
+            collect_quic_buffered_bytes_metrics(n as i64);
+            let network_started_at = Instant::now();
+
+            // Acquire the upload rate limiter chunk by chunk, so the large pieces don't
+            // monopolize the upload bandwidth shared by all connections.
+            if self.config.upload.rate_limit.as_u64() > 0 {
+                self.upload_rate_limiter.acquire(n).await;
+            }
+
+            let result = timeout(
+                self.request_timeout(),
+                writer.write_chunk(buffer.split().freeze()),
+            )
+            .await;
+            collect_quic_buffered_bytes_metrics(-(n as i64));
+            record.network_cost += network_started_at.elapsed();
+            match result? {
+                Ok(()) => record.sent_bytes += n as u64,
+                Err(quinn::WriteError::Stopped(_)) => {
+                    debug!(
+                        "stream is stopped by the peer after {} bytes sent",
+                        record.sent_bytes
+                    );
+                    record.result = STREAM_RESULT_CANCELLED;
+                    return Ok(());
+                }
+                Err(err) => {
+                    error!("failed to send piece content: {}", err);
+                    return Err(err.into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the error if the piece length exceeds the max piece size.
+    fn check_piece_size(&self, piece_id: &str, length: u64) -> Result<(), Error> {
+        let max_piece_size = self.config.storage.server.quic.max_piece_size.as_u64();
+        if length > max_piece_size {
+            error!(
+                "piece {} length {} exceeds the max piece size {}",
+                piece_id, length, max_piece_size
+            );
+            return Err(Error::new(
+                Code::Internal,
+                format!(
+                    "piece {} length {} exceeds the max piece size {}",
+                    piece_id, length, max_piece_size
+                ),
+            ));
+        }
 
         Ok(())
     }
+
+    /// Acquires the permit of the in-flight streams of the task, it waits for the in-flight
+    /// streams of the task to finish or returns the overloaded error when the task reaches
+    /// the limit, depending on queue_task_streams.
+    async fn acquire_task_permit(
+        &self,
+        task_id: &str,
+    ) -> Result<Option<KeyedPermit<String>>, Error> {
+        let Some(task_stream_limiter) = self.task_stream_limiter.as_ref() else {
+            return Ok(None);
+        };
+
+        if self.config.storage.server.quic.queue_task_streams {
+            return Ok(Some(task_stream_limiter.acquire(task_id.to_string()).await));
+        }
+
+        match task_stream_limiter.try_acquire(task_id.to_string()) {
+            Some(permit) => Ok(Some(permit)),
+            None => Err(overloaded_error(
+                format!("task {} reaches the concurrent streams limit", task_id),
+                DEFAULT_OVERLOADED_RETRY_AFTER,
+            )),
+        }
+    }
+
+    /// Acquires the storage worker to read the piece, it waits in the storage worker queue if
+    /// the workers are busy, and returns the overloaded error if the queue is full. It returns
+    /// None if the storage workers are disabled.
+    async fn acquire_storage_worker(&self) -> Result<Option<StorageWorkerPermit>, Error> {
+        let Some(storage_workers) = self.storage_workers.as_ref() else {
+            return Ok(None);
+        };
+
+        match storage_workers.admit().await {
+            Some(permit) => Ok(Some(StorageWorkerPermit::new(permit))),
+            None => Err(overloaded_error(
+                "storage worker queue is full".to_string(),
+                DEFAULT_OVERLOADED_RETRY_AFTER,
+            )),
+        }
+    }
+
+    /// Pins the task until the piece is served, it responds the not found error if the task
+    /// is being deleted by the garbage collection, instead of serving the piece halfway.
+    fn pin_task(&self, task_id: &str) -> Result<TaskPinGuard, Error> {
+        self.storage.pin_task(task_id).map_err(|err| {
+            debug!("task {} is being deleted: {}", task_id, err);
+            Error::new(Code::NotFound, format!("task {} is being deleted", task_id))
+        })
+    }
+
+    /// Returns whether the peer is banned for the protocol violations.
+    fn is_peer_banned(&self, ip: IpAddr) -> bool {
+        self.peer_bans
+            .as_ref()
+            .is_some_and(|peer_bans| peer_bans.is_banned(ip))
+    }
+
+    /// Records a protocol violation of the peer, the peer is banned when the violations reach
+    /// the threshold.
+    fn record_violation(&self, ip: IpAddr) {
+        if let Some(peer_bans) = self.peer_bans.as_ref() {
+            peer_bans.record_violation(ip);
+        }
+    }
+
+    /// Returns the timeout of reading the request and writing each chunk of the response.
+    fn request_timeout(&self) -> Duration {
+        self.config.storage.server.quic.request_timeout
+    }
+}
+
+/// forward_incoming forwards the incoming connections of the endpoint to the accept loop, and
+/// reports the index of the endpoint once it is closed.
+fn forward_incoming(
+    index: usize,
+    endpoint: Endpoint,
+    incoming_tx: mpsc::Sender<quinn::Incoming>,
+    closed_tx: mpsc::UnboundedSender<usize>,
+) {
+    tokio::spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            if incoming_tx.send(incoming).await.is_err() {
+                return;
+            }
+        }
+
+        let _ = closed_tx.send(index);
+    });
+}
+
+/// echo_pings echoes the ping datagrams of the connection with the pong datagrams carrying the
+/// build information and the uptime of the server, until the connection is closed. The build
+/// information of the peer is logged once per connection. The datagrams are unreliable, so the
+/// failed echo is just ignored.
+async fn echo_pings(
+    connection: quinn::Connection,
+    remote_address: SocketAddr,
+    stats: Arc<QUICServerStats>,
+) {
+    let mut build_info_logged = false;
+    while let Ok(bytes) = connection.read_datagram().await {
+        let ping = match Datagram::try_from(bytes) {
+            Ok(ping) => ping,
+            Err(err) => {
+                debug!("ignore invalid datagram from {}: {}", remote_address, err);
+                continue;
+            }
+        };
+
+        if let Datagram::Ping {
+            build_info: Some(build_info),
+            ..
+        } = &ping
+        {
+            if !build_info_logged {
+                info!(
+                    "peer {} runs version {} of commit {}",
+                    remote_address, build_info.version, build_info.commit
+                );
+                build_info_logged = true;
+            }
+        }
+
+        let Some(pong) = ping.pong(Some(BuildInfo::new(Some(stats.started_at.elapsed())))) else {
+            debug!("ignore unexpected pong from {}", remote_address);
+            continue;
+        };
+
+        if let Err(err) = connection.send_datagram(pong.into()) {
+            debug!("failed to echo ping to {}: {}", remote_address, err);
+        }
+    }
+}
+
+/// audit_log emits the audit event of the piece served to the peer, including the identity
+/// of the peer if the mutual TLS is enabled. The time of the event is recorded by the sink.
+fn audit_log(remote_address: SocketAddr, peer_identity: Option<&str>, record: &StreamRecord) {
+    info!(
+        target: AUDIT_LOG_TARGET,
+        remote_address = %remote_address,
+        peer_identity = peer_identity.unwrap_or("-"),
+        typ = record.typ,
+        task_id = record.task_id.as_str(),
+        piece_number = record.piece_number,
+        sent_bytes = record.sent_bytes,
+        result = record.result,
+        "quic audit log"
+    );
+}
+
+/// slow_request_log emits the warning of the slow request with the durations of its phases,
+/// so the slowness can be told apart between the peer, the storage and the network.
+fn slow_request_log(remote_address: SocketAddr, record: &StreamRecord, cost: Duration) {
+    warn!(
+        remote_address = %remote_address,
+        typ = record.typ,
+        task_id = record.task_id.as_str(),
+        piece_number = record.piece_number,
+        sent_bytes = record.sent_bytes,
+        cost_ms = cost.as_millis() as u64,
+        request_ms = record.request_cost.as_millis() as u64,
+        storage_ms = record.storage_cost.as_millis() as u64,
+        network_ms = record.network_cost.as_millis() as u64,
+        slowest_phase = record.slowest_phase(),
+        result = record.result,
+        "quic slow request"
+    );
+}
+
+/// overloaded_error returns the overloaded error with the retry-after hint, the hint is appended
+/// to the error message since the vortex error has no field for it.
+pub(crate) fn overloaded_error(reason: String, retry_after: Duration) -> Error {
+    Error::new(
+        Code::Reserved(VORTEX_ERROR_CODE_OVERLOADED),
+        format!("{}, retry after {}ms", reason, retry_after.as_millis()),
+    )
+}
+
+/// overloaded_retry_after returns the retry-after hint of the message of the overloaded error,
+/// it returns None if the message carries no hint.
+pub(crate) fn overloaded_retry_after(message: &str) -> Option<Duration> {
+    let (_, retry_after) = message.rsplit_once(", retry after ")?;
+    retry_after
+        .strip_suffix("ms")?
+        .parse()
+        .ok()
+        .map(Duration::from_millis)
+}
+
+/// reset_stream stops the request and resets the response of the stream with the code, so the
+/// peer tells the rejection, such as the overloaded server, apart from the other errors by the
+/// code instead of the error message.
+fn reset_stream(code: u32, reader: &mut quinn::RecvStream, writer: &mut quinn::SendStream) {
+    let _ = reader.stop(VarInt::from_u32(code));
+    if let Err(err) = writer.reset(VarInt::from_u32(code)) {
+        error!("failed to reset stream: {}", err);
+    }
+}
+
+/// endpoint_config returns the endpoint configuration of the transport. The maximum udp payload
+/// size advertised to the peers is raised to the upper bound of the path mtu discovery, otherwise
+/// the peers never send the datagrams larger than the default.
+pub(crate) fn endpoint_config(transport: &QUICTransport) -> ClientResult<EndpointConfig> {
+    let mut endpoint_config = EndpointConfig::default();
+    if transport.mtu_discovery {
+        endpoint_config
+            .max_udp_payload_size(
+                transport
+                    .mtu_discovery_upper_bound
+                    .max(DEFAULT_MAX_UDP_PAYLOAD_SIZE),
+            )
+            .map_err(|err| {
+                ClientError::ValidationError(format!(
+                    "invalid quic mtu discovery upper bound {}: {}",
+                    transport.mtu_discovery_upper_bound, err
+                ))
+            })?;
+    }
+
+    Ok(endpoint_config)
+}
+
+/// bind_endpoint binds the endpoint on the address. If reuse_port is true, the socket is bound
+/// with SO_REUSEPORT, so multiple endpoints can share the same address. If only_v6 is true,
+/// the IPv6 socket doesn't accept the IPv4-mapped addresses.
+fn bind_endpoint(
+    endpoint_config: EndpointConfig,
+    server_config: ServerConfig,
+    addr: SocketAddr,
+    reuse_port: bool,
+    only_v6: bool,
+) -> ClientResult<Endpoint> {
+    let runtime = quinn::default_runtime()
+        .ok_or_else(|| ClientError::Unknown("no async runtime found".to_string()))?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(only_v6)?;
+    }
+    socket.bind(&addr.into())?;
+
+    Ok(Endpoint::new(
+        endpoint_config,
+        Some(server_config),
+        socket.into(),
+        runtime,
+    )?)
+}
+
+/// QUICServerStats is the statistics of the QUIC server. The counters are updated with the
+/// relaxed atomics, so they don't add any lock on the hot path.
+#[derive(Debug)]
+pub struct QUICServerStats {
+    /// started_at is the time when the QUIC server is created.
+    started_at: Instant,
+
+    /// active_connections is the number of the established connections.
+    active_connections: AtomicU64,
+
+    /// active_streams is the number of the in-flight streams.
+    active_streams: AtomicU64,
+
+    /// download_piece_requests is the number of the download piece requests.
+    download_piece_requests: AtomicU64,
+
+    /// download_persistent_cache_piece_requests is the number of the download persistent
+    /// cache piece requests.
+    download_persistent_cache_piece_requests: AtomicU64,
+
+    /// received_bytes is the number of the received bytes of the requests.
+    received_bytes: AtomicU64,
+
+    /// sent_bytes is the number of the sent bytes of the piece content.
+    sent_bytes: AtomicU64,
+
+    /// refused_connections is the number of the refused connections.
+    refused_connections: AtomicU64,
+
+    /// idle_closed_connections is the number of the connections closed by the idle timeout,
+    /// such as the peer is gone or its NAT mapping is dropped.
+    idle_closed_connections: AtomicU64,
+
+    /// connections is the statistics of the established connections, keyed by the stable id
+    /// of the connection.
+    connections: Mutex<HashMap<usize, Arc<QUICConnectionStats>>>,
+
+    /// peer_traffic is the bytes uploaded to each peer for each task since the last drain, it
+    /// is aggregated across the connections of the peer.
+    peer_traffic: Mutex<HashMap<(IpAddr, String), u64>>,
+}
+
+/// QUICServerStats implements the statistics of the QUIC server.
+impl QUICServerStats {
+    /// new creates a new QUICServerStats.
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            active_connections: AtomicU64::new(0),
+            active_streams: AtomicU64::new(0),
+            download_piece_requests: AtomicU64::new(0),
+            download_persistent_cache_piece_requests: AtomicU64::new(0),
+            received_bytes: AtomicU64::new(0),
+            sent_bytes: AtomicU64::new(0),
+            refused_connections: AtomicU64::new(0),
+            idle_closed_connections: AtomicU64::new(0),
+            connections: Mutex::new(HashMap::new()),
+            peer_traffic: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// record_peer_traffic records the bytes uploaded to the peer for the task.
+    fn record_peer_traffic(&self, remote_ip: IpAddr, task_id: &str, bytes: u64) {
+        *self
+            .peer_traffic
+            .lock()
+            .unwrap()
+            .entry((remote_ip, task_id.to_string()))
+            .or_default() += bytes;
+    }
+
+    /// drain_peer_traffic returns the traffic uploaded to the peers since the last drain and
+    /// resets it, it is drained by the scheduler announcer on the announce interval.
+    pub fn drain_peer_traffic(&self) -> Vec<QUICPeerTraffic> {
+        std::mem::take(&mut *self.peer_traffic.lock().unwrap())
+            .into_iter()
+            .map(|((remote_ip, task_id), bytes)| QUICPeerTraffic {
+                remote_ip,
+                task_id,
+                traffic_type: TrafficType::RemotePeer,
+                bytes,
+            })
+            .collect()
+    }
+
+    /// add_connection adds the statistics of the established connection.
+    fn add_connection(
+        &self,
+        connection: quinn::Connection,
+        remote_address: SocketAddr,
+    ) -> Arc<QUICConnectionStats> {
+        let id = connection.stable_id();
+        let connection_stats = Arc::new(QUICConnectionStats {
+            connection,
+            remote_address,
+            served_bytes: AtomicU64::new(0),
+        });
+
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(id, connection_stats.clone());
+        connection_stats
+    }
+
+    /// remove_connection removes the statistics of the closed connection.
+    fn remove_connection(&self, id: usize) {
+        self.connections.lock().unwrap().remove(&id);
+    }
+
+    /// record_stream records the request and the traffic of the finished stream.
+    fn record_stream(&self, record: &StreamRecord) {
+        match record.typ {
+            "download_piece" => {
+                self.download_piece_requests.fetch_add(1, Ordering::Relaxed);
+            }
+            "download_persistent_cache_piece" => {
+                self.download_persistent_cache_piece_requests
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+
+        self.received_bytes
+            .fetch_add(record.received_bytes, Ordering::Relaxed);
+        self.sent_bytes
+            .fetch_add(record.sent_bytes, Ordering::Relaxed);
+    }
+
+    /// snapshot returns the current values of the statistics.
+    pub fn snapshot(&self) -> QUICServerStatsSnapshot {
+        QUICServerStatsSnapshot {
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            download_piece_requests: self.download_piece_requests.load(Ordering::Relaxed),
+            download_persistent_cache_piece_requests: self
+                .download_persistent_cache_piece_requests
+                .load(Ordering::Relaxed),
+            received_bytes: self.received_bytes.load(Ordering::Relaxed),
+            sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
+            refused_connections: self.refused_connections.load(Ordering::Relaxed),
+            idle_closed_connections: self.idle_closed_connections.load(Ordering::Relaxed),
+            connections: self
+                .connections
+                .lock()
+                .unwrap()
+                .values()
+                .map(|connection_stats| {
+                    let path = connection_stats.connection.stats().path;
+                    QUICConnectionStatsSnapshot {
+                        remote_address: connection_stats.remote_address,
+                        served_bytes: connection_stats.served_bytes.load(Ordering::Relaxed),
+                        rtt: path.rtt,
+                        cwnd: path.cwnd,
+                        sent_packets: path.sent_packets,
+                        lost_packets: path.lost_packets,
+                        lost_bytes: path.lost_bytes,
+                    }
+                })
+                .collect(),
+            uptime: self.started_at.elapsed(),
+        }
+    }
+}
+
+/// QUICPeerTraffic is the traffic uploaded to a peer for a task by the QUIC server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QUICPeerTraffic {
+    /// remote_ip is the ip of the peer.
+    pub remote_ip: IpAddr,
+
+    /// task_id is the id of the task.
+    pub task_id: String,
+
+    /// traffic_type is the type of the traffic, the pieces served to the peers are the remote
+    /// peer traffic.
+    pub traffic_type: TrafficType,
+
+    /// bytes is the number of the uploaded bytes.
+    pub bytes: u64,
+}
+
+/// QUICConnectionStats is the statistics of an established connection of the QUIC server.
+#[derive(Debug)]
+struct QUICConnectionStats {
+    /// connection is the connection to sample the path statistics from.
+    connection: quinn::Connection,
+
+    /// remote_address is the address of the peer.
+    remote_address: SocketAddr,
+
+    /// served_bytes is the number of the piece content bytes served on the connection.
+    served_bytes: AtomicU64,
+}
+
+/// QUICServerStatsSnapshot is the snapshot of the statistics of the QUIC server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QUICServerStatsSnapshot {
+    /// active_connections is the number of the established connections.
+    pub active_connections: u64,
+
+    /// active_streams is the number of the in-flight streams.
+    pub active_streams: u64,
+
+    /// download_piece_requests is the number of the download piece requests.
+    pub download_piece_requests: u64,
+
+    /// download_persistent_cache_piece_requests is the number of the download persistent
+    /// cache piece requests.
+    pub download_persistent_cache_piece_requests: u64,
+
+    /// received_bytes is the number of the received bytes of the requests.
+    pub received_bytes: u64,
+
+    /// sent_bytes is the number of the sent bytes of the piece content.
+    pub sent_bytes: u64,
+
+    /// refused_connections is the number of the refused connections.
+    pub refused_connections: u64,
+
+    /// idle_closed_connections is the number of the connections closed by the idle timeout.
+    pub idle_closed_connections: u64,
+
+    /// connections is the snapshot of the established connections.
+    pub connections: Vec<QUICConnectionStatsSnapshot>,
+
+    /// uptime is the duration since the QUIC server is created.
+    pub uptime: Duration,
+}
+
+/// QUICConnectionStatsSnapshot is the snapshot of the statistics of an established connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QUICConnectionStatsSnapshot {
+    /// remote_address is the address of the peer.
+    pub remote_address: SocketAddr,
+
+    /// served_bytes is the number of the piece content bytes served on the connection.
+    pub served_bytes: u64,
+
+    /// rtt is the current estimate of the round trip time of the connection.
+    pub rtt: Duration,
+
+    /// cwnd is the current congestion window of the connection in bytes.
+    pub cwnd: u64,
+
+    /// sent_packets is the number of the packets sent on the connection.
+    pub sent_packets: u64,
+
+    /// lost_packets is the number of the packets declared lost on the connection.
+    pub lost_packets: u64,
+
+    /// lost_bytes is the number of the bytes declared lost on the connection.
+    pub lost_bytes: u64,
+}
+
+/// connection_loss_rate returns the ratio of the lost packets to the sent packets of the
+/// connection.
+fn connection_loss_rate(lost_packets: u64, sent_packets: u64) -> f64 {
+    if sent_packets == 0 {
+        return 0.0;
+    }
+
+    lost_packets as f64 / sent_packets as f64
+}
+
+/// StreamRecord records the request and result of the stream for the metrics and the
+/// access log.
+struct StreamRecord {
+    /// typ is the request type of the stream.
+    typ: &'static str,
+
+    /// task_id is the task id of the request.
+    task_id: String,
+
+    /// piece_number is the piece number of the request.
+    piece_number: Option<u32>,
+
+    /// result is the result of the stream, such as ok, not_found and timeout.
+    result: &'static str,
+
+    /// sent_bytes is the number of the piece content bytes sent to the peer.
+    sent_bytes: u64,
+
+    /// received_bytes is the number of the received bytes of the request.
+    received_bytes: u64,
+
+    /// fallback_task_id is the id of the persistent cache task which serves the missing piece.
+    fallback_task_id: Option<String>,
+
+    /// started_at is the time when the stream is accepted.
+    started_at: Instant,
+
+    /// request_cost is the duration from accepting the stream to receiving the request,
+    /// including the admission of the stream.
+    request_cost: Duration,
+
+    /// storage_cost is the duration of reading the piece from the storage.
+    storage_cost: Duration,
+
+    /// network_cost is the duration of sending the response to the peer, including the upload
+    /// rate limit.
+    network_cost: Duration,
+}
+
+/// StreamRecord implements the record of the stream.
+impl StreamRecord {
+    /// phases returns the durations of the phases of the stream.
+    fn phases(&self) -> [(&'static str, Duration); 3] {
+        [
+            (STREAM_PHASE_REQUEST, self.request_cost),
+            (STREAM_PHASE_STORAGE, self.storage_cost),
+            (STREAM_PHASE_NETWORK, self.network_cost),
+        ]
+    }
+
+    /// slowest_phase returns the phase of the stream which costs the most.
+    fn slowest_phase(&self) -> &'static str {
+        self.phases()
+            .into_iter()
+            .max_by_key(|(_, cost)| *cost)
+            .map(|(phase, _)| phase)
+            .unwrap_or(STREAM_PHASE_REQUEST)
+    }
+}
+
+/// StreamRecord implements Default.
+impl Default for StreamRecord {
+    fn default() -> Self {
+        Self {
+            typ: "unknown",
+            task_id: String::new(),
+            piece_number: None,
+            result: STREAM_RESULT_OK,
+            sent_bytes: 0,
+            received_bytes: 0,
+            fallback_task_id: None,
+            started_at: Instant::now(),
+            request_cost: Duration::ZERO,
+            storage_cost: Duration::ZERO,
+            network_cost: Duration::ZERO,
+        }
+    }
+}
+
+/// PersistentCacheFallback is the persistent cache task serving the missing piece, the task is
+/// pinned until the piece is served.
+struct PersistentCacheFallback {
+    /// task_id is the id of the persistent cache task.
+    task_id: String,
+
+    /// traffic_type is the traffic type of the persistent cache piece serving the missing piece.
+    traffic_type: TrafficType,
+
+    /// _pin is the pin of the persistent cache task.
+    _pin: TaskPinGuard,
+}
+
+/// PeerViolations is the protocol violations and the ban of a peer.
+#[derive(Default)]
+struct PeerViolations {
+    /// violated_at is the time of the violations within the window.
+    violated_at: VecDeque<Instant>,
+
+    /// banned_until is the time when the ban of the peer expires.
+    banned_until: Option<Instant>,
+}
+
+/// PeerBans counts the protocol violations of the peers in a sliding window, and bans the peers
+/// whose violations reach the threshold for the ban duration. The expired bans and the stale
+/// violations are evicted to avoid unbounded growth.
+struct PeerBans {
+    /// threshold is the number of the violations within the window to ban the peer.
+    threshold: usize,
+
+    /// window is the sliding window to count the violations.
+    window: Duration,
+
+    /// duration is the duration of the ban.
+    duration: Duration,
+
+    /// peers is the violations of the peers, keyed by the remote ip.
+    peers: Mutex<HashMap<IpAddr, PeerViolations>>,
+
+    /// evicted_at is the time when the stale peers are evicted.
+    evicted_at: Mutex<Instant>,
+}
+
+/// PeerBans implements the ban of the peers.
+impl PeerBans {
+    /// new creates a new PeerBans.
+    fn new(threshold: usize, window: Duration, duration: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            window,
+            duration,
+            peers: Mutex::new(HashMap::new()),
+            evicted_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// is_banned returns whether the peer is banned.
+    fn is_banned(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.evict(now);
+
+        let mut peers = self.peers.lock().unwrap();
+        let Some(peer) = peers.get_mut(&ip) else {
+            return false;
+        };
+
+        match peer.banned_until {
+            Some(banned_until) if banned_until > now => true,
+            Some(_) => {
+                info!("ban of peer {} expired", ip);
+                peer.banned_until = None;
+                peer.violated_at.clear();
+                collect_quic_banned_peer_metrics(Self::banned_count(&peers, now));
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// record_violation records a violation of the peer, and bans the peer if the violations
+    /// within the window reach the threshold. It returns whether the peer is banned.
+    fn record_violation(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.evict(now);
+
+        let mut peers = self.peers.lock().unwrap();
+        let peer = peers.entry(ip).or_default();
+        if peer
+            .banned_until
+            .is_some_and(|banned_until| banned_until > now)
+        {
+            return true;
+        }
+
+        peer.violated_at.push_back(now);
+        while peer
+            .violated_at
+            .front()
+            .is_some_and(|violated_at| now.duration_since(*violated_at) > self.window)
+        {
+            peer.violated_at.pop_front();
+        }
+
+        if peer.violated_at.len() < self.threshold {
+            return false;
+        }
+
+        warn!(
+            "ban peer {} for {:?}, {} protocol violations within {:?}",
+            ip,
+            self.duration,
+            peer.violated_at.len(),
+            self.window
+        );
+        peer.banned_until = Some(now + self.duration);
+        collect_quic_banned_peer_metrics(Self::banned_count(&peers, now));
+        true
+    }
+
+    /// evict removes the expired bans and the peers without the violations within the window,
+    /// it runs at most once per window.
+    fn evict(&self, now: Instant) {
+        let mut evicted_at = self.evicted_at.lock().unwrap();
+        if now.duration_since(*evicted_at) < self.window {
+            return;
+        }
+
+        *evicted_at = now;
+        let mut peers = self.peers.lock().unwrap();
+        peers.retain(|ip, peer| {
+            if let Some(banned_until) = peer.banned_until {
+                if banned_until > now {
+                    return true;
+                }
+
+                info!("ban of peer {} expired", ip);
+                return false;
+            }
+
+            peer.violated_at
+                .back()
+                .is_some_and(|violated_at| now.duration_since(*violated_at) <= self.window)
+        });
+        collect_quic_banned_peer_metrics(Self::banned_count(&peers, now));
+    }
+
+    /// banned_count returns the number of the banned peers.
+    fn banned_count(peers: &HashMap<IpAddr, PeerViolations>, now: Instant) -> usize {
+        peers
+            .values()
+            .filter(|peer| {
+                peer.banned_until
+                    .is_some_and(|banned_until| banned_until > now)
+            })
+            .count()
+    }
+}
+
+/// DispatchQueue bounds the streams dispatched to the handlers, and the streams waiting for the
+/// dispatch. The streams beyond the queue depth are rejected immediately. It is also used by
+/// the storage workers to bound the pieces read from the storage.
+struct DispatchQueue {
+    /// semaphore limits the streams dispatched to the handlers concurrently.
+    semaphore: Arc<Semaphore>,
+
+    /// depth is the number of the streams waiting to be dispatched.
+    depth: AtomicUsize,
+
+    /// max_depth is the maximum number of the streams waiting to be dispatched.
+    max_depth: usize,
+
+    /// collect_depth_metrics collects the depth metrics of the queue.
+    collect_depth_metrics: fn(i64),
+}
+
+/// DispatchQueue implements the bounded dispatch queue.
+impl DispatchQueue {
+    /// new creates a new DispatchQueue.
+    fn new(
+        max_concurrent_dispatches: usize,
+        max_depth: usize,
+        collect_depth_metrics: fn(i64),
+    ) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(
+                max_concurrent_dispatches.clamp(1, Semaphore::MAX_PERMITS),
+            )),
+            depth: AtomicUsize::new(0),
+            max_depth,
+            collect_depth_metrics,
+        }
+    }
+
+    /// admit returns the permit to dispatch the stream, it waits in the queue if the handlers
+    /// are busy, and returns None if the queue is full.
+    async fn admit(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+
+        if self.depth.fetch_add(1, Ordering::AcqRel) >= self.max_depth {
+            self.depth.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+
+        // Leave the queue when the stream is dispatched or cancelled, e.g. by the timeout.
+        let _slot = DispatchQueueSlot::new(&self.depth, self.collect_depth_metrics);
+        self.semaphore.clone().acquire_owned().await.ok()
+    }
+}
+
+/// DispatchQueueSlot is the slot of a stream waiting in the dispatch queue, it leaves the queue
+/// when dropped.
+struct DispatchQueueSlot<'a> {
+    /// depth is the depth of the dispatch queue.
+    depth: &'a AtomicUsize,
+
+    /// collect_depth_metrics collects the depth metrics of the dispatch queue.
+    collect_depth_metrics: fn(i64),
+}
+
+/// DispatchQueueSlot implements the slot of the dispatch queue.
+impl<'a> DispatchQueueSlot<'a> {
+    /// new creates a new DispatchQueueSlot, the depth has been increased by the caller.
+    fn new(depth: &'a AtomicUsize, collect_depth_metrics: fn(i64)) -> Self {
+        collect_depth_metrics(1);
+        Self {
+            depth,
+            collect_depth_metrics,
+        }
+    }
+}
+
+/// DispatchQueueSlot implements Drop.
+impl Drop for DispatchQueueSlot<'_> {
+    fn drop(&mut self) {
+        self.depth.fetch_sub(1, Ordering::AcqRel);
+        (self.collect_depth_metrics)(-1);
+    }
+}
+
+/// StorageWorkerPermit holds a storage worker, and releases it with the busy metrics when the
+/// piece is served.
+struct StorageWorkerPermit {
+    /// _permit is the permit of the storage workers.
+    _permit: OwnedSemaphorePermit,
+}
+
+/// StorageWorkerPermit implements the permit of the storage workers.
+impl StorageWorkerPermit {
+    /// new creates a new StorageWorkerPermit.
+    fn new(permit: OwnedSemaphorePermit) -> Self {
+        collect_quic_storage_worker_busy_metrics(1);
+        Self { _permit: permit }
+    }
+}
+
+/// StorageWorkerPermit implements Drop.
+impl Drop for StorageWorkerPermit {
+    fn drop(&mut self) {
+        collect_quic_storage_worker_busy_metrics(-1);
+    }
+}
+
+/// StorageWorkerReader reads the piece content with the storage worker, it holds the storage
+/// worker while a chunk is read from the storage and releases it once the chunk is read, so
+/// the memory is bounded by the chunk instead of the piece.
+struct StorageWorkerReader<R> {
+    /// inner is the reader of the piece content.
+    inner: R,
+
+    /// storage_workers is used to acquire the storage worker before the next chunk is read.
+    storage_workers: PollSemaphore,
+
+    /// storage_worker is the storage worker held while the chunk is read.
+    storage_worker: Option<StorageWorkerPermit>,
+}
+
+/// StorageWorkerReader implements AsyncRead.
+impl<R: AsyncRead + Unpin> AsyncRead for StorageWorkerReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        // The stream is admitted already, so it waits for the storage worker without the queue.
+        if self.storage_worker.is_none() {
+            match ready!(self.storage_workers.poll_acquire(cx)) {
+                Some(permit) => self.storage_worker = Some(StorageWorkerPermit::new(permit)),
+                None => {
+                    return Poll::Ready(Err(std::io::Error::other("storage workers are closed")))
+                }
+            }
+        }
+
+        let result = ready!(Pin::new(&mut self.inner).poll_read(cx, buf));
+        self.storage_worker = None;
+        Poll::Ready(result)
+    }
+}
+
+/// PieceFlights deduplicates the concurrent reads of the same piece. The first request reads
+/// the piece into a shared buffer, and the concurrent requests of the piece wait for the read
+/// and share the buffer. The bytes of the shared buffers are bounded by the maximum size.
+struct PieceFlights {
+    /// flights is the in-flight reads keyed by the piece id.
+    flights: Mutex<HashMap<String, Weak<PieceFlight>>>,
+
+    /// size is the bytes reserved by the in-flight reads.
+    size: Arc<AtomicU64>,
+
+    /// max_size is the maximum bytes reserved by the in-flight reads.
+    max_size: u64,
+}
+
+/// PieceFlights implements the deduplication of the piece reads.
+impl PieceFlights {
+    /// new creates a new PieceFlights.
+    fn new(max_size: u64) -> Arc<Self> {
+        Arc::new(Self {
+            flights: Mutex::new(HashMap::new()),
+            size: Arc::new(AtomicU64::new(0)),
+            max_size,
+        })
+    }
+
+    /// join joins the in-flight read of the piece, or starts a new one if there is none. It
+    /// returns None if the piece exceeds the remaining size of the shared buffers, then the
+    /// piece is read by the request itself.
+    fn join(self: &Arc<Self>, piece_id: &str, length: u64) -> Option<PieceFlightGuard> {
+        let mut flights = self.flights.lock().unwrap();
+        let flight = match flights.get(piece_id).and_then(Weak::upgrade) {
+            Some(flight) => {
+                debug!("share the in-flight read of piece {}", piece_id);
+                flight
+            }
+            None => {
+                if self.size.load(Ordering::Acquire) + length > self.max_size {
+                    return None;
+                }
+
+                self.size.fetch_add(length, Ordering::AcqRel);
+                let flight = Arc::new(PieceFlight {
+                    content: tokio::sync::OnceCell::new(),
+                    length,
+                    size: self.size.clone(),
+                });
+                flights.insert(piece_id.to_string(), Arc::downgrade(&flight));
+                flight
+            }
+        };
+
+        Some(PieceFlightGuard {
+            flights: self.clone(),
+            piece_id: piece_id.to_string(),
+            flight: Some(flight),
+        })
+    }
+}
+
+/// PieceFlight is the in-flight read of a piece, the reserved bytes are released when the last
+/// request of the piece finishes.
+struct PieceFlight {
+    /// content is the result of the read, it is shared by all requests of the piece.
+    content: tokio::sync::OnceCell<Result<Bytes, Error>>,
+
+    /// length is the length of the piece.
+    length: u64,
+
+    /// size is the bytes reserved by the in-flight reads.
+    size: Arc<AtomicU64>,
+}
+
+/// PieceFlight implements Drop.
+impl Drop for PieceFlight {
+    fn drop(&mut self) {
+        self.size.fetch_sub(self.length, Ordering::AcqRel);
+    }
+}
+
+/// PieceFlightGuard holds the in-flight read of a piece until the request finishes.
+struct PieceFlightGuard {
+    /// flights is the in-flight reads.
+    flights: Arc<PieceFlights>,
+
+    /// piece_id is the id of the piece.
+    piece_id: String,
+
+    /// flight is the in-flight read, it is only taken when dropped.
+    flight: Option<Arc<PieceFlight>>,
+}
+
+/// PieceFlightGuard implements the in-flight read of a piece.
+impl PieceFlightGuard {
+    /// length returns the length of the piece.
+    fn length(&self) -> u64 {
+        self.flight.as_ref().map_or(0, |flight| flight.length)
+    }
+
+    /// content returns the content of the piece, it is read by the first request and the
+    /// concurrent requests wait for it. The error of the read is returned to all requests. If
+    /// the reading request is cancelled, one of the waiting requests reads it instead.
+    async fn content<F, Fut>(&self, read: F) -> Result<Bytes, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Bytes, Error>>,
+    {
+        let Some(flight) = self.flight.as_ref() else {
+            return Err(Error::new(
+                Code::Internal,
+                "piece flight is released".to_string(),
+            ));
+        };
+
+        match flight.content.get_or_init(read).await {
+            Ok(content) => Ok(content.clone()),
+            Err(err) => Err(Error::new(err.code(), err.message().to_string())),
+        }
+    }
+}
+
+/// PieceFlightGuard implements Drop.
+impl Drop for PieceFlightGuard {
+    fn drop(&mut self) {
+        // Release the flight under the lock, so the entry is removed only if no request holds
+        // it, and a new request never joins the released flight.
+        let mut flights = self.flights.flights.lock().unwrap();
+        drop(self.flight.take());
+        if flights
+            .get(&self.piece_id)
+            .is_some_and(|flight| flight.strong_count() == 0)
+        {
+            flights.remove(&self.piece_id);
+        }
+    }
+}
+
+/// SharedPieceReader reads the piece from the shared buffer, and holds the in-flight read of
+/// the piece until the piece is served.
+struct SharedPieceReader {
+    /// content is the shared buffer of the piece.
+    content: std::io::Cursor<Bytes>,
+
+    /// _flight is the in-flight read of the piece.
+    _flight: PieceFlightGuard,
+}
+
+/// SharedPieceReader implements AsyncRead.
+impl AsyncRead for SharedPieceReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.content).poll_read(cx, buf)
+    }
+}
+
+/// RequestPermit holds the bytes of the request budget, and releases them with the buffered
+/// request metrics when the stream completes.
+struct RequestPermit {
+    /// _permit is the permit of the request budget.
+    _permit: OwnedSemaphorePermit,
+
+    /// size is the number of the reserved bytes.
+    size: u32,
+}
+
+/// RequestPermit implements the request permit.
+impl RequestPermit {
+    /// new creates a new RequestPermit and collects the buffered request metrics.
+    fn new(permit: OwnedSemaphorePermit, size: u32) -> Self {
+        collect_quic_request_buffered_bytes_metrics(size as i64);
+        Self {
+            _permit: permit,
+            size,
+        }
+    }
+}
+
+/// RequestPermit implements Drop.
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        collect_quic_request_buffered_bytes_metrics(-(self.size as i64));
+    }
+}
+
+/// Returns whether the request is safe to be replayed, so it can be served from the early
+/// data. Only the read-only requests are replay-safe, the new tags must not be added here
+/// unless they don't mutate the state of the server.
+fn is_replay_safe(tag: Tag) -> bool {
+    matches!(tag, Tag::DownloadPiece | Tag::DownloadPersistentCachePiece)
+}
+
+/// Returns the error code of the storage error, the missing task or piece is reported as not
+/// found, so the peer can try the other parents instead of backing off.
+fn storage_error_code(err: &ClientError) -> Code {
+    match err {
+        ClientError::TaskNotFound(_) | ClientError::PieceNotFound(_) => Code::NotFound,
+        ClientError::IO(err) if err.kind() == std::io::ErrorKind::NotFound => Code::NotFound,
+        _ => Code::Internal,
+    }
+}
+
+/// Returns the label of the error code for the metrics.
+fn code_label(code: Code) -> &'static str {
+    match code {
+        Code::InvalidArgument => "invalid_argument",
+        Code::NotFound => "not_found",
+        Code::Internal => "internal",
+        Code::Reserved(VORTEX_ERROR_CODE_OVERLOADED) => "overloaded",
+        _ => "unknown",
+    }
+}
+
+/// Returns the label of the connection error for the metrics.
+fn connection_closed_reason(err: &quinn::ConnectionError) -> &'static str {
+    match err {
+        quinn::ConnectionError::ApplicationClosed(_) => "application_closed",
+        quinn::ConnectionError::LocallyClosed => "locally_closed",
+        quinn::ConnectionError::TimedOut => "idle_timeout",
+        quinn::ConnectionError::Reset => "reset",
+        quinn::ConnectionError::ConnectionClosed(_) => "connection_closed",
+        quinn::ConnectionError::TransportError(_) => "transport_error",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::client::quic::QUICClient;
+    use bytesize::ByteSize;
+    use dragonfly_client_config::dfdaemon::QUICVerifyMode;
+
+    #[tokio::test]
+    async fn test_dispatch_queue_admit() {
+        let dispatch_queue = Arc::new(DispatchQueue::new(
+            1,
+            1,
+            collect_quic_dispatch_queue_depth_metrics,
+        ));
+        let permit = dispatch_queue.admit().await.unwrap();
+
+        // The second stream waits in the queue until the first one is finished.
+        let waiter = tokio::spawn({
+            let dispatch_queue = dispatch_queue.clone();
+            async move { dispatch_queue.admit().await.is_some() }
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(dispatch_queue.depth.load(Ordering::Acquire), 1);
+
+        // The third stream is rejected immediately since the queue is full.
+        assert!(dispatch_queue.admit().await.is_none());
+
+        drop(permit);
+        assert!(waiter.await.unwrap());
+        assert_eq!(dispatch_queue.depth.load(Ordering::Acquire), 0);
+        assert!(dispatch_queue.admit().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_piece_flights() {
+        let piece_flights = PieceFlights::new(8);
+        let reads = Arc::new(AtomicUsize::new(0));
+        let read = || {
+            let reads = reads.clone();
+            || async move {
+                reads.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(Bytes::from_static(b"data"))
+            }
+        };
+
+        // The concurrent requests of the piece share a single read.
+        let first_flight = piece_flights.join("piece", 4).unwrap();
+        let second_flight = piece_flights.join("piece", 4).unwrap();
+        let (first_content, second_content) =
+            tokio::join!(first_flight.content(read()), second_flight.content(read()));
+        assert_eq!(first_content.unwrap(), Bytes::from_static(b"data"));
+        assert_eq!(second_content.unwrap(), Bytes::from_static(b"data"));
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+        assert_eq!(piece_flights.size.load(Ordering::SeqCst), 4);
+
+        // The piece exceeding the remaining size is not shared.
+        assert!(piece_flights.join("other_piece", 5).is_none());
+
+        // The shared buffer is released with the last request, and the next request reads
+        // the piece again.
+        drop(first_flight);
+        assert_eq!(piece_flights.size.load(Ordering::SeqCst), 4);
+        drop(second_flight);
+        assert_eq!(piece_flights.size.load(Ordering::SeqCst), 0);
+        assert!(piece_flights.flights.lock().unwrap().is_empty());
+
+        let flight = piece_flights.join("piece", 4).unwrap();
+        flight.content(read()).await.unwrap();
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+        // The error of the read is returned to all requests of the piece.
+        drop(flight);
+        let first_flight = piece_flights.join("piece", 4).unwrap();
+        let second_flight = piece_flights.join("piece", 4).unwrap();
+        let err = first_flight
+            .content(|| async { Err(Error::new(Code::NotFound, "not found".to_string())) })
+            .await
+            .unwrap_err();
+        assert!(matches!(err.code(), Code::NotFound));
+        assert!(matches!(
+            second_flight.content(read()).await.unwrap_err().code(),
+            Code::NotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_storage_workers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        let storage = Arc::new(
+            Storage::new(
+                Arc::new(config.clone()),
+                temp_dir.path(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        // The storage workers are disabled by default.
+        let server = new_quic_server(
+            Arc::new(config.clone()),
+            storage.clone(),
+            shutdown::Shutdown::new(),
+        );
+        assert!(server
+            .handler
+            .acquire_storage_worker()
+            .await
+            .unwrap()
+            .is_none());
+
+        // The piece beyond the busy workers and the full queue is rejected.
+        config.storage.server.quic.storage_workers = 1;
+        config.storage.server.quic.storage_worker_queue_depth = 0;
+        let server = new_quic_server(Arc::new(config), storage, shutdown::Shutdown::new());
+        let worker = server.handler.acquire_storage_worker().await.unwrap();
+        assert!(worker.is_some());
+        assert!(server.handler.acquire_storage_worker().await.is_err());
+
+        drop(worker);
+        assert!(server
+            .handler
+            .acquire_storage_worker()
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_storage_worker_released_before_write() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.storage_workers = 1;
+        config.storage.server.quic.storage_worker_queue_depth = 1;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Arc::new(
+            Storage::new(
+                config.clone(),
+                temp_dir.path(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        // The large piece exceeds the receive window of the stream, so its write stalls if the
+        // peer doesn't read it.
+        let large_content = vec![0u8; 8 * 1024 * 1024];
+        for (task_id, content) in [
+            ("large-task", large_content.as_slice()),
+            ("small-task", b"data".as_slice()),
+        ] {
+            let length = content.len() as u64;
+            storage
+                .download_task_started(task_id, length, length, None)
+                .await
+                .unwrap();
+            let piece_id = storage.piece_id(task_id, 0);
+            storage.download_piece_started(&piece_id, 0).await.unwrap();
+            storage
+                .download_piece_from_source_finished(
+                    &piece_id,
+                    task_id,
+                    0,
+                    length,
+                    &mut &content[..],
+                    Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+        }
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), storage, shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let stats = server.stats();
+        let server = tokio::spawn(async move { server.run().await });
+
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+
+        // The peer requests the large piece and never reads it.
+        let (mut stalled_writer, _stalled_reader) = connection.open_bi().await.unwrap();
+        let request: Bytes = Vortex::DownloadPiece(
+            Header::new_download_piece(),
+            DownloadPiece::new("large-task".to_string(), 0),
+        )
+        .into();
+        stalled_writer.write_all(&request).await.unwrap();
+        stalled_writer.finish().unwrap();
+        timeout(Duration::from_secs(5), async {
+            while stats.snapshot().active_streams == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // The stalled peer doesn't hold the storage worker, so the other piece is read.
+        assert_eq!(
+            timeout(
+                Duration::from_secs(5),
+                download_piece(&connection, "small-task", 0)
+            )
+            .await
+            .unwrap()
+            .unwrap(),
+            Bytes::from_static(b"data")
+        );
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_endpoint_config() {
+        let mut transport = QUICTransport::default();
+        assert!(endpoint_config(&transport).is_ok());
+
+        transport.mtu_discovery_upper_bound = 8952;
+        assert!(endpoint_config(&transport).is_ok());
+
+        // The upper bound beyond the maximum udp payload size is rejected.
+        transport.mtu_discovery_upper_bound = u16::MAX;
+        assert!(endpoint_config(&transport).is_err());
+
+        transport.mtu_discovery = false;
+        assert!(endpoint_config(&transport).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_server_local_addr() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+
+        // The port assigned by the OS is reported before the server runs.
+        let local_addr = server.bind().unwrap();
+        assert_ne!(local_addr.port(), 0);
+        assert_eq!(server.local_addr().unwrap(), local_addr);
+        let server = tokio::spawn(async move { server.run().await });
+
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        assert!(client.ping().await.is_ok());
+        assert_eq!(
+            client.server_build_info().unwrap().version,
+            env!("CARGO_PKG_VERSION")
+        );
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_server_workers_on_zero_port() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.workers = 4;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+
+        // All workers share the port assigned by the OS to the first worker.
+        let local_addr = server.bind().unwrap();
+        assert_ne!(local_addr.port(), 0);
+        assert_eq!(server.local_addrs.len(), 4);
+        assert!(server.local_addrs.iter().all(|addr| *addr == local_addr));
+        let server = tokio::spawn(async move { server.run().await });
+
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        assert!(client.ping().await.is_ok());
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_server_only_v6() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.quic.additional_listen_addrs =
+            vec!["[::]:4000".parse().unwrap(), "[::]:4001".parse().unwrap()];
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let server = new_quic_server_on(
+            config,
+            "0.0.0.0:4000".parse().unwrap(),
+            Arc::new(storage),
+            shutdown::Shutdown::new(),
+        );
+
+        // The IPv6 address is dual stack unless the IPv4 address on the same port is listened.
+        assert!(server.only_v6("[::]:4000".parse().unwrap()));
+        assert!(!server.only_v6("[::]:4001".parse().unwrap()));
+        assert!(!server.only_v6("0.0.0.0:4000".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_connection_byte_quota() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.connection_byte_quota = ByteSize::b(8);
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+
+        // Write a task with two pieces of 4 bytes to the storage.
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 8, None)
+            .await
+            .unwrap();
+        for number in 0..2 {
+            let piece_id = storage.piece_id(task_id, number);
+            storage
+                .download_piece_started(&piece_id, number)
+                .await
+                .unwrap();
+            storage
+                .download_piece_from_source_finished(
+                    &piece_id,
+                    task_id,
+                    number as u64 * 4,
+                    4,
+                    &mut &b"data"[..],
+                    Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+        }
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let stats = server.stats();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The quota trips after the pieces of 8 bytes are served on the connection.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        assert_eq!(
+            download_piece(&connection, task_id, 0).await.unwrap(),
+            b"data"[..]
+        );
+        assert_eq!(
+            download_piece(&connection, task_id, 1).await.unwrap(),
+            b"data"[..]
+        );
+        assert_eq!(stats.snapshot().connections[0].served_bytes, 8);
+
+        let (mut writer, mut reader) = connection.open_bi().await.unwrap();
+        let request: Bytes = Vortex::DownloadPiece(
+            Header::new_download_piece(),
+            DownloadPiece::new(task_id.to_string(), 0),
+        )
+        .into();
+        writer.write_all(&request).await.unwrap();
+        writer.finish().unwrap();
+        assert!(matches!(
+            reader.read_to_end(usize::MAX).await,
+            Err(quinn::ReadToEndError::Read(quinn::ReadError::Reset(code)))
+                if code == VarInt::from_u32(QUIC_STREAM_CODE_QUOTA_EXCEEDED)
+        ));
+
+        // The fresh connection resumes the downloads.
+        let (connection, _) = client.connect().await.unwrap();
+        assert_eq!(
+            download_piece(&connection, task_id, 0).await.unwrap(),
+            b"data"[..]
+        );
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_serve_piece_while_deleting_task() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Arc::new(
+            Storage::new(
+                config.clone(),
+                temp_dir.path(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap(),
+        );
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), storage.clone(), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let server = tokio::spawn(async move { server.run().await });
+
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        let content = vec![1u8; 4 * 1024 * 1024];
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        for _ in 0..20 {
+            storage
+                .download_task_started(task_id, content.len() as u64, content.len() as u64, None)
+                .await
+                .unwrap();
+            let piece_id = storage.piece_id(task_id, 0);
+            storage.download_piece_started(&piece_id, 0).await.unwrap();
+            storage
+                .download_piece_from_source_finished(
+                    &piece_id,
+                    task_id,
+                    0,
+                    content.len() as u64,
+                    &mut &content[..],
+                    Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+
+            // The piece is either served completely or not found, never partially.
+            let (downloaded, _) = tokio::join!(
+                download_piece(&connection, task_id, 0),
+                storage.delete_task(task_id)
+            );
+            match downloaded {
+                Ok(downloaded) => assert_eq!(downloaded, content[..]),
+                Err(err) => assert!(matches!(err.code(), Code::NotFound)),
+            }
+        }
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_peer_traffic() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 4, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                4,
+                &mut &b"data"[..],
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let stats = server.stats();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The traffic of the peer is aggregated across its connections.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        for _ in 0..2 {
+            let (connection, _) = client.connect().await.unwrap();
+            assert!(download_piece(&connection, task_id, 0).await.is_ok());
+        }
+
+        assert_eq!(
+            stats.drain_peer_traffic(),
+            vec![QUICPeerTraffic {
+                remote_ip: "127.0.0.1".parse().unwrap(),
+                task_id: task_id.to_string(),
+                traffic_type: TrafficType::RemotePeer,
+                bytes: 8,
+            }]
+        );
+        assert!(stats.drain_peer_traffic().is_empty());
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_connection_path_stats() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 4, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                4,
+                &mut &b"data"[..],
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let stats = server.stats();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The path statistics of the live connection are reported by the snapshot.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        assert_eq!(
+            download_piece(&connection, task_id, 0).await.unwrap(),
+            b"data"[..]
+        );
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.connections.len(), 1);
+        assert!(snapshot.connections[0].rtt > Duration::ZERO);
+        assert!(snapshot.connections[0].cwnd > 0);
+        assert!(snapshot.connections[0].sent_packets > 0);
+        assert!(snapshot.connections[0].lost_packets <= snapshot.connections[0].sent_packets);
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_connection_loss_rate() {
+        assert_eq!(connection_loss_rate(0, 0), 0.0);
+        assert_eq!(connection_loss_rate(0, 100), 0.0);
+        assert_eq!(connection_loss_rate(5, 100), 0.05);
+    }
+
+    #[tokio::test]
+    async fn test_idle_closed_connection() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+
+        // Neither side sends the keep-alive packets, so the silent connection is reaped by the
+        // idle timeout.
+        config.storage.server.quic.transport.max_idle_timeout = Duration::from_millis(200);
+        config.storage.server.quic.transport.keep_alive_interval = Duration::from_secs(60);
+        config.download.quic.transport.keep_alive_interval = Duration::from_secs(60);
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let stats = server.stats();
+        let server = tokio::spawn(async move { server.run().await });
+
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        assert!(matches!(
+            timeout(Duration::from_secs(5), connection.closed())
+                .await
+                .unwrap(),
+            quinn::ConnectionError::TimedOut
+        ));
+
+        timeout(Duration::from_secs(5), async {
+            while stats.snapshot().active_connections > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(stats.snapshot().idle_closed_connections, 1);
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_drain_closes_idle_connection() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.drain_timeout = Duration::from_secs(60);
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let drain = server.drain_handle();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The connection without the in-flight streams is closed with GOAWAY at the start of
+        // the drain, instead of after the drain timeout.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        drain.trigger();
+        match timeout(Duration::from_secs(5), connection.closed())
+            .await
+            .unwrap()
+        {
+            quinn::ConnectionError::ApplicationClosed(close) => {
+                assert_eq!(close.error_code, VarInt::from_u32(QUIC_CLOSE_CODE_GOAWAY));
+            }
+            err => panic!("unexpected connection error {}", err),
+        }
+
+        assert!(timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap()
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stop_stream_while_serving_piece() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.write_buffer_size = ByteSize::kib(64);
+        config.download.quic.transport.stream_receive_window = ByteSize::kib(256);
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        let content = vec![0u8; ByteSize::mib(8).as_u64() as usize];
+        storage
+            .download_task_started(task_id, content.len() as u64, content.len() as u64, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                content.len() as u64,
+                &mut content.as_slice(),
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let stats = server.stats();
+        let server = tokio::spawn(async move { server.run().await });
+
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        let (mut writer, mut reader) = connection.open_bi().await.unwrap();
+        let request: Bytes = Vortex::DownloadPiece(
+            Header::new_download_piece(),
+            DownloadPiece::new(task_id.to_string(), 0),
+        )
+        .into();
+        writer.write_all(&request).await.unwrap();
+        writer.finish().unwrap();
+
+        // Stop the stream after reading the beginning of the piece, the server aborts the
+        // read of the rest of the piece instead of sending it.
+        let mut buffer = vec![0u8; HEADER_SIZE];
+        reader.read_exact(&mut buffer).await.unwrap();
+        reader.stop(VarInt::from_u32(0)).unwrap();
+
+        timeout(Duration::from_secs(5), async {
+            while stats.snapshot().active_streams > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(stats.snapshot().sent_bytes < ByteSize::mib(1).as_u64());
+
+        // The connection keeps serving the other streams.
+        assert!(download_piece(&connection, task_id, 1).await.is_err());
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_persistent_cache_fallback() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+
+        let storage = Arc::new(
+            Storage::new(
+                Arc::new(config.clone()),
+                temp_dir.path(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap(),
+        );
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 8, None)
+            .await
+            .unwrap();
+        for (number, content) in [(0, b"data"), (1, b"miss")] {
+            let piece_id = storage.piece_id(task_id, number);
+            storage
+                .download_piece_started(&piece_id, number)
+                .await
+                .unwrap();
+            storage
+                .download_piece_from_source_finished(
+                    &piece_id,
+                    task_id,
+                    number as u64 * 4,
+                    4,
+                    &mut &content[..],
+                    Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+        }
+
+        // The persistent cache task only stores the identical content of the first piece.
+        let persistent_cache_task_id = "a3c4e940ad06c47fc36ac67801e6f8e3-persistent-cache-task";
+        storage
+            .create_persistent_cache_task_started(
+                persistent_cache_task_id,
+                Duration::from_secs(3600),
+                4,
+                4,
+            )
+            .await
+            .unwrap();
+        storage
+            .create_persistent_cache_piece(
+                &storage.persistent_cache_piece_id(persistent_cache_task_id, 0),
+                persistent_cache_task_id,
+                0,
+                0,
+                4,
+                &mut &b"data"[..],
+            )
+            .await
+            .unwrap();
+        storage
+            .create_persistent_cache_task_finished(persistent_cache_task_id)
+            .await
+            .unwrap();
+
+        // Evict the content of the task, the piece metadata is kept.
+        storage.content.delete_task(task_id).await.unwrap();
+
+        for persistent_cache_fallback in [false, true] {
+            config.storage.server.quic.persistent_cache_fallback = persistent_cache_fallback;
+            let config = Arc::new(config.clone());
+            let shutdown = shutdown::Shutdown::new();
+            let mut server = new_quic_server(config.clone(), storage.clone(), shutdown.clone());
+            let local_addr = server.bind().unwrap();
+            let stats = server.stats();
+            let server = tokio::spawn(async move { server.run().await });
+
+            let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+            let (connection, _) = client.connect().await.unwrap();
+            let result = download_piece(&connection, task_id, 0).await;
+            if persistent_cache_fallback {
+                assert_eq!(result.unwrap(), Bytes::from_static(b"data"));
+
+                // The traffic is accounted to the persistent cache task.
+                assert_eq!(
+                    stats.drain_peer_traffic()[0].task_id,
+                    persistent_cache_task_id
+                );
+            } else {
+                assert!(result.is_err());
+            }
+
+            // The piece without the identical persistent cache piece is never served.
+            assert!(download_piece(&connection, task_id, 1).await.is_err());
+
+            shutdown.trigger();
+            assert!(server.await.unwrap().is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_piece() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.verify_digest = true;
+        config.storage.server.quic.persistent_cache_fallback = true;
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Arc::new(
+            Storage::new(
+                config.clone(),
+                temp_dir.path(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap(),
+        );
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        let piece_id = storage.piece_id(task_id, 0);
+        storage
+            .download_task_started(task_id, 4, 4, None)
+            .await
+            .unwrap();
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                4,
+                &mut &b"data"[..],
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        // The persistent cache task stores the identical content of the piece.
+        let persistent_cache_task_id = "a3c4e940ad06c47fc36ac67801e6f8e3-persistent-cache-task";
+        storage
+            .create_persistent_cache_task_started(
+                persistent_cache_task_id,
+                Duration::from_secs(3600),
+                4,
+                4,
+            )
+            .await
+            .unwrap();
+        storage
+            .create_persistent_cache_piece(
+                &storage.persistent_cache_piece_id(persistent_cache_task_id, 0),
+                persistent_cache_task_id,
+                0,
+                0,
+                4,
+                &mut &b"data"[..],
+            )
+            .await
+            .unwrap();
+        storage
+            .create_persistent_cache_task_finished(persistent_cache_task_id)
+            .await
+            .unwrap();
+
+        // Corrupt the content of the piece.
+        std::fs::write(
+            temp_dir
+                .path()
+                .join(crate::content::DEFAULT_CONTENT_DIR)
+                .join(crate::content::DEFAULT_TASK_DIR)
+                .join(&task_id[..3])
+                .join(task_id),
+            b"dirt",
+        )
+        .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), storage.clone(), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The corrupted piece is responded as the internal error, instead of being served
+        // from the persistent cache as the missing piece.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        let err = download_piece(&connection, task_id, 0).await.unwrap_err();
+        assert!(matches!(err.code(), Code::Internal));
+        assert!(err.message().contains("corrupted"));
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_persistent_cache_fallback_evicted_task() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.quic.persistent_cache_fallback = true;
+        let config = Arc::new(config);
+
+        let storage = Arc::new(
+            Storage::new(
+                config.clone(),
+                temp_dir.path(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap(),
+        );
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        let piece_id = storage.piece_id(task_id, 0);
+        storage
+            .download_task_started(task_id, 4, 4, None)
+            .await
+            .unwrap();
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                4,
+                &mut &b"data"[..],
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let persistent_cache_task_id = "a3c4e940ad06c47fc36ac67801e6f8e3-persistent-cache-task";
+        storage
+            .create_persistent_cache_task_started(
+                persistent_cache_task_id,
+                Duration::from_secs(3600),
+                4,
+                4,
+            )
+            .await
+            .unwrap();
+        storage
+            .create_persistent_cache_piece(
+                &storage.persistent_cache_piece_id(persistent_cache_task_id, 0),
+                persistent_cache_task_id,
+                0,
+                0,
+                4,
+                &mut &b"data"[..],
+            )
+            .await
+            .unwrap();
+        storage
+            .create_persistent_cache_task_finished(persistent_cache_task_id)
+            .await
+            .unwrap();
+
+        // The whole task is evicted, including the piece metadata.
+        storage.delete_task(task_id).await;
+        assert!(storage.get_piece(&piece_id).unwrap().is_none());
+
+        let server = new_quic_server(config, storage.clone(), shutdown::Shutdown::new());
+        let (_, mut reader, fallback) = server
+            .handler
+            .handle_piece(&piece_id, task_id)
+            .await
+            .unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"data");
+
+        // The persistent cache piece is imported from the local file, so its traffic is not
+        // served by the remote peer.
+        let fallback = fallback.unwrap();
+        assert_eq!(fallback.task_id, persistent_cache_task_id);
+        assert_eq!(fallback.traffic_type, TrafficType::BackToSource);
+
+        // The missing piece without the evicted metadata is never served.
+        match server
+            .handler
+            .handle_piece(&storage.piece_id(task_id, 1), task_id)
+            .await
+        {
+            Err(err) => assert!(matches!(err.code(), Code::NotFound)),
+            Ok(_) => panic!("serve the missing piece"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_empty_piece() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+
+        // The task of the zero-byte file has a single empty piece.
+        let task_id = "d3c4e940ad06c47fc36ac67801e6f8e36cb400e2391708620bc7e865b102062c";
+        storage
+            .download_task_started(task_id, 4, 0, None)
+            .await
+            .unwrap();
+        let piece_id = storage.piece_id(task_id, 0);
+        storage.download_piece_started(&piece_id, 0).await.unwrap();
+        storage
+            .download_piece_from_source_finished(
+                &piece_id,
+                task_id,
+                0,
+                0,
+                &mut &b""[..],
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The empty piece is found with the empty content.
+        let client = QUICClient::new(config, local_addr.to_string()).unwrap();
+        let (connection, _) = client.connect().await.unwrap();
+        assert!(download_piece(&connection, task_id, 0)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let (mut reader, offset, _) = client.download_piece(0, task_id).await.unwrap();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await.unwrap();
+        assert_eq!(offset, 0);
+        assert!(content.is_empty());
+
+        // The missing piece is not found, instead of the empty content.
+        assert!(matches!(
+            client.download_piece(1, task_id).await,
+            Err(ClientError::PieceNotFound(_))
+        ));
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    /// download_piece downloads the piece on the connection, and returns the piece content or
+    /// the error responded by the server.
+    async fn download_piece(
+        connection: &quinn::Connection,
+        task_id: &str,
+        number: u32,
+    ) -> std::result::Result<Bytes, Error> {
+        let (mut writer, mut reader) = connection.open_bi().await.unwrap();
+        let request: Bytes = Vortex::DownloadPiece(
+            Header::new_download_piece(),
+            DownloadPiece::new(task_id.to_string(), number),
+        )
+        .into();
+        writer.write_all(&request).await.unwrap();
+        writer.finish().unwrap();
+
+        let mut response = Bytes::from(reader.read_to_end(usize::MAX).await.unwrap());
+        let header = Header::try_from(response.split_to(HEADER_SIZE)).unwrap();
+        match header.tag() {
+            Tag::PieceContent => Ok(response.split_off(header.length() as usize)),
+            Tag::Error => Err(Error::try_from(response).unwrap()),
+            tag => panic!("unexpected tag {:?}", tag),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_cipher_suites() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.cipher_suites = vec!["TLS13_AES_256_GCM_SHA384".to_string()];
+        config.download.quic.verify_mode = QUICVerifyMode::Skip;
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let shutdown = shutdown::Shutdown::new();
+        let mut server = new_quic_server(config.clone(), Arc::new(storage), shutdown.clone());
+        let local_addr = server.bind().unwrap();
+        let server = tokio::spawn(async move { server.run().await });
+
+        // The handshake fails if the client only offers the cipher suite forbidden by the server.
+        let mut client_config = (*config).clone();
+        client_config.download.quic.cipher_suites =
+            vec!["TLS13_CHACHA20_POLY1305_SHA256".to_string()];
+        let client = QUICClient::new(Arc::new(client_config), local_addr.to_string()).unwrap();
+        assert!(client.connect().await.is_err());
+
+        let mut client_config = (*config).clone();
+        client_config.download.quic.cipher_suites = vec![
+            "TLS13_CHACHA20_POLY1305_SHA256".to_string(),
+            "TLS13_AES_256_GCM_SHA384".to_string(),
+        ];
+        let client = QUICClient::new(Arc::new(client_config), local_addr.to_string()).unwrap();
+        assert!(client.connect().await.is_ok());
+
+        shutdown.trigger();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quic_server_rejects_unsupported_cipher_suites() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.dir = temp_dir.path().to_path_buf();
+        config.storage.server.ip = Some("127.0.0.1".parse().unwrap());
+        config.storage.server.quic.cipher_suites =
+            vec!["TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256".to_string()];
+        let config = Arc::new(config);
+
+        let storage = Storage::new(
+            config.clone(),
+            temp_dir.path(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let mut server = new_quic_server(config, Arc::new(storage), shutdown::Shutdown::new());
+        assert!(server.bind().is_err());
+    }
+
+    /// QUICServerFixture is the quic server running on an ephemeral port of the loopback with
+    /// the storage in the storage directory of the config.
+    pub(crate) struct QUICServerFixture {
+        /// storage is the storage served by the quic server.
+        pub(crate) storage: Arc<Storage>,
+
+        /// local_addr is the address the quic server is listening on.
+        pub(crate) local_addr: SocketAddr,
+
+        /// stats is the connection stats of the quic server.
+        pub(crate) stats: Arc<QUICServerStats>,
+
+        /// shutdown is used to shutdown the quic server.
+        shutdown: shutdown::Shutdown,
+
+        /// server is the task running the quic server.
+        server: tokio::task::JoinHandle<ClientResult<()>>,
+    }
+
+    /// QUICServerFixture implements the quic server fixture.
+    impl QUICServerFixture {
+        /// start creates the storage and starts the quic server.
+        pub(crate) async fn start(config: Arc<Config>) -> Self {
+            let storage = Storage::new(
+                config.clone(),
+                &config.storage.dir,
+                config.storage.dir.clone(),
+            )
+            .await
+            .unwrap();
+            let storage = Arc::new(storage);
+
+            let shutdown = shutdown::Shutdown::new();
+            let mut server = new_quic_server(config, storage.clone(), shutdown.clone());
+            let local_addr = server.bind().unwrap();
+            let stats = server.stats();
+            let server = tokio::spawn(async move { server.run().await });
+            Self {
+                storage,
+                local_addr,
+                stats,
+                shutdown,
+                server,
+            }
+        }
+
+        /// stop shuts down the quic server and waits for it to exit.
+        pub(crate) async fn stop(self) {
+            self.shutdown.trigger();
+            assert!(self.server.await.unwrap().is_ok());
+        }
+    }
+
+    /// quic_server_config creates the server config of the quic server, it is used by the mock
+    /// quic servers.
+    pub(crate) async fn quic_server_config(config: Arc<Config>) -> ServerConfig {
+        let storage = Storage::new(
+            config.clone(),
+            &config.storage.dir,
+            config.storage.dir.clone(),
+        )
+        .await
+        .unwrap();
+        new_quic_server(config, Arc::new(storage), shutdown::Shutdown::new())
+            .server_config()
+            .unwrap()
+    }
+
+    /// new_quic_server creates the quic server listening on an ephemeral port of the loopback.
+    pub(crate) fn new_quic_server(
+        config: Arc<Config>,
+        storage: Arc<Storage>,
+        shutdown: shutdown::Shutdown,
+    ) -> QUICServer {
+        new_quic_server_on(config, "127.0.0.1:0".parse().unwrap(), storage, shutdown)
+    }
+
+    /// new_quic_server_on creates the quic server listening on the address.
+    pub(crate) fn new_quic_server_on(
+        config: Arc<Config>,
+        addr: SocketAddr,
+        storage: Arc<Storage>,
+        shutdown: shutdown::Shutdown,
+    ) -> QUICServer {
+        let (shutdown_complete_tx, _shutdown_complete_rx) = mpsc::unbounded_channel();
+        QUICServer::new(
+            config,
+            addr,
+            Arc::new(IDGenerator::new(
+                "127.0.0.1".to_string(),
+                "localhost".to_string(),
+                false,
+            )),
+            storage,
+            Arc::new(RateLimiter::builder().build()),
+            shutdown,
+            shutdown_complete_tx,
+        )
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer({
+                let output = output.clone();
+                move || LogWriter(output.clone())
+            })
+            .finish();
+
+        let record = StreamRecord {
+            typ: "download_persistent_cache_piece",
+            task_id: "task".to_string(),
+            piece_number: Some(1),
+            sent_bytes: 1024,
+            ..Default::default()
+        };
+        tracing::subscriber::with_default(subscriber, || {
+            audit_log("10.0.0.1:4006".parse().unwrap(), Some("CN=peer"), &record);
+        });
+
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.contains(AUDIT_LOG_TARGET));
+        assert!(output.contains("remote_address=10.0.0.1:4006"));
+        assert!(output.contains("peer_identity=\"CN=peer\""));
+        assert!(output.contains("task_id=\"task\""));
+        assert!(output.contains("piece_number=1"));
+        assert!(output.contains("sent_bytes=1024"));
+        assert!(output.contains("result=\"ok\""));
+    }
+
+    #[test]
+    fn test_slow_request_log() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer({
+                let output = output.clone();
+                move || LogWriter(output.clone())
+            })
+            .finish();
+
+        // The storage is the slowest phase of the stream.
+        let record = StreamRecord {
+            typ: "download_piece",
+            task_id: "task".to_string(),
+            piece_number: Some(1),
+            sent_bytes: 1024,
+            request_cost: Duration::from_millis(5),
+            storage_cost: Duration::from_secs(8),
+            network_cost: Duration::from_millis(50),
+            ..Default::default()
+        };
+        assert_eq!(record.slowest_phase(), STREAM_PHASE_STORAGE);
+        tracing::subscriber::with_default(subscriber, || {
+            slow_request_log(
+                "10.0.0.1:4006".parse().unwrap(),
+                &record,
+                Duration::from_millis(8055),
+            );
+        });
+
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("WARN"));
+        assert!(output.contains("remote_address=10.0.0.1:4006"));
+        assert!(output.contains("task_id=\"task\""));
+        assert!(output.contains("cost_ms=8055"));
+        assert!(output.contains("storage_ms=8000"));
+        assert!(output.contains("slowest_phase=\"storage\""));
+    }
+
+    /// LogWriter writes the tracing output to the shared buffer.
+    struct LogWriter(Arc<Mutex<Vec<u8>>>);
+
+    /// LogWriter implements std::io::Write.
+    impl std::io::Write for LogWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peer_bans() {
+        let peer_bans = PeerBans::new(3, Duration::from_secs(60), Duration::from_millis(50));
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let other_peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        // The peer is banned after the violations reach the threshold.
+        assert!(!peer_bans.record_violation(peer));
+        assert!(!peer_bans.record_violation(peer));
+        assert!(!peer_bans.is_banned(peer));
+        assert!(peer_bans.record_violation(peer));
+        assert!(peer_bans.is_banned(peer));
+        assert!(!peer_bans.is_banned(other_peer));
+
+        // The peer is allowed again after the ban expires.
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!peer_bans.is_banned(peer));
+        assert!(!peer_bans.record_violation(peer));
+    }
 }
@@ -0,0 +1,97 @@
+/*
+ *     Copyright 2025 The Dragonfly Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dragonfly_client_core::{Error, Result};
+use quinn::rustls::crypto::{ring, CryptoProvider};
+use quinn::rustls::quic::Suite;
+use std::sync::Arc;
+
+/// QUIC_ALPN is the ALPN protocol identifier of the vortex protocol over QUIC, the version
+/// in the identifier is bumped when the wire format changes.
+pub const QUIC_ALPN: &[u8] = b"dragonfly-vortex/1";
+
+/// crypto_provider returns the crypto provider of the quic server and client, which only
+/// negotiates the configured cipher suites. QUIC requires TLS 1.3, so only the TLS 1.3 cipher
+/// suites are usable, and all of them are enabled if no cipher suite is configured.
+pub fn crypto_provider(cipher_suites: &[String]) -> Result<Arc<CryptoProvider>> {
+    let mut provider = ring::default_provider();
+    provider
+        .cipher_suites
+        .retain(|cipher_suite| cipher_suite.tls13().is_some());
+
+    if cipher_suites.is_empty() {
+        return Ok(Arc::new(provider));
+    }
+
+    for name in cipher_suites {
+        if !provider
+            .cipher_suites
+            .iter()
+            .any(|cipher_suite| cipher_suite.suite().as_str() == Some(name.as_str()))
+        {
+            return Err(Error::ValidationError(format!(
+                "unsupported quic cipher suite {}, only the TLS 1.3 cipher suites are usable",
+                name
+            )));
+        }
+    }
+
+    provider.cipher_suites.retain(|cipher_suite| {
+        cipher_suite
+            .suite()
+            .as_str()
+            .is_some_and(|suite| cipher_suites.iter().any(|name| name == suite))
+    });
+
+    Ok(Arc::new(provider))
+}
+
+/// initial_suite returns the cipher suite to protect the initial packets. QUIC always protects
+/// the initial packets with TLS13_AES_128_GCM_SHA256, so it is used even if the suite is not
+/// negotiable by the configured cipher suites.
+pub fn initial_suite() -> Suite {
+    ring::cipher_suite::TLS13_AES_128_GCM_SHA256
+        .tls13()
+        .and_then(|cipher_suite| cipher_suite.quic_suite())
+        .expect("TLS13_AES_128_GCM_SHA256 supports quic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crypto_provider() {
+        // All TLS 1.3 cipher suites are enabled by default.
+        let provider = crypto_provider(&[]).unwrap();
+        assert_eq!(provider.cipher_suites.len(), 3);
+        assert!(provider
+            .cipher_suites
+            .iter()
+            .all(|cipher_suite| cipher_suite.tls13().is_some()));
+
+        let provider = crypto_provider(&["TLS13_AES_256_GCM_SHA384".to_string()]).unwrap();
+        assert_eq!(provider.cipher_suites.len(), 1);
+        assert_eq!(
+            provider.cipher_suites[0].suite().as_str(),
+            Some("TLS13_AES_256_GCM_SHA384")
+        );
+
+        // The unknown and the TLS 1.2 cipher suites are rejected.
+        assert!(crypto_provider(&["TLS13_UNKNOWN".to_string()]).is_err());
+        assert!(crypto_provider(&["TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256".to_string()]).is_err());
+    }
+}
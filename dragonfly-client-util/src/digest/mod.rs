@@ -20,6 +20,7 @@ use std::fmt;
 use std::io::{self, Read};
 use std::path::Path;
 use std::str::FromStr;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tracing::instrument;
 
 /// SEPARATOR is the separator of digest.
@@ -149,6 +150,53 @@ impl FromStr for Digest {
     }
 }
 
+/// Hasher calculates the digest incrementally, such as the digest of the content received in
+/// chunks.
+pub enum Hasher {
+    /// Crc32 is the hasher of the crc32 algorithm.
+    Crc32(crc32fast::Hasher),
+
+    /// Sha256 is the hasher of the sha256 algorithm.
+    Sha256(sha2::Sha256),
+
+    /// Sha512 is the hasher of the sha512 algorithm.
+    Sha512(sha2::Sha512),
+}
+
+/// Hasher implements the Hasher.
+impl Hasher {
+    /// new returns a new Hasher of the algorithm.
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+            Algorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+        }
+    }
+
+    /// update feeds the data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Crc32(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    /// finalize returns the digest of the fed data.
+    pub fn finalize(self) -> Digest {
+        match self {
+            Hasher::Crc32(hasher) => Digest::new(Algorithm::Crc32, hasher.finalize().to_string()),
+            Hasher::Sha256(hasher) => {
+                Digest::new(Algorithm::Sha256, hex::encode(hasher.finalize()))
+            }
+            Hasher::Sha512(hasher) => {
+                Digest::new(Algorithm::Sha512, hex::encode(hasher.finalize()))
+            }
+        }
+    }
+}
+
 /// calculate_file_digest calculates the digest of a file.
 #[instrument(skip_all)]
 pub fn calculate_file_digest(algorithm: Algorithm, path: &Path) -> ClientResult<Digest> {
@@ -201,6 +249,85 @@ pub fn verify_file_digest(expected_digest: Digest, file_path: &Path) -> ClientRe
     Ok(())
 }
 
+/// calculate_reader_digest calculates the digest of the content read from the reader.
+#[instrument(skip_all)]
+pub async fn calculate_reader_digest<R: AsyncRead + Unpin + ?Sized>(
+    algorithm: Algorithm,
+    reader: &mut R,
+) -> ClientResult<Digest> {
+    let mut buffer = vec![0; 64 * 1024];
+    match algorithm {
+        Algorithm::Crc32 => {
+            let mut hasher = crc32fast::Hasher::new();
+            loop {
+                let n = reader.read(&mut buffer).await?;
+                if n == 0 {
+                    break;
+                }
+
+                hasher.update(&buffer[..n]);
+            }
+
+            Ok(Digest::new(algorithm, hasher.finalize().to_string()))
+        }
+        Algorithm::Sha256 => {
+            let mut hasher = sha2::Sha256::new();
+            loop {
+                let n = reader.read(&mut buffer).await?;
+                if n == 0 {
+                    break;
+                }
+
+                hasher.update(&buffer[..n]);
+            }
+
+            Ok(Digest::new(algorithm, hex::encode(hasher.finalize())))
+        }
+        Algorithm::Sha512 => {
+            let mut hasher = sha2::Sha512::new();
+            loop {
+                let n = reader.read(&mut buffer).await?;
+                if n == 0 {
+                    break;
+                }
+
+                hasher.update(&buffer[..n]);
+            }
+
+            Ok(Digest::new(algorithm, hex::encode(hasher.finalize())))
+        }
+    }
+}
+
+/// verify_reader_digest verifies the digest of the content read from the reader against an
+/// expected digest, the expected digest is in the format of `algorithm:encoded`.
+pub async fn verify_reader_digest<R: AsyncRead + Unpin + ?Sized>(
+    expected_digest: &str,
+    reader: &mut R,
+) -> ClientResult<()> {
+    let algorithm = match expected_digest.split_once(SEPARATOR) {
+        Some((algorithm, _)) => algorithm
+            .parse::<Algorithm>()
+            .map_err(ClientError::ValidationError)?,
+        None => {
+            return Err(ClientError::ValidationError(format!(
+                "invalid digest: {}",
+                expected_digest
+            )));
+        }
+    };
+
+    let digest = calculate_reader_digest(algorithm, reader).await?;
+    if digest.to_string() != expected_digest {
+        return Err(ClientError::DigestMismatch(
+            expected_digest.to_string(),
+            digest.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -228,6 +355,30 @@ mod tests {
         assert_eq!(digest.to_string(), "sha256:encoded_hash");
     }
 
+    #[test]
+    fn test_hasher() {
+        let content = b"test content";
+        for algorithm in [Algorithm::Crc32, Algorithm::Sha256, Algorithm::Sha512] {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(&content[..4]);
+            hasher.update(&content[4..]);
+
+            let mut expected = Hasher::new(algorithm);
+            expected.update(content);
+            assert_eq!(
+                hasher.finalize().to_string(),
+                expected.finalize().to_string()
+            );
+        }
+
+        let mut hasher = Hasher::new(Algorithm::Crc32);
+        hasher.update(content);
+        assert_eq!(
+            hasher.finalize().to_string(),
+            format!("crc32:{}", crc32fast::hash(content))
+        );
+    }
+
     #[test]
     fn test_calculate_file_digest() {
         let content = b"test content";
@@ -275,4 +426,25 @@ mod tests {
         let expected_crc32_digest = Digest::new(Algorithm::Crc32, "1475635037".to_string());
         assert!(verify_file_digest(expected_crc32_digest, path).is_ok());
     }
+
+    #[tokio::test]
+    async fn test_verify_reader_digest() {
+        let content = b"test content";
+        assert!(verify_reader_digest("crc32:1475635037", &mut &content[..])
+            .await
+            .is_ok());
+        assert!(verify_reader_digest(
+            "sha256:6ae8a75555209fd6c44157c0aed8016e763ff435a19cf186f76863140143ff72",
+            &mut &content[..]
+        )
+        .await
+        .is_ok());
+        assert!(matches!(
+            verify_reader_digest("crc32:1475635037", &mut &b"test contenT"[..]).await,
+            Err(ClientError::DigestMismatch(_, _))
+        ));
+        assert!(verify_reader_digest("invalid", &mut &content[..])
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,309 @@
+/*
+ *     Copyright 2025 The Dragonfly Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bucket is the token bucket of a key.
+#[derive(Debug)]
+struct Bucket {
+    /// tokens is the number of the available tokens.
+    tokens: f64,
+
+    /// updated_at is the time when the tokens are refilled.
+    updated_at: Instant,
+}
+
+/// KeyedRateLimiter is a token bucket rate limiter keyed by the caller, such as the
+/// remote ip of the peer. The buckets of the idle keys are evicted to avoid unbounded
+/// growth.
+#[derive(Debug)]
+pub struct KeyedRateLimiter<K> {
+    /// rate is the number of the tokens refilled per second.
+    rate: f64,
+
+    /// burst is the maximum number of the tokens in the bucket.
+    burst: f64,
+
+    /// idle_timeout is the timeout to evict the bucket of the idle key.
+    idle_timeout: Duration,
+
+    /// buckets is the token buckets of the keys.
+    buckets: Mutex<HashMap<K, Bucket>>,
+
+    /// evicted_at is the time when the idle buckets are evicted.
+    evicted_at: Mutex<Instant>,
+}
+
+/// KeyedRateLimiter implements the keyed token bucket rate limiter.
+impl<K: Eq + Hash> KeyedRateLimiter<K> {
+    /// new creates a new KeyedRateLimiter, the rate is the number of the tokens refilled
+    /// per second and the burst is the maximum number of the tokens in the bucket.
+    pub fn new(rate: u32, burst: u32, idle_timeout: Duration) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst.max(1) as f64,
+            idle_timeout,
+            buckets: Mutex::new(HashMap::new()),
+            evicted_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// try_acquire acquires a token of the key. If the bucket is empty, it returns the
+    /// duration to wait for the next token.
+    pub fn try_acquire(&self, key: K) -> Result<(), Duration> {
+        let now = Instant::now();
+        self.evict(now);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+
+        // Refill the tokens by the elapsed time since the last refill.
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if self.rate <= 0.0 {
+            return Err(self.idle_timeout);
+        }
+
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+    }
+
+    /// len returns the number of the buckets.
+    pub fn len(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+
+    /// is_empty returns whether there is no bucket.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// evict removes the buckets of the keys which are idle longer than the idle timeout,
+    /// it runs at most once per idle timeout.
+    fn evict(&self, now: Instant) {
+        let mut evicted_at = self.evicted_at.lock().unwrap();
+        if now.duration_since(*evicted_at) < self.idle_timeout {
+            return;
+        }
+
+        *evicted_at = now;
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| now.duration_since(bucket.updated_at) < self.idle_timeout);
+    }
+}
+
+/// KeyedConcurrencyLimiter limits the number of the in-flight operations per key, such as
+/// the streams of a task. The semaphore of a key is removed once it has no in-flight
+/// operation and no waiter, so the limiter doesn't grow with the keys.
+#[derive(Debug)]
+pub struct KeyedConcurrencyLimiter<K> {
+    /// limit is the maximum number of the in-flight operations per key.
+    limit: usize,
+
+    /// semaphores is the semaphores of the keys.
+    semaphores: Mutex<HashMap<K, Arc<Semaphore>>>,
+}
+
+/// KeyedConcurrencyLimiter implements the keyed concurrency limiter.
+impl<K: Eq + Hash + Clone> KeyedConcurrencyLimiter<K> {
+    /// new creates a new KeyedConcurrencyLimiter with the limit per key.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            semaphores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// try_acquire acquires a permit of the key, it returns None if the key reaches the limit.
+    pub fn try_acquire(self: &Arc<Self>, key: K) -> Option<KeyedPermit<K>> {
+        let semaphore = self.semaphore(&key);
+        match semaphore.try_acquire_owned() {
+            Ok(permit) => Some(KeyedPermit::new(self.clone(), key, permit)),
+            Err(_) => {
+                drop(semaphore);
+                self.release(&key);
+                None
+            }
+        }
+    }
+
+    /// acquire acquires a permit of the key, it waits until an in-flight operation of the key
+    /// finishes if the key reaches the limit.
+    pub async fn acquire(self: &Arc<Self>, key: K) -> KeyedPermit<K> {
+        let semaphore = self.semaphore(&key);
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .expect("semaphore of the key is never closed");
+        KeyedPermit::new(self.clone(), key, permit)
+    }
+
+    /// len returns the number of the keys which have in-flight operations or waiters.
+    pub fn len(&self) -> usize {
+        self.semaphores.lock().unwrap().len()
+    }
+
+    /// is_empty returns whether there is no key.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// semaphore returns the semaphore of the key, it is created if the key doesn't exist.
+    fn semaphore(&self, key: &K) -> Arc<Semaphore> {
+        self.semaphores
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit)))
+            .clone()
+    }
+
+    /// release removes the semaphore of the key if it is only referenced by the limiter and
+    /// has no in-flight operation.
+    fn release(&self, key: &K) {
+        let mut semaphores = self.semaphores.lock().unwrap();
+        if let Some(semaphore) = semaphores.get(key) {
+            if Arc::strong_count(semaphore) == 1 && semaphore.available_permits() == self.limit {
+                semaphores.remove(key);
+            }
+        }
+    }
+}
+
+/// KeyedPermit is the permit of a key, it is released when dropped.
+#[derive(Debug)]
+pub struct KeyedPermit<K: Eq + Hash + Clone> {
+    /// limiter is the limiter which issues the permit.
+    limiter: Arc<KeyedConcurrencyLimiter<K>>,
+
+    /// key is the key of the permit.
+    key: K,
+
+    /// permit is the permit of the semaphore of the key.
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+/// KeyedPermit implements the permit of a key.
+impl<K: Eq + Hash + Clone> KeyedPermit<K> {
+    /// new creates a new KeyedPermit.
+    fn new(limiter: Arc<KeyedConcurrencyLimiter<K>>, key: K, permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            limiter,
+            key,
+            permit: Some(permit),
+        }
+    }
+}
+
+/// KeyedPermit implements Drop.
+impl<K: Eq + Hash + Clone> Drop for KeyedPermit<K> {
+    fn drop(&mut self) {
+        // Release the permit before the semaphore is checked for the removal.
+        drop(self.permit.take());
+        self.limiter.release(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+
+    #[test]
+    fn test_keyed_rate_limiter_try_acquire() {
+        let limiter = KeyedRateLimiter::new(1, 2, Duration::from_secs(60));
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let other_peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        assert!(limiter.try_acquire(peer).is_ok());
+        assert!(limiter.try_acquire(peer).is_ok());
+
+        let retry_after = limiter.try_acquire(peer).unwrap_err();
+        assert!(retry_after > Duration::ZERO);
+        assert!(retry_after <= Duration::from_secs(1));
+
+        // The other peer is not affected by the exhausted bucket.
+        assert!(limiter.try_acquire(other_peer).is_ok());
+        assert_eq!(limiter.len(), 2);
+    }
+
+    #[test]
+    fn test_keyed_rate_limiter_evict() {
+        let limiter = KeyedRateLimiter::new(1, 1, Duration::from_millis(10));
+        assert!(limiter.try_acquire("peer-1").is_ok());
+        assert_eq!(limiter.len(), 1);
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(limiter.try_acquire("peer-2").is_ok());
+        assert_eq!(limiter.len(), 1);
+    }
+
+    #[test]
+    fn test_keyed_concurrency_limiter_try_acquire() {
+        let limiter = Arc::new(KeyedConcurrencyLimiter::new(2));
+
+        let hot_permit_1 = limiter.try_acquire("hot-task").unwrap();
+        let hot_permit_2 = limiter.try_acquire("hot-task").unwrap();
+        assert!(limiter.try_acquire("hot-task").is_none());
+
+        // The other task is not affected by the exhausted task.
+        let cold_permit = limiter.try_acquire("cold-task").unwrap();
+        assert_eq!(limiter.len(), 2);
+
+        drop(hot_permit_1);
+        assert!(limiter.try_acquire("hot-task").is_some());
+
+        // The key is removed once it has no in-flight operation.
+        drop(hot_permit_2);
+        drop(cold_permit);
+        assert!(limiter.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_keyed_concurrency_limiter_acquire() {
+        let limiter = Arc::new(KeyedConcurrencyLimiter::new(1));
+        let permit = limiter.acquire("task").await;
+
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                let _permit = limiter.acquire("task").await;
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+
+        drop(permit);
+        waiter.await.unwrap();
+        assert!(limiter.is_empty());
+    }
+}
@@ -20,16 +20,28 @@ use lazy_static::lazy_static;
 use lru::LruCache;
 use rcgen::{Certificate, CertificateParams, KeyPair};
 use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::vec::Vec;
 use std::{fs, io};
-use tracing::instrument;
+use tracing::{instrument, warn};
+use x509_parser::extensions::GeneralName;
 
 /// DEFAULT_CERTS_CACHE_CAPACITY is the default capacity of the certificates cache.
 const DEFAULT_CERTS_CACHE_CAPACITY: usize = 1000;
 
+/// SELF_SIGNED_CERT_FILE_NAME is the file name of the persisted self-signed certificate.
+const SELF_SIGNED_CERT_FILE_NAME: &str = "cert.pem";
+
+/// SELF_SIGNED_KEY_FILE_NAME is the file name of the private key of the persisted self-signed
+/// certificate.
+const SELF_SIGNED_KEY_FILE_NAME: &str = "key.pem";
+
 /// CertKeyPair is the type of the certificate and private key pair.
 type CertKeyPair = (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>);
 
@@ -140,6 +152,16 @@ pub fn generate_cert_from_pem(cert_path: &PathBuf) -> ClientResult<Vec<Certifica
     Ok(certs)
 }
 
+/// Generate private key from PEM format file.
+#[instrument(skip_all)]
+pub fn generate_key_from_pem(key_path: &PathBuf) -> ClientResult<PrivateKeyDer<'static>> {
+    let f = fs::File::open(key_path)?;
+    let mut key_pem_reader = io::BufReader::new(f);
+    let key = rustls_pemfile::private_key(&mut key_pem_reader)?
+        .ok_or_else(|| ClientError::Unknown("failed to load private key".to_string()))?;
+    Ok(key)
+}
+
 /// generate_self_signed_certs_by_ca_cert generates a self-signed certificates
 /// by given subject alternative names with CA certificate.
 #[instrument(skip_all)]
@@ -201,6 +223,153 @@ pub fn generate_simple_self_signed_certs(
     Ok((certs, key))
 }
 
+/// load_or_generate_self_signed_certs loads the self-signed certificates persisted in the
+/// directory, so the identity is stable across restarts. The certificates are generated and
+/// persisted if the files are missing, unparseable, expired or issued for the other subject
+/// alternative names. The names which parse as the IP addresses are issued as the IP address
+/// entries, and the others as the DNS name entries.
+#[instrument(skip_all)]
+pub fn load_or_generate_self_signed_certs(
+    dir: &Path,
+    subject_alt_names: impl Into<Vec<String>>,
+) -> ClientResult<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    // Normalize the IP addresses, so they are compared with the names parsed from the
+    // certificate in the same format.
+    let subject_alt_names: Vec<String> = subject_alt_names
+        .into()
+        .into_iter()
+        .map(|name| match name.parse::<IpAddr>() {
+            Ok(ip) => ip.to_string(),
+            Err(_) => name,
+        })
+        .collect();
+
+    let cert_path = dir.join(SELF_SIGNED_CERT_FILE_NAME);
+    let key_path = dir.join(SELF_SIGNED_KEY_FILE_NAME);
+    if cert_path.exists() && key_path.exists() {
+        match load_self_signed_certs(&cert_path, &key_path, &subject_alt_names) {
+            Ok(Some(certs_and_key)) => return Ok(certs_and_key),
+            Ok(None) => warn!(
+                "self-signed certificate {:?} expired or its names changed, regenerate it",
+                cert_path
+            ),
+            Err(err) => warn!(
+                "failed to load self-signed certificate {:?}, regenerate it: {}",
+                cert_path, err
+            ),
+        }
+    }
+
+    let cert = rcgen::generate_simple_self_signed(subject_alt_names)
+        .or_err(ErrorType::CertificateError)?;
+    let cert_pem = cert.serialize_pem().or_err(ErrorType::CertificateError)?;
+    let key_pem = cert.serialize_private_key_pem();
+
+    // Only the owner can read the private key.
+    fs::create_dir_all(dir)?;
+    fs::write(&cert_path, cert_pem.as_bytes())?;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&key_path)?
+        .write_all(key_pem.as_bytes())?;
+
+    Ok((
+        load_certs_from_pem(&cert_pem)?,
+        load_key_from_pem(&key_pem)?,
+    ))
+}
+
+/// load_self_signed_certs loads the persisted self-signed certificates, it returns None if the
+/// certificate is expired or not issued for the subject alternative names.
+fn load_self_signed_certs(
+    cert_path: &PathBuf,
+    key_path: &PathBuf,
+    subject_alt_names: &[String],
+) -> ClientResult<Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>> {
+    let certs = generate_cert_from_pem(cert_path)?;
+    let key = generate_key_from_pem(key_path)?;
+    let cert = certs
+        .first()
+        .ok_or_else(|| ClientError::Unknown("failed to load certificate".to_string()))?;
+
+    let (_, parsed_cert) =
+        x509_parser::parse_x509_certificate(cert.as_ref()).or_err(ErrorType::CertificateError)?;
+    if !parsed_cert.validity().is_valid() {
+        return Ok(None);
+    }
+
+    let (_, cert_subject_alt_names) = parse_cert_subject(cert)?;
+    if cert_subject_alt_names != subject_alt_names {
+        return Ok(None);
+    }
+
+    Ok(Some((certs, key)))
+}
+
+/// order_cert_chain validates that the certificate chain loaded from the PEM file is ordered
+/// from the leaf to the intermediates, each certificate is issued by the next one. The strict
+/// peers fail to build the path with the misordered chain, so it is reordered with a warning if
+/// all certificates can be linked into a single chain, otherwise it is returned as is.
+#[instrument(skip_all)]
+pub fn order_cert_chain(
+    certs: Vec<CertificateDer<'static>>,
+) -> ClientResult<Vec<CertificateDer<'static>>> {
+    // Parse the raw subject and issuer of each certificate.
+    let mut names = Vec::with_capacity(certs.len());
+    for cert in &certs {
+        let (_, parsed_cert) = x509_parser::parse_x509_certificate(cert.as_ref())
+            .or_err(ErrorType::CertificateError)?;
+        names.push((
+            parsed_cert.subject().as_raw().to_vec(),
+            parsed_cert.issuer().as_raw().to_vec(),
+        ));
+    }
+
+    let is_ordered = names
+        .windows(2)
+        .all(|pair| pair[0].1 == pair[1].0 && pair[0].0 != pair[0].1);
+    if is_ordered {
+        return Ok(certs);
+    }
+
+    // The leaf is the only certificate which issues none of the others.
+    let leaves: Vec<usize> = (0..names.len())
+        .filter(|&i| {
+            !names
+                .iter()
+                .enumerate()
+                .any(|(j, (_, issuer))| i != j && *issuer == names[i].0)
+        })
+        .collect();
+
+    let mut order = Vec::with_capacity(names.len());
+    if let [leaf] = leaves[..] {
+        order.push(leaf);
+        while let Some(next) = (0..names.len())
+            .find(|&j| !order.contains(&j) && names[j].0 == names[*order.last().unwrap()].1)
+        {
+            order.push(next);
+        }
+    }
+
+    if order.len() != certs.len() {
+        warn!("certificate chain is not ordered from the leaf to the intermediates");
+        return Ok(certs);
+    }
+
+    warn!("certificate chain is not ordered from the leaf to the intermediates, reorder it");
+    let mut certs: Vec<Option<CertificateDer<'static>>> = certs.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|i| certs[i].take()).collect())
+}
+
+/// cert_fingerprint returns the hex encoded SHA-256 fingerprint of the DER format certificate.
+pub fn cert_fingerprint(cert: &CertificateDer<'_>) -> String {
+    hex::encode(Sha256::digest(cert.as_ref()))
+}
+
 /// certs_to_raw_certs converts DER format of the certificates to raw certificates.
 #[instrument(skip_all)]
 pub fn certs_to_raw_certs(certs: Vec<CertificateDer<'static>>) -> Vec<Vec<u8>> {
@@ -233,6 +402,42 @@ pub fn load_key_from_pem(key_pem: &str) -> ClientResult<PrivateKeyDer<'static>>
     Ok(key)
 }
 
+/// parse_cert_subject parses the subject distinguished name and the subject alternative names
+/// of the DER format certificate.
+pub fn parse_cert_subject(cert: &CertificateDer<'_>) -> ClientResult<(String, Vec<String>)> {
+    let (_, cert) =
+        x509_parser::parse_x509_certificate(cert.as_ref()).or_err(ErrorType::CertificateError)?;
+
+    let subject_alt_names = match cert
+        .subject_alternative_name()
+        .or_err(ErrorType::CertificateError)?
+    {
+        Some(extension) => extension
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name) => Some(name.to_string()),
+                GeneralName::RFC822Name(name) => Some(name.to_string()),
+                GeneralName::URI(uri) => Some(uri.to_string()),
+                GeneralName::IPAddress(ip) => match ip.len() {
+                    4 => <[u8; 4]>::try_from(*ip)
+                        .ok()
+                        .map(|ip| Ipv4Addr::from(ip).to_string()),
+                    16 => <[u8; 16]>::try_from(*ip)
+                        .ok()
+                        .map(|ip| Ipv6Addr::from(ip).to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok((cert.subject().to_string(), subject_alt_names))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +627,136 @@ Z+yQ5jhu/fmSBNhqO/8Lp+Y=
         assert!(result.is_ok());
         assert!(matches!(result.unwrap(), PrivateKeyDer::Pkcs8(_)));
     }
+
+    #[test]
+    fn test_generate_key_from_pem() {
+        let key_file = NamedTempFile::new().unwrap();
+        key_file.as_file().write_all(SERVER_KEY.as_bytes()).unwrap();
+
+        let result = generate_key_from_pem(&key_file.path().to_path_buf());
+        assert!(result.is_ok());
+        assert!(matches!(result.unwrap(), PrivateKeyDer::Pkcs8(_)));
+    }
+
+    #[test]
+    fn test_parse_cert_subject() {
+        let certs = load_certs_from_pem(SERVER_CERT).unwrap();
+
+        let (subject, subject_alt_names) = parse_cert_subject(&certs[0]).unwrap();
+        assert!(subject.contains("CN=localhost"));
+        assert_eq!(subject_alt_names, vec!["localhost".to_string()]);
+    }
+
+    #[test]
+    fn test_load_or_generate_self_signed_certs() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // The certificate persisted by the first start is loaded by the second start.
+        let (certs, _) =
+            load_or_generate_self_signed_certs(dir.path(), vec!["d7y".into()]).unwrap();
+        let (reloaded_certs, _) =
+            load_or_generate_self_signed_certs(dir.path(), vec!["d7y".into()]).unwrap();
+        assert_eq!(certs, reloaded_certs);
+        assert_eq!(
+            cert_fingerprint(&certs[0]),
+            cert_fingerprint(&reloaded_certs[0])
+        );
+
+        // The unparseable certificate is regenerated.
+        fs::write(dir.path().join(SELF_SIGNED_CERT_FILE_NAME), "invalid").unwrap();
+        let (regenerated_certs, _) =
+            load_or_generate_self_signed_certs(dir.path(), vec!["d7y".into()]).unwrap();
+        assert_ne!(certs, regenerated_certs);
+    }
+
+    #[test]
+    fn test_load_or_generate_self_signed_certs_subject_alt_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let subject_alt_names = vec![
+            "d7y".to_string(),
+            "storage.example.com".to_string(),
+            "127.0.0.1".to_string(),
+            "0:0:0:0:0:0:0:1".to_string(),
+        ];
+
+        // The IP addresses are issued as the IP address entries.
+        let (certs, _) =
+            load_or_generate_self_signed_certs(dir.path(), subject_alt_names.clone()).unwrap();
+        let (_, cert_subject_alt_names) = parse_cert_subject(&certs[0]).unwrap();
+        assert_eq!(
+            cert_subject_alt_names,
+            vec!["d7y", "storage.example.com", "127.0.0.1", "::1"]
+        );
+
+        let (_, parsed_cert) = x509_parser::parse_x509_certificate(certs[0].as_ref()).unwrap();
+        let ip_names = parsed_cert
+            .subject_alternative_name()
+            .unwrap()
+            .unwrap()
+            .value
+            .general_names
+            .iter()
+            .filter(|name| matches!(name, GeneralName::IPAddress(_)))
+            .count();
+        assert_eq!(ip_names, 2);
+
+        // The certificate is regenerated when the names are changed.
+        let (regenerated_certs, _) =
+            load_or_generate_self_signed_certs(dir.path(), vec!["d7y".to_string()]).unwrap();
+        assert_ne!(certs, regenerated_certs);
+        let (_, cert_subject_alt_names) = parse_cert_subject(&regenerated_certs[0]).unwrap();
+        assert_eq!(cert_subject_alt_names, vec!["d7y"]);
+    }
+
+    #[test]
+    fn test_order_cert_chain() {
+        // Write the chain of the leaf, the intermediate and the root to a single PEM file.
+        let chain_pem = generate_cert_chain_pem();
+        let mut chain_file = NamedTempFile::new().unwrap();
+        chain_file.write_all(chain_pem.concat().as_bytes()).unwrap();
+
+        let certs = generate_cert_from_pem(&chain_file.path().to_path_buf()).unwrap();
+        assert_eq!(certs.len(), 3);
+        assert_eq!(order_cert_chain(certs.clone()).unwrap(), certs);
+
+        // The misordered chain is reordered from the leaf to the root.
+        let misordered_certs = vec![certs[2].clone(), certs[0].clone(), certs[1].clone()];
+        assert_eq!(order_cert_chain(misordered_certs).unwrap(), certs);
+
+        // The chain which can't be linked is returned as is.
+        let unlinked_certs = vec![certs[0].clone(), certs[2].clone()];
+        assert_eq!(
+            order_cert_chain(unlinked_certs.clone()).unwrap(),
+            unlinked_certs
+        );
+    }
+
+    /// generate_cert_chain_pem generates the PEM format certificates of the leaf, the
+    /// intermediate CA and the root CA.
+    fn generate_cert_chain_pem() -> Vec<String> {
+        let ca_params = |common_name: &str| {
+            let mut params = CertificateParams::new(Vec::new());
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            params.distinguished_name = rcgen::DistinguishedName::new();
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, common_name);
+            params
+        };
+
+        let root = Certificate::from_params(ca_params("Test Root CA")).unwrap();
+        let intermediate = Certificate::from_params(ca_params("Test Intermediate CA")).unwrap();
+
+        let mut leaf_params = CertificateParams::new(vec!["localhost".to_string()]);
+        leaf_params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "localhost");
+        let leaf = Certificate::from_params(leaf_params).unwrap();
+
+        vec![
+            leaf.serialize_pem_with_signer(&intermediate).unwrap(),
+            intermediate.serialize_pem_with_signer(&root).unwrap(),
+            root.serialize_pem().unwrap(),
+        ]
+    }
 }
@@ -23,7 +23,9 @@ use dragonfly_client_config::{
 };
 use dragonfly_client_core::error::{ErrorType, OrErr};
 use dragonfly_client_core::Result;
-use dragonfly_client_util::{net::Interface, shutdown};
+use dragonfly_client_metric::collect_upload_piece_traffic_metrics;
+use dragonfly_client_storage::server::quic::QUICServerStats;
+use dragonfly_client_util::{id_generator::IDGenerator, net::Interface, shutdown};
 use std::env;
 use std::sync::Arc;
 use std::time::Duration;
@@ -45,6 +47,13 @@ pub struct SchedulerAnnouncer {
     /// interface is the network interface.
     interface: Arc<Interface>,
 
+    /// id_generator is the id generator.
+    id_generator: Arc<IDGenerator>,
+
+    /// quic_server_stats is the statistics of the storage quic server, the traffic uploaded
+    /// to the peers over quic is drained on the announce interval.
+    quic_server_stats: Arc<QUICServerStats>,
+
     /// shutdown is used to shutdown the announcer.
     shutdown: shutdown::Shutdown,
 
@@ -55,11 +64,14 @@ pub struct SchedulerAnnouncer {
 /// SchedulerAnnouncer implements the scheduler announcer of the dfdaemon.
 impl SchedulerAnnouncer {
     /// new creates a new scheduler announcer.
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         config: Arc<Config>,
         host_id: String,
         scheduler_client: Arc<SchedulerClient>,
         interface: Arc<Interface>,
+        id_generator: Arc<IDGenerator>,
+        quic_server_stats: Arc<QUICServerStats>,
         shutdown: shutdown::Shutdown,
         shutdown_complete_tx: mpsc::UnboundedSender<()>,
     ) -> Result<Self> {
@@ -68,6 +80,8 @@ impl SchedulerAnnouncer {
             host_id,
             scheduler_client,
             interface,
+            id_generator,
+            quic_server_stats,
             shutdown,
             _shutdown_complete: shutdown_complete_tx,
         };
@@ -90,6 +104,8 @@ impl SchedulerAnnouncer {
         loop {
             tokio::select! {
                 _ = interval.tick() => {
+                    self.report_quic_peer_traffic();
+
                     let request = match self.make_announce_host_request(interval.period()).await {
                         Ok(request) => request,
                         Err(err) => {
@@ -117,6 +133,23 @@ impl SchedulerAnnouncer {
         }
     }
 
+    /// report_quic_peer_traffic drains the traffic uploaded to the peers by the storage quic
+    /// server since the last announce, and reports it as the upload traffic like the pieces
+    /// uploaded by the grpc server.
+    fn report_quic_peer_traffic(&self) {
+        for traffic in self.quic_server_stats.drain_peer_traffic() {
+            debug!(
+                "quic server uploaded {} bytes of task {} to peer {} as {:?} traffic",
+                traffic.bytes, traffic.task_id, traffic.remote_ip, traffic.traffic_type
+            );
+
+            collect_upload_piece_traffic_metrics(
+                self.id_generator.task_type(&traffic.task_id) as i32,
+                traffic.bytes,
+            );
+        }
+    }
+
     /// make_announce_host_request makes the announce host request.
     #[instrument(skip_all)]
     async fn make_announce_host_request(&self, interval: Duration) -> Result<AnnounceHostRequest> {
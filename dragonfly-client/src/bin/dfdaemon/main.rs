@@ -309,6 +309,7 @@ async fn main() -> Result<(), anyhow::Error> {
 
     // Initialize storage quic server.
     let mut storage_quic_server = QUICServer::new(
+        config.clone(),
         SocketAddr::new(
             config.storage.server.ip.unwrap(),
             config.storage.server.quic_port,
@@ -320,6 +321,20 @@ async fn main() -> Result<(), anyhow::Error> {
         shutdown_complete_tx.clone(),
     );
 
+    // Bind the storage quic server before the upload grpc server is initialized, so the port
+    // assigned by the OS is announced to the children if the configured port is zero.
+    let storage_quic_addr = storage_quic_server.bind().inspect_err(|err| {
+        error!("bind storage quic server failed: {}", err);
+    })?;
+    info!("storage quic server bound on {}", storage_quic_addr);
+
+    // Fail fast if the quic client can't offer any of the configured cipher suites, instead of
+    // failing every download from the parents.
+    dragonfly_client_storage::tls::crypto_provider(&config.download.quic.cipher_suites)
+        .inspect_err(|err| {
+            error!("invalid quic client cipher suites: {}", err);
+        })?;
+
     // Initialize proxy server.
     let proxy = Proxy::new(
         config.clone(),
@@ -334,6 +349,8 @@ async fn main() -> Result<(), anyhow::Error> {
         id_generator.host_id(),
         scheduler_client.clone(),
         interface.clone(),
+        id_generator.clone(),
+        storage_quic_server.stats(),
         shutdown.clone(),
         shutdown_complete_tx.clone(),
     )
@@ -342,9 +359,16 @@ async fn main() -> Result<(), anyhow::Error> {
         error!("initialize scheduler announcer failed: {}", err);
     })?;
 
-    // Initialize upload grpc server.
+    // Initialize upload grpc server, it announces the bound port of the storage quic server.
+    let upload_config = if config.storage.server.quic_port == 0 {
+        let mut upload_config = (*config).clone();
+        upload_config.storage.server.quic_port = storage_quic_addr.port();
+        Arc::new(upload_config)
+    } else {
+        config.clone()
+    };
     let mut dfdaemon_upload_grpc = DfdaemonUploadServer::new(
-        config.clone(),
+        upload_config,
         SocketAddr::new(config.upload.server.ip.unwrap(), config.upload.server.port),
         task.clone(),
         persistent_cache_task.clone(),
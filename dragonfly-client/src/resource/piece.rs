@@ -22,8 +22,8 @@ use dragonfly_client_config::dfdaemon::Config;
 use dragonfly_client_core::{error::BackendError, Error, Result};
 use dragonfly_client_metric::{
     collect_backend_request_failure_metrics, collect_backend_request_finished_metrics,
-    collect_backend_request_started_metrics, collect_download_piece_traffic_metrics,
-    collect_upload_piece_traffic_metrics,
+    collect_backend_request_started_metrics, collect_download_piece_protocol_metrics,
+    collect_download_piece_traffic_metrics, collect_upload_piece_traffic_metrics,
 };
 use dragonfly_client_storage::{metadata, Storage};
 use dragonfly_client_util::id_generator::IDGenerator;
@@ -73,8 +73,9 @@ pub struct Piece {
     /// tcp_downloader is the TCP piece downloader.
     tcp_downloader: Arc<dyn piece_downloader::Downloader>,
 
-    /// quic_downloader is the QUIC piece downloader.
-    quic_downloader: Arc<dyn piece_downloader::Downloader>,
+    /// quic_downloader is the QUIC piece downloader, which falls back to the gRPC piece
+    /// downloader if the parent doesn't serve quic.
+    quic_downloader: piece_downloader::QUICFallbackDownloader,
 
     /// backend_factory is the backend factory.
     backend_factory: Arc<BackendFactory>,
@@ -101,15 +102,19 @@ impl Piece {
         upload_rate_limiter: Arc<RateLimiter>,
         prefetch_rate_limiter: Arc<RateLimiter>,
     ) -> Result<Self> {
+        let grpc_downloader =
+            piece_downloader::DownloaderFactory::new("grpc", config.clone())?.build();
         Ok(Self {
             config: config.clone(),
             id_generator,
             storage,
-            grpc_downloader: piece_downloader::DownloaderFactory::new("grpc", config.clone())?
-                .build(),
+            grpc_downloader: grpc_downloader.clone(),
             tcp_downloader: piece_downloader::DownloaderFactory::new("tcp", config.clone())?
                 .build(),
-            quic_downloader: piece_downloader::DownloaderFactory::new("quic", config)?.build(),
+            quic_downloader: piece_downloader::QUICFallbackDownloader::new(
+                config,
+                grpc_downloader,
+            )?,
             backend_factory,
             download_rate_limiter,
             upload_rate_limiter,
@@ -390,7 +395,7 @@ impl Piece {
 
     /// download_from_parent downloads a single piece from a parent.
     #[allow(clippy::too_many_arguments)]
-    #[instrument(skip_all, fields(piece_id))]
+    #[instrument(skip_all, fields(piece_id, protocol))]
     pub async fn download_from_parent(
         &self,
         piece_id: &str,
@@ -426,26 +431,33 @@ impl Piece {
             self.download_rate_limiter.acquire(length as usize).await;
         }
 
-        let (mut reader, offset, digest) = match (
+        let (mut reader, offset, digest, protocol) = match (
             self.config.download.protocol.as_str(),
             parent.download_ip,
             parent.download_tcp_port,
             parent.download_quic_port,
         ) {
             ("tcp", Some(ip), Some(port), _) => {
-                self.tcp_downloader
+                let (reader, offset, digest) = self
+                    .tcp_downloader
                     .download_piece(
                         format!("{}:{}", ip, port).as_str(),
                         number,
                         host_id,
                         task_id,
                     )
-                    .await?
+                    .await?;
+                (reader, offset, digest, "tcp")
             }
             ("quic", Some(ip), _, Some(port)) => {
+                let grpc_addr = parent
+                    .host
+                    .as_ref()
+                    .map(|host| format!("{}:{}", host.ip, host.port));
                 self.quic_downloader
                     .download_piece(
                         format!("{}:{}", ip, port).as_str(),
+                        grpc_addr.as_deref(),
                         number,
                         host_id,
                         task_id,
@@ -463,7 +475,8 @@ impl Piece {
                     Error::InvalidPeer(parent.id.clone())
                 })?;
 
-                self.grpc_downloader
+                let (reader, offset, digest) = self
+                    .grpc_downloader
                     .download_piece(
                         format!("{}:{}", host.ip, host.port).as_str(),
                         number,
@@ -476,10 +489,15 @@ impl Piece {
                         if let Some(err) = self.storage.download_piece_failed(piece_id).err() {
                             error!("set piece metadata failed: {}", err)
                         };
-                    })?
+                    })?;
+                (reader, offset, digest, "grpc")
             }
         };
 
+        // Record the protocol serving the piece.
+        Span::current().record("protocol", protocol);
+        collect_download_piece_protocol_metrics(protocol);
+
         // Record the finish of downloading piece.
         match self
             .storage
@@ -778,7 +796,7 @@ impl Piece {
 
     /// download_persistent_cache_from_parent downloads a persistent cache piece from a parent.
     #[allow(clippy::too_many_arguments)]
-    #[instrument(skip_all, fields(piece_id))]
+    #[instrument(skip_all, fields(piece_id, protocol))]
     pub async fn download_persistent_cache_from_parent(
         &self,
         piece_id: &str,
@@ -814,28 +832,33 @@ impl Piece {
             return Ok(piece);
         }
 
-        let (mut reader, offset, digest) = match (
+        let (mut reader, offset, digest, protocol) = match (
             self.config.download.protocol.as_str(),
             parent.download_ip,
             parent.download_tcp_port,
             parent.download_quic_port,
         ) {
             ("tcp", Some(ip), Some(port), _) => {
-                self.tcp_downloader
+                let (reader, offset, digest) = self
+                    .tcp_downloader
                     .download_persistent_cache_piece(
                         format!("{}:{}", ip, port).as_str(),
                         number,
                         host_id,
                         task_id,
                     )
-                    .await?
+                    .await?;
+                (reader, offset, digest, "tcp")
             }
             ("quic", Some(ip), _, Some(port)) => {
-                let quic_downloader =
-                    piece_downloader::DownloaderFactory::new("quic", self.config.clone())?.build();
-                quic_downloader
+                let grpc_addr = parent
+                    .host
+                    .as_ref()
+                    .map(|host| format!("{}:{}", host.ip, host.port));
+                self.quic_downloader
                     .download_persistent_cache_piece(
                         format!("{}:{}", ip, port).as_str(),
+                        grpc_addr.as_deref(),
                         number,
                         host_id,
                         task_id,
@@ -857,7 +880,8 @@ impl Piece {
                     Error::InvalidPeer(parent.id.clone())
                 })?;
 
-                self.grpc_downloader
+                let (reader, offset, digest) = self
+                    .grpc_downloader
                     .download_persistent_cache_piece(
                         format!("{}:{}", host.ip, host.port).as_str(),
                         number,
@@ -874,10 +898,15 @@ impl Piece {
                         {
                             error!("set persistent cache piece metadata failed: {}", err)
                         };
-                    })?
+                    })?;
+                (reader, offset, digest, "grpc")
             }
         };
 
+        // Record the protocol serving the piece.
+        Span::current().record("protocol", protocol);
+        collect_download_piece_protocol_metrics(protocol);
+
         // Record the finish of downloading piece.
         match self
             .storage
@@ -18,13 +18,18 @@ use crate::grpc::dfdaemon_upload::DfdaemonUploadClient;
 use dragonfly_api::dfdaemon::v2::{DownloadPersistentCachePieceRequest, DownloadPieceRequest};
 use dragonfly_client_config::dfdaemon::Config;
 use dragonfly_client_core::{Error, Result};
-use dragonfly_client_storage::{client::quic::QUICClient, client::tcp::TCPClient, metadata};
+use dragonfly_client_storage::{
+    client::quic::{is_quic_unavailable, QUICClient, QUICEndpoint},
+    client::tcp::TCPClient,
+    metadata,
+};
 use dragonfly_client_util::pool::{Builder as PoolBuilder, Entry, Factory, Pool};
+use std::collections::HashMap;
 use std::io::Cursor;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::io::AsyncRead;
-use tracing::{error, instrument};
+use tracing::{error, instrument, warn};
 
 /// DEFAULT_DOWNLOADER_CAPACITY is the default capacity of the downloader to store the clients.
 const DEFAULT_DOWNLOADER_CAPACITY: usize = 2000;
@@ -82,7 +87,7 @@ impl DownloaderFactory {
                 config.clone(),
                 DEFAULT_DOWNLOADER_CAPACITY,
                 DEFAULT_DOWNLOADER_IDLE_TIMEOUT,
-            )),
+            )?),
             _ => {
                 error!("unsupported protocol: {}", protocol);
                 return Err(Error::InvalidParameter);
@@ -290,7 +295,8 @@ impl Downloader for GRPCDownloader {
 
 /// QUICDownloader is the downloader for downloading pieces by the QUIC protocol.
 /// It will reuse the quic clients to download pieces from the other peers by
-/// peer's address.
+/// peer's address, and the clients share a single endpoint. Each client keeps one
+/// connection to its peer, and the idle clients are evicted by the pool.
 pub struct QUICDownloader {
     /// client_pool is the pool of the quic clients.
     client_pool: Pool<String, QUICClient, QUICClientFactory>,
@@ -299,6 +305,9 @@ pub struct QUICDownloader {
 /// Factory for creating QUICClient instances.
 struct QUICClientFactory {
     config: Arc<Config>,
+
+    /// endpoint is the client endpoint shared by the quic clients.
+    endpoint: QUICEndpoint,
 }
 
 /// QUICClientFactory implements the Factory trait for creating QUICClient instances.
@@ -308,22 +317,28 @@ impl Factory<String, QUICClient> for QUICClientFactory {
 
     /// Creates a new QUICClient for the given address.
     async fn make_client(&self, addr: &String) -> Result<QUICClient> {
-        Ok(QUICClient::new(self.config.clone(), addr.clone()))
+        Ok(QUICClient::with_endpoint(
+            self.config.clone(),
+            self.endpoint.clone(),
+            addr.clone(),
+        ))
     }
 }
 
 /// QUICDownloader implements the downloader with the QUIC protocol.
 impl QUICDownloader {
-    /// new returns a new QUICDownloader.
-    pub fn new(config: Arc<Config>, capacity: usize, idle_timeout: Duration) -> Self {
-        Self {
+    /// new returns a new QUICDownloader. It fails if the TLS config of the quic clients is
+    /// invalid, such as the client certificate can't be loaded.
+    pub fn new(config: Arc<Config>, capacity: usize, idle_timeout: Duration) -> Result<Self> {
+        Ok(Self {
             client_pool: PoolBuilder::new(QUICClientFactory {
                 config: config.clone(),
+                endpoint: QUICEndpoint::new(config.clone())?,
             })
             .capacity(capacity)
             .idle_timeout(idle_timeout)
             .build(),
-        }
+        })
     }
 
     /// get_client_entry returns a client entry by the address.
@@ -394,6 +409,209 @@ impl Downloader for QUICDownloader {
     }
 }
 
+/// QUICFallbackDownloader is the downloader for downloading pieces by the QUIC protocol, and it
+/// falls back to the gRPC protocol if the parent doesn't serve quic, such as the handshake fails
+/// or times out. The parent without quic is remembered for the ttl, and its pieces are
+/// downloaded by the gRPC protocol without probing quic again within the ttl.
+pub struct QUICFallbackDownloader {
+    /// enable indicates whether to fall back to the gRPC protocol.
+    enable: bool,
+
+    /// ttl is the duration to remember the parent which doesn't serve quic.
+    ttl: Duration,
+
+    /// quic_downloader is the downloader for downloading pieces by the QUIC protocol.
+    quic_downloader: Arc<dyn Downloader>,
+
+    /// grpc_downloader is the downloader for downloading pieces by the gRPC protocol.
+    grpc_downloader: Arc<dyn Downloader>,
+
+    /// quic_unavailable_peers stores the quic addresses of the parents which don't serve quic
+    /// with the time they are found.
+    quic_unavailable_peers: Mutex<HashMap<String, Instant>>,
+}
+
+/// QUICFallbackDownloader implements the downloader with the QUIC protocol falling back to the
+/// gRPC protocol.
+impl QUICFallbackDownloader {
+    /// new returns a new QUICFallbackDownloader. The connect timeout of the quic clients is
+    /// limited by the connect timeout of the fallback, so the parent without quic is found
+    /// quickly.
+    pub fn new(config: Arc<Config>, grpc_downloader: Arc<dyn Downloader>) -> Result<Self> {
+        let mut quic_config = (*config).clone();
+        if config.download.quic.fallback.enable {
+            quic_config.download.quic.connect_timeout = config
+                .download
+                .quic
+                .connect_timeout
+                .min(config.download.quic.fallback.connect_timeout);
+        }
+
+        let quic_downloader = Arc::new(QUICDownloader::new(
+            Arc::new(quic_config),
+            DEFAULT_DOWNLOADER_CAPACITY,
+            DEFAULT_DOWNLOADER_IDLE_TIMEOUT,
+        )?);
+
+        Ok(Self::with_downloaders(
+            &config,
+            quic_downloader,
+            grpc_downloader,
+        ))
+    }
+
+    /// with_downloaders returns a new QUICFallbackDownloader with the given downloaders.
+    fn with_downloaders(
+        config: &Config,
+        quic_downloader: Arc<dyn Downloader>,
+        grpc_downloader: Arc<dyn Downloader>,
+    ) -> Self {
+        Self {
+            enable: config.download.quic.fallback.enable,
+            ttl: config.download.quic.fallback.ttl,
+            quic_downloader,
+            grpc_downloader,
+            quic_unavailable_peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// download_piece downloads a piece from the parent by the QUIC protocol, and falls back to
+    /// the gRPC address of the parent if the parent doesn't serve quic. It returns the protocol
+    /// serving the piece along with the piece.
+    #[instrument(skip_all)]
+    pub async fn download_piece(
+        &self,
+        quic_addr: &str,
+        grpc_addr: Option<&str>,
+        number: u32,
+        host_id: &str,
+        task_id: &str,
+    ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, u64, String, &'static str)> {
+        self.download(false, quic_addr, grpc_addr, number, host_id, task_id)
+            .await
+    }
+
+    /// download_persistent_cache_piece downloads a persistent cache piece from the parent by the
+    /// QUIC protocol, and falls back to the gRPC address of the parent if the parent doesn't
+    /// serve quic. It returns the protocol serving the piece along with the piece.
+    #[instrument(skip_all)]
+    pub async fn download_persistent_cache_piece(
+        &self,
+        quic_addr: &str,
+        grpc_addr: Option<&str>,
+        number: u32,
+        host_id: &str,
+        task_id: &str,
+    ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, u64, String, &'static str)> {
+        self.download(true, quic_addr, grpc_addr, number, host_id, task_id)
+            .await
+    }
+
+    /// download downloads the piece or the persistent cache piece with the fallback.
+    async fn download(
+        &self,
+        persistent_cache: bool,
+        quic_addr: &str,
+        grpc_addr: Option<&str>,
+        number: u32,
+        host_id: &str,
+        task_id: &str,
+    ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, u64, String, &'static str)> {
+        let grpc_addr = match grpc_addr {
+            Some(grpc_addr) if self.enable => grpc_addr,
+            _ => {
+                let (reader, offset, digest) = Self::download_by(
+                    self.quic_downloader.as_ref(),
+                    persistent_cache,
+                    quic_addr,
+                    number,
+                    host_id,
+                    task_id,
+                )
+                .await?;
+
+                return Ok((reader, offset, digest, "quic"));
+            }
+        };
+
+        if !self.is_quic_unavailable(quic_addr) {
+            match Self::download_by(
+                self.quic_downloader.as_ref(),
+                persistent_cache,
+                quic_addr,
+                number,
+                host_id,
+                task_id,
+            )
+            .await
+            {
+                Ok((reader, offset, digest)) => return Ok((reader, offset, digest, "quic")),
+                Err(err) if is_quic_unavailable(&err) => {
+                    warn!(
+                        "parent {} doesn't serve quic, fall back to grpc {}: {}",
+                        quic_addr, grpc_addr, err
+                    );
+                    self.set_quic_unavailable(quic_addr);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let (reader, offset, digest) = Self::download_by(
+            self.grpc_downloader.as_ref(),
+            persistent_cache,
+            grpc_addr,
+            number,
+            host_id,
+            task_id,
+        )
+        .await?;
+
+        Ok((reader, offset, digest, "grpc"))
+    }
+
+    /// download_by downloads the piece or the persistent cache piece by the downloader.
+    async fn download_by(
+        downloader: &dyn Downloader,
+        persistent_cache: bool,
+        addr: &str,
+        number: u32,
+        host_id: &str,
+        task_id: &str,
+    ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, u64, String)> {
+        if persistent_cache {
+            downloader
+                .download_persistent_cache_piece(addr, number, host_id, task_id)
+                .await
+        } else {
+            downloader
+                .download_piece(addr, number, host_id, task_id)
+                .await
+        }
+    }
+
+    /// is_quic_unavailable returns whether the parent is found not serving quic within the ttl.
+    fn is_quic_unavailable(&self, quic_addr: &str) -> bool {
+        let mut quic_unavailable_peers = self.quic_unavailable_peers.lock().unwrap();
+        match quic_unavailable_peers.get(quic_addr) {
+            Some(found_at) if found_at.elapsed() < self.ttl => true,
+            Some(_) => {
+                quic_unavailable_peers.remove(quic_addr);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// set_quic_unavailable remembers the parent not serving quic, and the expired parents are
+    /// removed to bound the size of the cache.
+    fn set_quic_unavailable(&self, quic_addr: &str) {
+        let mut quic_unavailable_peers = self.quic_unavailable_peers.lock().unwrap();
+        quic_unavailable_peers.retain(|_, found_at| found_at.elapsed() < self.ttl);
+        quic_unavailable_peers.insert(quic_addr.to_string(), Instant::now());
+    }
+}
+
 /// TCPDownloader is the downloader for downloading pieces by the TCP protocol.
 /// It will reuse the tcp clients to download pieces from the other peers by
 /// peer's address.
@@ -499,3 +717,126 @@ impl Downloader for TCPDownloader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::AsyncReadExt;
+
+    /// MockDownloader is the downloader serving the protocol name as the piece content, and
+    /// the handshake fails for the unavailable addresses.
+    struct MockDownloader {
+        protocol: &'static str,
+        unavailable_addrs: Vec<&'static str>,
+        requests: AtomicUsize,
+    }
+
+    impl MockDownloader {
+        fn new(protocol: &'static str, unavailable_addrs: Vec<&'static str>) -> Arc<Self> {
+            Arc::new(Self {
+                protocol,
+                unavailable_addrs,
+                requests: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[tonic::async_trait]
+    impl Downloader for MockDownloader {
+        async fn download_piece(
+            &self,
+            addr: &str,
+            _number: u32,
+            _host_id: &str,
+            _task_id: &str,
+        ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, u64, String)> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            if self.unavailable_addrs.contains(&addr) {
+                return Err(Error::QUICHandshakeFailed(addr.to_string()));
+            }
+
+            Ok((
+                Box::new(Cursor::new(self.protocol.as_bytes().to_vec())),
+                0,
+                String::new(),
+            ))
+        }
+
+        async fn download_persistent_cache_piece(
+            &self,
+            addr: &str,
+            number: u32,
+            host_id: &str,
+            task_id: &str,
+        ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, u64, String)> {
+            self.download_piece(addr, number, host_id, task_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quic_fallback_downloader_grpc_only_peer() {
+        let config = Config::default();
+        let quic_downloader = MockDownloader::new("quic", vec!["127.0.0.1:4001"]);
+        let grpc_downloader = MockDownloader::new("grpc", vec![]);
+        let downloader = QUICFallbackDownloader::with_downloaders(
+            &config,
+            quic_downloader.clone(),
+            grpc_downloader.clone(),
+        );
+
+        for _ in 0..2 {
+            let (mut reader, _, _, protocol) = downloader
+                .download_piece("127.0.0.1:4001", Some("127.0.0.1:4000"), 0, "host", "task")
+                .await
+                .unwrap();
+            assert_eq!(protocol, "grpc");
+
+            let mut content = String::new();
+            reader.read_to_string(&mut content).await.unwrap();
+            assert_eq!(content, "grpc");
+        }
+
+        // The parent without quic is remembered, so quic is only probed once.
+        assert_eq!(quic_downloader.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(grpc_downloader.requests.load(Ordering::SeqCst), 2);
+
+        // The error is returned if the parent has no gRPC address to fall back to.
+        assert!(matches!(
+            downloader
+                .download_persistent_cache_piece("127.0.0.1:4001", None, 0, "host", "task")
+                .await,
+            Err(Error::QUICHandshakeFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quic_fallback_downloader_quic_peer() {
+        let config = Config::default();
+        let quic_downloader = MockDownloader::new("quic", vec![]);
+        let grpc_downloader = MockDownloader::new("grpc", vec![]);
+        let downloader = QUICFallbackDownloader::with_downloaders(
+            &config,
+            quic_downloader.clone(),
+            grpc_downloader.clone(),
+        );
+
+        let (mut reader, _, _, protocol) = downloader
+            .download_persistent_cache_piece(
+                "127.0.0.1:4001",
+                Some("127.0.0.1:4000"),
+                0,
+                "host",
+                "task",
+            )
+            .await
+            .unwrap();
+        assert_eq!(protocol, "quic");
+
+        let mut content = String::new();
+        reader.read_to_string(&mut content).await.unwrap();
+        assert_eq!(content, "quic");
+        assert_eq!(quic_downloader.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(grpc_downloader.requests.load(Ordering::SeqCst), 0);
+    }
+}